@@ -0,0 +1,88 @@
+// Compares a full parse of a large program with incremental updates after small edits.
+// Run with `cargo bench --bench incremental`.
+
+use std::time::{Duration, Instant};
+
+use oxide::{
+    incremental::{IncrementalParser, TextEdit},
+    parser::Parser,
+};
+
+const STMTS: usize = 20_000;
+const ROUNDS: u32 = 20;
+
+fn source() -> String {
+    let mut text = String::from("init\nint i, total;\nbool done;\ntotal = 0;\n");
+    for n in 0..STMTS / 4 {
+        text.push_str(&format!("i = {} * 2 + total % 7;\n", n));
+        text.push_str("done = i > 100 && !done || total == 3;\n");
+        text.push_str("while (i < 10) do\n    i = i + 1;\nendwhile;\n");
+        text.push_str(&format!("print(\"step\", {}); // progress\n", n));
+    }
+    text.push_str("halt\n");
+    text
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    let text = source();
+    let middle = text[text.len() / 2..].find("i = ").unwrap() + text.len() / 2;
+    println!("{} bytes, {} lines", text.len(), text.lines().count());
+
+    let full = time(|| {
+        Parser::new(&text).parse_program().unwrap();
+    });
+    println!("full parse:               {:?}", full);
+
+    let mut parser = IncrementalParser::new(text.clone());
+    let mut reused = true;
+    // Retypes one digit of a literal in the middle of the file.
+    let mut digit = 0;
+    let same_line = time(|| {
+        digit = (digit + 1) % 10;
+        reused &= parser.edit(TextEdit {
+            range: middle + 4..middle + 5,
+            text: digit.to_string(),
+        });
+    });
+    println!("edit within a line:       {:?}", same_line);
+
+    // Adds and removes a line, moving every statement below it.
+    let mut inserted = false;
+    let new_line = time(|| {
+        let edit = match inserted {
+            false => TextEdit {
+                range: middle..middle,
+                text: "total = 1;\n".to_string(),
+            },
+            true => TextEdit {
+                range: middle..middle + "total = 1;\n".len(),
+                text: String::new(),
+            },
+        };
+        inserted = !inserted;
+        reused &= parser.edit(edit);
+    });
+    println!("insert or delete a line:  {:?}", new_line);
+
+    assert!(reused, "an edit fell back to a full parse");
+    // Neither depends on the size of the file, so both stay well under a millisecond.
+    for (edit, took) in [
+        ("an edit within a line", same_line),
+        ("a new line", new_line),
+    ] {
+        assert!(took < Duration::from_millis(1), "{} took {:?}", edit, took);
+    }
+    let expected = Parser::new(parser.text()).parse_program().unwrap();
+    assert_eq!(
+        format!("{:?}", parser.program().unwrap()),
+        format!("{:?}", expected)
+    );
+}
@@ -0,0 +1,9 @@
+use std::io;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(e) = oxide::lsp::run(stdin.lock(), stdout.lock()) {
+        panic!("oxide-lsp: {}", e);
+    }
+}
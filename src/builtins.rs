@@ -0,0 +1,573 @@
+use crate::{format::token_text, value::Value, Token};
+
+// The builtin function registry. Builtins are called by name, like `len(s)`, and looked up
+// here at every call site. A name can have several overloads, `abs(int)` and `abs(float)`,
+// each taking a fixed list of parameter types and returning one type, so the analyzer can
+// check a call and know its type wherever the types of its arguments are known. An int
+// argument widens to a float parameter, as it does in arithmetic.
+
+// The program's input and output, for the builtins that use them.
+pub trait Io {
+    // The next line of input without its line break, None at the end of the input.
+    fn read_line(&mut self) -> Result<Option<String>, String>;
+    fn write(&mut self, text: &str) -> Result<(), String>;
+}
+
+pub enum Function {
+    // Only depends on its arguments, so a call with constant arguments can be folded.
+    Pure(fn(&[Value]) -> Result<Value, String>),
+    // Reads or writes the program's input or output, so it only runs in the interpreter.
+    Io(fn(&mut dyn Io, &[Value]) -> Result<Value, String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Math,
+    String,
+    Conversion,
+    Io,
+}
+
+impl Category {
+    pub const ALL: [Category; 4] = [
+        Category::Math,
+        Category::String,
+        Category::Conversion,
+        Category::Io,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Math => "math",
+            Category::String => "string",
+            Category::Conversion => "conversion",
+            Category::Io => "I/O",
+        }
+    }
+}
+
+pub struct Builtin {
+    pub name: &'static str,
+    pub category: Category,
+    pub params: &'static [Token],
+    pub returns: Token,
+    pub doc: &'static str,
+    pub function: Function,
+}
+
+impl Builtin {
+    // The signature as it is documented, `abs(int) -> int`.
+    pub fn signature(&self) -> String {
+        let params: Vec<String> = self.params.iter().map(token_text).collect();
+        format!(
+            "{}({}) -> {}",
+            self.name,
+            params.join(", "),
+            token_text(&self.returns)
+        )
+    }
+}
+
+const fn builtin(
+    name: &'static str,
+    category: Category,
+    params: &'static [Token],
+    returns: Token,
+    doc: &'static str,
+    function: Function,
+) -> Builtin {
+    Builtin {
+        name,
+        category,
+        params,
+        returns,
+        doc,
+        function,
+    }
+}
+
+// Every overload of a name takes the same number of arguments.
+pub static BUILTINS: &[Builtin] = &[
+    builtin(
+        "abs",
+        Category::Math,
+        &[Token::INT],
+        Token::INT,
+        "Absolute value.",
+        Function::Pure(abs),
+    ),
+    builtin(
+        "abs",
+        Category::Math,
+        &[Token::FLOAT],
+        Token::FLOAT,
+        "Absolute value.",
+        Function::Pure(abs),
+    ),
+    builtin(
+        "min",
+        Category::Math,
+        &[Token::INT, Token::INT],
+        Token::INT,
+        "The smaller argument.",
+        Function::Pure(min),
+    ),
+    builtin(
+        "min",
+        Category::Math,
+        &[Token::FLOAT, Token::FLOAT],
+        Token::FLOAT,
+        "The smaller argument.",
+        Function::Pure(min),
+    ),
+    builtin(
+        "max",
+        Category::Math,
+        &[Token::INT, Token::INT],
+        Token::INT,
+        "The larger argument.",
+        Function::Pure(max),
+    ),
+    builtin(
+        "max",
+        Category::Math,
+        &[Token::FLOAT, Token::FLOAT],
+        Token::FLOAT,
+        "The larger argument.",
+        Function::Pure(max),
+    ),
+    builtin(
+        "sqrt",
+        Category::Math,
+        &[Token::FLOAT],
+        Token::FLOAT,
+        "Square root, of a number that isn't negative.",
+        Function::Pure(sqrt),
+    ),
+    builtin(
+        "pow",
+        Category::Math,
+        &[Token::INT, Token::INT],
+        Token::INT,
+        "The first argument raised to the second, like **.",
+        Function::Pure(pow),
+    ),
+    builtin(
+        "pow",
+        Category::Math,
+        &[Token::FLOAT, Token::FLOAT],
+        Token::FLOAT,
+        "The first argument raised to the second, like **.",
+        Function::Pure(pow),
+    ),
+    builtin(
+        "floor",
+        Category::Math,
+        &[Token::FLOAT],
+        Token::FLOAT,
+        "Rounds down to a whole number.",
+        Function::Pure(floor),
+    ),
+    builtin(
+        "ceil",
+        Category::Math,
+        &[Token::FLOAT],
+        Token::FLOAT,
+        "Rounds up to a whole number.",
+        Function::Pure(ceil),
+    ),
+    builtin(
+        "round",
+        Category::Math,
+        &[Token::FLOAT],
+        Token::FLOAT,
+        "Rounds to the nearest whole number, halves away from zero.",
+        Function::Pure(round),
+    ),
+    builtin(
+        "len",
+        Category::String,
+        &[Token::STRING],
+        Token::INT,
+        "Number of characters.",
+        Function::Pure(len),
+    ),
+    builtin(
+        "substr",
+        Category::String,
+        &[Token::STRING, Token::INT, Token::INT],
+        Token::STRING,
+        "substr(s, i, n) is the n characters of s from position i, counted from 0.",
+        Function::Pure(substr),
+    ),
+    builtin(
+        "upper",
+        Category::String,
+        &[Token::STRING],
+        Token::STRING,
+        "Upper case.",
+        Function::Pure(upper),
+    ),
+    builtin(
+        "lower",
+        Category::String,
+        &[Token::STRING],
+        Token::STRING,
+        "Lower case.",
+        Function::Pure(lower),
+    ),
+    builtin(
+        "trim",
+        Category::String,
+        &[Token::STRING],
+        Token::STRING,
+        "Without leading and trailing whitespace.",
+        Function::Pure(trim),
+    ),
+    builtin(
+        "contains",
+        Category::String,
+        &[Token::STRING, Token::STRING],
+        Token::BOOL,
+        "contains(s, t) is whether t occurs in s.",
+        Function::Pure(contains),
+    ),
+    builtin(
+        "replace",
+        Category::String,
+        &[Token::STRING, Token::STRING, Token::STRING],
+        Token::STRING,
+        "replace(s, from, to) replaces every from in s with to.",
+        Function::Pure(replace),
+    ),
+    builtin(
+        "split",
+        Category::String,
+        &[Token::STRING, Token::STRING, Token::INT],
+        Token::STRING,
+        "split(s, sep, i) is field i of s split at every sep.",
+        Function::Pure(split),
+    ),
+    builtin(
+        "ord",
+        Category::Conversion,
+        &[Token::STRING],
+        Token::INT,
+        "The code point of a single character.",
+        Function::Pure(ord),
+    ),
+    builtin(
+        "chr",
+        Category::Conversion,
+        &[Token::INT],
+        Token::STRING,
+        "The character with a code point.",
+        Function::Pure(chr),
+    ),
+    builtin(
+        "readline",
+        Category::Io,
+        &[],
+        Token::STRING,
+        "The next line of input, see read.",
+        Function::Io(readline),
+    ),
+    builtin(
+        "write",
+        Category::Io,
+        &[Token::STRING],
+        Token::INT,
+        "Prints without a line break, returns the number of characters.",
+        Function::Io(write),
+    ),
+];
+
+// Whether an argument of type `arg` can be passed for `param`.
+fn accepts(param: &Token, arg: &Token) -> bool {
+    param == arg || (param == &Token::FLOAT && arg == &Token::INT)
+}
+
+// Picks the overload of `name` for arguments of the given types, None being a type that
+// isn't known before running. An exact match wins over one that widens an int. Returns
+// None when unknown argument types leave more than one overload possible.
+pub fn resolve(
+    name: &str,
+    arg_types: &[Option<Token>],
+) -> Result<Option<&'static Builtin>, String> {
+    let overloads: Vec<&Builtin> = BUILTINS
+        .iter()
+        .filter(|builtin| builtin.name == name)
+        .collect();
+    if overloads.is_empty() {
+        return Err(format!("Unknown function '{}'.", name));
+    }
+    let signatures: Vec<&[Token]> = overloads.iter().map(|builtin| builtin.params).collect();
+    Ok(select(name, &signatures, arg_types)?.map(|index| overloads[index]))
+}
+
+// The overload resolution behind resolve, over the parameter lists of a function's
+// overloads, which all have the same length. Also checks calls of native functions,
+// which have a single signature.
+pub(crate) fn select(
+    name: &str,
+    signatures: &[&[Token]],
+    arg_types: &[Option<Token>],
+) -> Result<Option<usize>, String> {
+    let arity = signatures[0].len();
+    if arg_types.len() != arity {
+        return Err(format!(
+            "'{}' takes {} argument{}, got {}.",
+            name,
+            arity,
+            if arity == 1 { "" } else { "s" },
+            arg_types.len()
+        ));
+    }
+    let matches = |params: &[Token], exact: bool| {
+        params.iter().zip(arg_types).all(|(param, arg)| match arg {
+            None => true,
+            Some(arg) if exact => param == arg,
+            Some(arg) => accepts(param, arg),
+        })
+    };
+    let candidates: Vec<usize> = (0..signatures.len())
+        .filter(|index| matches(signatures[*index], false))
+        .collect();
+    match candidates.as_slice() {
+        [] => Err(illegal_arguments(name, signatures, arg_types)),
+        [index] => Ok(Some(*index)),
+        _ if arg_types.iter().all(Option::is_some) => Ok(candidates
+            .iter()
+            .copied()
+            .find(|index| matches(signatures[*index], true))
+            .or(Some(candidates[0]))),
+        _ => Ok(None),
+    }
+}
+
+// Reports the first argument no overload takes, or all of them when only their
+// combination is wrong.
+fn illegal_arguments(name: &str, signatures: &[&[Token]], arg_types: &[Option<Token>]) -> String {
+    for (index, arg) in arg_types.iter().enumerate() {
+        let Some(arg) = arg else {
+            continue;
+        };
+        let mut expected: Vec<String> = vec![];
+        for params in signatures {
+            let param = token_text(&params[index]);
+            if !expected.contains(&param) {
+                expected.push(param);
+            }
+        }
+        if !signatures.iter().any(|params| accepts(&params[index], arg)) {
+            return format!(
+                "Illegal argument {} for '{}': {}, expected {}.",
+                index + 1,
+                name,
+                token_text(arg),
+                expected.join(" or ")
+            );
+        }
+    }
+    let args: Vec<String> = arg_types
+        .iter()
+        .map(|arg| arg.as_ref().map_or("_".to_string(), token_text))
+        .collect();
+    format!("Illegal arguments for '{}': {}.", name, args.join(", "))
+}
+
+// Widens the int arguments passed for float parameters.
+pub(crate) fn widen(args: &mut [Value], params: &[Token]) {
+    for (arg, param) in args.iter_mut().zip(params) {
+        if let (Value::Int(i), Token::FLOAT) = (&arg, param) {
+            *arg = Value::Float(*i as f64);
+        }
+    }
+}
+
+// Calls a builtin. Io is None outside the interpreter, where the I/O builtins can't run.
+pub fn call(name: &str, mut args: Vec<Value>, io: Option<&mut dyn Io>) -> Result<Value, String> {
+    let arg_types: Vec<Option<Token>> = args.iter().map(|arg| Some(arg.type_specifier())).collect();
+    let builtin = resolve(name, &arg_types)?.expect("argument types are known");
+    widen(&mut args, builtin.params);
+    match (&builtin.function, io) {
+        (Function::Pure(function), _) => function(&args),
+        (Function::Io(function), Some(io)) => function(io, &args),
+        (Function::Io(_), None) => Err(format!("'{}' is not a constant.", name)),
+    }
+}
+
+fn string(value: &Value) -> &str {
+    match value {
+        Value::Str(s) => s,
+        _ => unreachable!("argument types are checked by call"),
+    }
+}
+
+fn int(value: &Value) -> i32 {
+    match value {
+        Value::Int(i) => *i,
+        _ => unreachable!("argument types are checked by call"),
+    }
+}
+
+fn float(value: &Value) -> f64 {
+    match value {
+        Value::Float(f) => *f,
+        _ => unreachable!("argument types are checked by call"),
+    }
+}
+
+fn abs(args: &[Value]) -> Result<Value, String> {
+    match &args[0] {
+        Value::Int(i) => i
+            .checked_abs()
+            .map(Value::Int)
+            .ok_or_else(|| "Integer overflow in 'abs'.".to_string()),
+        value => Ok(Value::Float(float(value).abs())),
+    }
+}
+
+fn min(args: &[Value]) -> Result<Value, String> {
+    Ok(match (&args[0], &args[1]) {
+        (Value::Int(a), Value::Int(b)) => Value::Int(*a.min(b)),
+        (a, b) => Value::Float(float(a).min(float(b))),
+    })
+}
+
+fn max(args: &[Value]) -> Result<Value, String> {
+    Ok(match (&args[0], &args[1]) {
+        (Value::Int(a), Value::Int(b)) => Value::Int(*a.max(b)),
+        (a, b) => Value::Float(float(a).max(float(b))),
+    })
+}
+
+fn sqrt(args: &[Value]) -> Result<Value, String> {
+    match float(&args[0]) {
+        f if f < 0.0 => Err(format!("Square root of the negative number {:?}.", f)),
+        f => Ok(Value::Float(f.sqrt())),
+    }
+}
+
+fn pow(args: &[Value]) -> Result<Value, String> {
+    crate::value::binary_op(&Token::POW, args[0].clone(), args[1].clone())
+}
+
+fn floor(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Float(float(&args[0]).floor()))
+}
+
+fn ceil(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Float(float(&args[0]).ceil()))
+}
+
+fn round(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Float(float(&args[0]).round()))
+}
+
+// Lengths and positions count characters, not bytes.
+fn len(args: &[Value]) -> Result<Value, String> {
+    let count = string(&args[0]).chars().count();
+    i32::try_from(count)
+        .map(Value::Int)
+        .map_err(|_| "Integer overflow in 'len'.".to_string())
+}
+
+// The `n` characters starting at position `i`, counted from 0.
+fn substr(args: &[Value]) -> Result<Value, String> {
+    let s = string(&args[0]);
+    let (i, n) = (int(&args[1]), int(&args[2]));
+    let count = s.chars().count();
+    match (usize::try_from(i), usize::try_from(n)) {
+        (Ok(start), Ok(length)) if start + length <= count => {
+            Ok(Value::Str(s.chars().skip(start).take(length).collect()))
+        }
+        _ => Err(format!(
+            "substr({}, {}) is out of range for a string of length {}.",
+            i, n, count
+        )),
+    }
+}
+
+fn upper(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Str(string(&args[0]).to_uppercase()))
+}
+
+fn lower(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Str(string(&args[0]).to_lowercase()))
+}
+
+fn trim(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Str(string(&args[0]).trim().to_string()))
+}
+
+fn contains(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Bool(string(&args[0]).contains(string(&args[1]))))
+}
+
+fn replace(args: &[Value]) -> Result<Value, String> {
+    let (s, from, to) = (string(&args[0]), string(&args[1]), string(&args[2]));
+    if from.is_empty() {
+        return Err("replace pattern can't be empty.".to_string());
+    }
+    Ok(Value::Str(s.replace(from, to)))
+}
+
+// The length in characters of what a builtin call returns, for the builtins whose result
+// can be much longer than their arguments: replace(s, "a", t) is as long as s with every
+// "a" taking the length of t.
+pub(crate) fn result_length(name: &str, args: &[Value]) -> Option<usize> {
+    match (name, args) {
+        ("replace", [Value::Str(s), Value::Str(from), Value::Str(to)]) if !from.is_empty() => {
+            let count = s.matches(from.as_str()).count();
+            let (from_length, to_length) = (from.chars().count(), to.chars().count());
+            Some(s.chars().count() - count * from_length + count * to_length)
+        }
+        _ => None,
+    }
+}
+
+// There are no lists, so split returns a single field: split("a,b", ",", 1) is "b".
+fn split(args: &[Value]) -> Result<Value, String> {
+    let (s, separator, i) = (string(&args[0]), string(&args[1]), int(&args[2]));
+    if separator.is_empty() {
+        return Err("split separator can't be empty.".to_string());
+    }
+    let count = s.split(separator).count();
+    usize::try_from(i)
+        .ok()
+        .and_then(|index| s.split(separator).nth(index))
+        .map(|field| Value::Str(field.to_string()))
+        .ok_or_else(|| format!("split field {} is out of range, there are {}.", i, count))
+}
+
+fn ord(args: &[Value]) -> Result<Value, String> {
+    let s = string(&args[0]);
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(Value::Int(c as i32)),
+        _ => Err(format!("ord takes a single character, got \"{}\".", s)),
+    }
+}
+
+fn chr(args: &[Value]) -> Result<Value, String> {
+    let i = int(&args[0]);
+    u32::try_from(i)
+        .ok()
+        .and_then(char::from_u32)
+        .map(|c| Value::Str(c.to_string()))
+        .ok_or_else(|| format!("{} is not a character code point.", i))
+}
+
+fn readline(io: &mut dyn Io, _: &[Value]) -> Result<Value, String> {
+    match io.read_line()? {
+        Some(line) => Ok(Value::Str(line)),
+        None => Err("Unexpected end of input in 'readline'.".to_string()),
+    }
+}
+
+fn write(io: &mut dyn Io, args: &[Value]) -> Result<Value, String> {
+    let text = string(&args[0]);
+    io.write(text)?;
+    len(args)
+}
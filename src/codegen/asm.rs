@@ -0,0 +1,522 @@
+use super::{check_standalone, emit_stmts, variables, CodegenError};
+use crate::{
+    builtins::{self, BUILTINS},
+    format::token_text,
+    operators,
+    parser::{
+        AssignStmt, BinaryExpr, CallExpr, CastExpr, CtrlStmt, Expr, IfStmt, LoopStmt, PostfixExpr,
+        PrefixExpr, PrintStmt, Program, Stmt, StmtList,
+    },
+    semantic::{ident_name, SymbolTable},
+    types::{binary_type, unary_type},
+    value::Value,
+    Token,
+};
+use std::collections::HashMap;
+
+const RUNTIME: &str = include_str!("runtime.s");
+
+// Registers the builtins' arguments are passed in. Only builtins taking strings have
+// more than two parameters.
+const ARG_REGISTERS: &[&str] = &["%edi", "%esi"];
+
+// Compiles a program with ints and bools to x86-64 assembly for the GNU assembler, to be
+// linked against libc: `cc -o prog prog.s`. Every variable and every value waiting for
+// the rest of an expression has an 8 byte stack slot below %rbp, and expressions are
+// evaluated into %eax. %rsp stays put after the prologue, so calls into libc find the
+// stack aligned. String literals can be printed, other strings, floats and input are
+// reported as CodegenErrors.
+pub struct Generator<'a> {
+    symbols: &'a SymbolTable,
+    code: String,
+    slots: HashMap<String, usize>,
+    // Slots in use for intermediate values, and the most used at once.
+    temps: usize,
+    max_temps: usize,
+    labels: usize,
+    strings: Vec<String>,
+    // The line of the statement being emitted, lines in it count from here.
+    base: u32,
+}
+
+// A value print has evaluated, in a slot, or a string literal.
+enum Printed {
+    Slot(usize, Token),
+    Literal(usize),
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(symbols: &'a SymbolTable) -> Self {
+        Self {
+            symbols,
+            code: String::new(),
+            slots: HashMap::new(),
+            temps: 0,
+            max_temps: 0,
+            labels: 0,
+            strings: vec![],
+            base: 0,
+        }
+    }
+
+    pub fn emit_program(mut self, program: &Program) -> Result<String, CodegenError> {
+        check_standalone(self.symbols)?;
+        for (name, _) in variables(program) {
+            let slot = self.slots.len();
+            self.slots.insert(name, slot);
+        }
+        emit_stmts(program, |line, ctrl_stmt| {
+            self.base = line;
+            self.emit_ctrl_stmt(ctrl_stmt)
+        })?;
+        let frame = (8 * (self.slots.len() + self.max_temps)).next_multiple_of(16);
+        let mut assembly = String::from(RUNTIME);
+        if !self.strings.is_empty() {
+            assembly.push_str("\n    .section .rodata\n");
+            for (n, s) in self.strings.iter().enumerate() {
+                assembly.push_str(&format!(
+                    ".Lstring{}:\n    .string {}\n",
+                    n,
+                    string_literal(s)
+                ));
+            }
+        }
+        assembly.push_str("\n    .text\n    .globl main\nmain:\n");
+        assembly.push_str("    pushq %rbp\n    movq %rsp, %rbp\n");
+        assembly.push_str(&format!("    subq ${}, %rsp\n", frame));
+        assembly.push_str(&self.code);
+        assembly.push_str("    xorl %eax, %eax\n    leave\n    ret\n");
+        assembly.push_str("\n    .section .note.GNU-stack,\"\",@progbits\n");
+        Ok(assembly)
+    }
+
+    fn line(&mut self, line: String) {
+        self.code.push_str("    ");
+        self.code.push_str(&line);
+        self.code.push('\n');
+    }
+
+    fn label(&mut self) -> usize {
+        self.labels += 1;
+        self.labels
+    }
+
+    fn place_label(&mut self, kind: &str, label: usize) {
+        self.code.push_str(&format!(".L{}{}:\n", kind, label));
+    }
+
+    // The stack slot of a variable, after the variables come the temporaries.
+    fn slot(&self, slot: usize) -> String {
+        format!("-{}(%rbp)", 8 * (slot + 1))
+    }
+
+    // Stores %eax in a new temporary slot.
+    fn push_temp(&mut self) -> usize {
+        let slot = self.slots.len() + self.temps;
+        self.temps += 1;
+        self.max_temps = self.max_temps.max(self.temps);
+        self.line(format!("movl %eax, {}", self.slot(slot)));
+        slot
+    }
+
+    fn pop_temp(&mut self) -> usize {
+        self.temps -= 1;
+        self.slots.len() + self.temps
+    }
+
+    fn emit_stmt_list(&mut self, stmt_list: &StmtList) -> Result<(), CodegenError> {
+        for stmt in &stmt_list.stmts {
+            // Variables were given their slots up front.
+            if let Stmt::Ctrl(ctrl_stmt) = stmt {
+                self.emit_ctrl_stmt(ctrl_stmt)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_ctrl_stmt(&mut self, ctrl_stmt: &CtrlStmt) -> Result<(), CodegenError> {
+        self.emit_stmt_line(ctrl_stmt.line());
+        match ctrl_stmt {
+            CtrlStmt::Assign(assign_stmt) => self.emit_assign_stmt(assign_stmt),
+            CtrlStmt::Print(print_stmt) => self.emit_print_stmt(print_stmt),
+            CtrlStmt::Read(read_stmt) => Err(no_input(read_stmt.line)),
+            CtrlStmt::If(if_stmt) => self.emit_if_stmt(if_stmt),
+            CtrlStmt::Loop(loop_stmt) => self.emit_loop_stmt(loop_stmt),
+        }
+    }
+
+    fn emit_stmt_line(&mut self, line: u32) {
+        self.line(format!("movl ${}, ox_stmt_line(%rip)", self.base + line));
+    }
+
+    fn emit_assign_stmt(&mut self, assign_stmt: &AssignStmt) -> Result<(), CodegenError> {
+        let name = ident_name(&assign_stmt.ident);
+        let line = assign_stmt.line;
+        let declared = self.variable_type(name, line)?;
+        let value_type = self.emit_expr(&assign_stmt.expr)?;
+        let value_type = match operators::compound_assignment(&assign_stmt.op) {
+            Some(op) => {
+                self.line("movl %eax, %ecx".to_string());
+                self.line(format!("movl {}, %eax", self.variable(name)));
+                self.emit_binary_op(op, &declared, &value_type, line)?
+            }
+            None => value_type,
+        };
+        if value_type != declared {
+            return Err(CodegenError::new(
+                line,
+                format!(
+                    "Cannot assign {} to '{}' declared as {:?}.",
+                    token_text(&value_type),
+                    name,
+                    declared
+                ),
+            ));
+        }
+        self.line(format!("movl %eax, {}", self.variable(name)));
+        Ok(())
+    }
+
+    // Evaluates every value before printing any, like the interpreter.
+    fn emit_print_stmt(&mut self, print_stmt: &PrintStmt) -> Result<(), CodegenError> {
+        let mut printed = vec![];
+        for expr in &print_stmt.exprs {
+            match self.string_literal(expr) {
+                Some(s) => {
+                    self.strings.push(s);
+                    printed.push(Printed::Literal(self.strings.len() - 1));
+                }
+                None => {
+                    let type_specifier = self.emit_expr(expr)?;
+                    printed.push(Printed::Slot(self.push_temp(), type_specifier));
+                }
+            }
+        }
+        for value in printed {
+            match value {
+                Printed::Slot(slot, type_specifier) => {
+                    self.line(format!("movl {}, %esi", self.slot(slot)));
+                    self.line(format!("call ox_print_{}", token_text(&type_specifier)));
+                }
+                Printed::Literal(n) => {
+                    self.line(format!("leaq .Lstring{}(%rip), %rsi", n));
+                    self.line("call ox_print_string".to_string());
+                }
+            }
+        }
+        for expr in &print_stmt.exprs {
+            if self.string_literal(expr).is_none() {
+                self.pop_temp();
+            }
+        }
+        self.line("movl $10, %edi".to_string());
+        self.line("call putchar@PLT".to_string());
+        Ok(())
+    }
+
+    // The text of a string literal or string constant, the only strings there are here.
+    fn string_literal(&self, expr: &Expr) -> Option<String> {
+        let value = match expr {
+            Expr::Primary(primary) => match Value::from_literal(&primary.constant) {
+                Some(value) => value,
+                None => self
+                    .symbols
+                    .constant(ident_name(&primary.constant))?
+                    .clone(),
+            },
+            Expr::Paren(paren) => return self.string_literal(&paren.expr),
+            _ => return None,
+        };
+        match value {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn emit_if_stmt(&mut self, if_stmt: &IfStmt) -> Result<(), CodegenError> {
+        let label = self.label();
+        self.emit_condition(&if_stmt.condition)?;
+        self.line("testl %eax, %eax".to_string());
+        self.line(format!("je .Lelse{}", label));
+        self.emit_stmt_list(&if_stmt.then_stmts)?;
+        self.line(format!("jmp .Lendif{}", label));
+        self.place_label("else", label);
+        if let Some(else_stmts) = &if_stmt.else_stmts {
+            self.emit_stmt_list(else_stmts)?;
+        }
+        self.place_label("endif", label);
+        Ok(())
+    }
+
+    fn emit_loop_stmt(&mut self, loop_stmt: &LoopStmt) -> Result<(), CodegenError> {
+        let label = self.label();
+        self.place_label("while", label);
+        self.emit_stmt_line(loop_stmt.line);
+        self.emit_condition(&loop_stmt.condition)?;
+        self.line("testl %eax, %eax".to_string());
+        self.line(format!("je .Lendwhile{}", label));
+        self.emit_stmt_list(&loop_stmt.stmts)?;
+        self.line(format!("jmp .Lwhile{}", label));
+        self.place_label("endwhile", label);
+        Ok(())
+    }
+
+    fn emit_condition(&mut self, condition: &Expr) -> Result<(), CodegenError> {
+        match self.emit_expr(condition)? {
+            Token::BOOL => Ok(()),
+            type_specifier => Err(CodegenError::new(
+                condition.position().0,
+                format!(
+                    "Condition must be bool, found {}.",
+                    token_text(&type_specifier)
+                ),
+            )),
+        }
+    }
+
+    fn variable(&self, name: &str) -> String {
+        self.slot(self.slots[name])
+    }
+
+    fn variable_type(&self, name: &str, line: u32) -> Result<Token, CodegenError> {
+        match self.symbols.get(name) {
+            Some(symbol) => supported(symbol.type_specifier.clone(), line),
+            None => Err(CodegenError::new(
+                line,
+                format!("Use of undeclared variable '{}'.", name),
+            )),
+        }
+    }
+
+    // Leaves the value of the expression in %eax and returns its type.
+    fn emit_expr(&mut self, expr: &Expr) -> Result<Token, CodegenError> {
+        let line = expr.position().0;
+        match expr {
+            Expr::Primary(primary) => match Value::from_literal(&primary.constant) {
+                Some(value) => self.emit_literal(&value, line),
+                None if primary.constant == Token::EOF => Err(no_input(line)),
+                None => {
+                    let name = ident_name(&primary.constant);
+                    match self.symbols.constant(name) {
+                        Some(value) => self.emit_literal(&value.clone(), line),
+                        None => {
+                            let type_specifier = self.variable_type(name, line)?;
+                            self.line(format!("movl {}, %eax", self.variable(name)));
+                            Ok(type_specifier)
+                        }
+                    }
+                }
+            },
+            Expr::Paren(paren) => self.emit_expr(&paren.expr),
+            Expr::Prefix(PrefixExpr { op, expr, .. }) | Expr::Postfix(PostfixExpr { op, expr }) => {
+                let operand_type = self.emit_expr(expr)?;
+                let result_type =
+                    unary_type(op, &operand_type).map_err(|msg| CodegenError::new(line, msg))?;
+                match op {
+                    Token::MINUS => {
+                        self.line("negl %eax".to_string());
+                        self.line("jo ox_overflow_minus".to_string());
+                    }
+                    Token::NOT => self.line("xorl $1, %eax".to_string()),
+                    Token::BITNOT => self.line("notl %eax".to_string()),
+                    _ => {}
+                }
+                Ok(result_type)
+            }
+            Expr::Binary(binary) => self.emit_binary_expr(binary, line),
+            Expr::Cast(cast) => self.emit_cast_expr(cast, line),
+            Expr::Call(call) => self.emit_call_expr(call),
+        }
+    }
+
+    fn emit_literal(&mut self, value: &Value, line: u32) -> Result<Token, CodegenError> {
+        let literal = match value {
+            Value::Int(i) => *i,
+            Value::Bool(b) => *b as i32,
+            Value::Float(_) => return Err(no_floats(line)),
+            Value::Str(_) => return Err(no_strings(line)),
+        };
+        self.line(format!("movl ${}, %eax", literal));
+        Ok(value.type_specifier())
+    }
+
+    fn emit_binary_expr(&mut self, binary: &BinaryExpr, line: u32) -> Result<Token, CodegenError> {
+        let lhs = self.emit_expr(&binary.lhs)?;
+        if !matches!(binary.op, Token::ANDOP | Token::OROP) {
+            let slot = self.push_temp();
+            let rhs = self.emit_expr(&binary.rhs)?;
+            self.pop_temp();
+            self.line("movl %eax, %ecx".to_string());
+            self.line(format!("movl {}, %eax", self.slot(slot)));
+            return self.emit_binary_op(&binary.op, &lhs, &rhs, line);
+        }
+        // The right operand is only evaluated when the left one doesn't decide the result,
+        // which is then left in %eax.
+        binary_type(&binary.op, &lhs, &Token::BOOL).map_err(|msg| CodegenError::new(line, msg))?;
+        let label = self.label();
+        self.line("testl %eax, %eax".to_string());
+        match binary.op {
+            Token::ANDOP => self.line(format!("je .Lshort{}", label)),
+            _ => self.line(format!("jne .Lshort{}", label)),
+        }
+        let rhs = self.emit_expr(&binary.rhs)?;
+        binary_type(&binary.op, &Token::BOOL, &rhs).map_err(|msg| CodegenError::new(line, msg))?;
+        self.place_label("short", label);
+        Ok(Token::BOOL)
+    }
+
+    // The left operand is in %eax and the right one in %ecx.
+    fn emit_binary_op(
+        &mut self,
+        op: &Token,
+        lhs: &Token,
+        rhs: &Token,
+        line: u32,
+    ) -> Result<Token, CodegenError> {
+        let result_type = binary_type(op, lhs, rhs).map_err(|msg| CodegenError::new(line, msg))?;
+        match op {
+            Token::PLUS => {
+                self.line("addl %ecx, %eax".to_string());
+                self.line("jo ox_overflow_plus".to_string());
+            }
+            Token::MINUS => {
+                self.line("subl %ecx, %eax".to_string());
+                self.line("jo ox_overflow_minus".to_string());
+            }
+            Token::MULT => {
+                self.line("imull %ecx, %eax".to_string());
+                self.line("jo ox_overflow_mult".to_string());
+            }
+            Token::DIV | Token::MOD | Token::IDIV | Token::IMOD | Token::POW => {
+                self.line("movl %eax, %edi".to_string());
+                self.line("movl %ecx, %esi".to_string());
+                self.line(format!("call ox_int_{}", int_function(op)));
+            }
+            Token::SHL | Token::SHR => {
+                let (instruction, error) = match op {
+                    Token::SHL => ("shll", "ox_shl_range"),
+                    _ => ("sarl", "ox_shr_range"),
+                };
+                self.line("cmpl $32, %ecx".to_string());
+                self.line(format!("jae {}", error));
+                self.line(format!("{} %cl, %eax", instruction));
+            }
+            Token::BITAND => self.line("andl %ecx, %eax".to_string()),
+            Token::BITOR => self.line("orl %ecx, %eax".to_string()),
+            Token::BITXOR => self.line("xorl %ecx, %eax".to_string()),
+            // What is left are comparisons.
+            _ => {
+                let set = match op {
+                    Token::EQUALOP => "sete",
+                    Token::LTHAN => "setl",
+                    _ => "setg",
+                };
+                self.line("cmpl %ecx, %eax".to_string());
+                self.line(format!("{} %al", set));
+                self.line("movzbl %al, %eax".to_string());
+            }
+        }
+        Ok(result_type)
+    }
+
+    fn emit_cast_expr(&mut self, cast: &CastExpr, line: u32) -> Result<Token, CodegenError> {
+        let from = self.emit_expr(&cast.expr)?;
+        let to = supported(cast.type_specifier.clone(), line)?;
+        // A bool already is 0 or 1, an int becomes one.
+        if from == Token::INT && to == Token::BOOL {
+            self.line("testl %eax, %eax".to_string());
+            self.line("setne %al".to_string());
+            self.line("movzbl %al, %eax".to_string());
+        }
+        Ok(to)
+    }
+
+    fn emit_call_expr(&mut self, call: &CallExpr) -> Result<Token, CodegenError> {
+        let name = ident_name(&call.name);
+        if self.symbols.natives().get(name).is_some() {
+            return Err(CodegenError::new(
+                call.line,
+                format!("The native function '{}' can't be compiled.", name),
+            ));
+        }
+        let mut arg_types = vec![];
+        let mut arg_slots = vec![];
+        for arg in &call.args {
+            arg_types.push(Some(self.emit_expr(arg)?));
+            arg_slots.push(self.push_temp());
+        }
+        let builtin = builtins::resolve(name, &arg_types)
+            .map_err(|msg| CodegenError::new(call.line, msg))?
+            .expect("argument types are known");
+        if builtin.name == "readline" {
+            return Err(no_input(call.line));
+        }
+        for param in builtin.params {
+            supported(param.clone(), call.line)?;
+        }
+        let returns = supported(builtin.returns.clone(), call.line)?;
+        for (slot, register) in arg_slots.iter().zip(ARG_REGISTERS) {
+            self.line(format!("movl {}, {}", self.slot(*slot), register));
+            self.pop_temp();
+        }
+        // Overloads get the type of their first parameter added, ox_abs_int.
+        let overloaded = BUILTINS.iter().filter(|other| other.name == name).count() > 1;
+        match builtin.params.first() {
+            Some(param) if overloaded => {
+                self.line(format!("call ox_{}_{}", name, token_text(param)))
+            }
+            _ => self.line(format!("call ox_{}", name)),
+        }
+        Ok(returns)
+    }
+}
+
+// Ints and bools fit in a register, nothing else does.
+fn supported(type_specifier: Token, line: u32) -> Result<Token, CodegenError> {
+    match type_specifier {
+        Token::FLOAT => Err(no_floats(line)),
+        Token::STRING => Err(no_strings(line)),
+        _ => Ok(type_specifier),
+    }
+}
+
+fn no_floats(line: u32) -> CodegenError {
+    CodegenError::new(line, "Floats can't be compiled to assembly.".to_string())
+}
+
+fn no_strings(line: u32) -> CodegenError {
+    CodegenError::new(
+        line,
+        "Strings can't be compiled to assembly, except literals given to print.".to_string(),
+    )
+}
+
+fn no_input(line: u32) -> CodegenError {
+    CodegenError::new(
+        line,
+        "Input can't be read by an assembly program.".to_string(),
+    )
+}
+
+fn int_function(op: &Token) -> &'static str {
+    match op {
+        Token::DIV => "div",
+        Token::MOD => "mod",
+        Token::IDIV => "idiv",
+        Token::IMOD => "imod",
+        _ => "pow",
+    }
+}
+
+// Anything but printable ASCII is written as an octal escape, as are " and \.
+fn string_literal(s: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in s.bytes() {
+        match byte {
+            b' '..=b'~' if byte != b'"' && byte != b'\\' => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
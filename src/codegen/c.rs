@@ -0,0 +1,448 @@
+use super::{check_standalone, emit_stmts, variables, CodegenError};
+use crate::{
+    builtins::{self, BUILTINS},
+    format::token_text,
+    operators,
+    parser::{
+        AssignStmt, BinaryExpr, CallExpr, CastExpr, CtrlStmt, Expr, IfStmt, LoopStmt, PostfixExpr,
+        PrefixExpr, PrintStmt, Program, ReadStmt, Stmt, StmtList,
+    },
+    semantic::{ident_name, SymbolTable},
+    types::{binary_type, promote, unary_type},
+    value::Value,
+    Token,
+};
+
+const RUNTIME: &str = include_str!("runtime.c");
+
+// Compiles a program to a self-contained C99 translation unit: the runtime in runtime.c
+// followed by a main function. Variables become typed locals of main (int32_t, double,
+// bool and ox_str), and every operation that can fail or has an effect is evaluated
+// into a temporary of its own, so the program does things in the interpreter's order
+// and stops at the same runtime error, which it reports on stderr with exit status 1.
+// Each statement sets ox_stmt_line first, the line errors are reported at.
+pub struct Generator<'a> {
+    symbols: &'a SymbolTable,
+    code: String,
+    indent: usize,
+    temps: usize,
+    // The line the top level statement being emitted starts on, see Program::stmts.
+    base: u32,
+}
+
+// A C expression that is only a literal, a variable or a temporary, with its type.
+type Operand = (String, Token);
+
+impl<'a> Generator<'a> {
+    pub fn new(symbols: &'a SymbolTable) -> Self {
+        Self {
+            symbols,
+            code: String::new(),
+            indent: 1,
+            temps: 0,
+            base: 0,
+        }
+    }
+
+    pub fn emit_program(mut self, program: &Program) -> Result<String, CodegenError> {
+        check_standalone(self.symbols)?;
+        for (name, type_specifier) in variables(program) {
+            let zero = match type_specifier {
+                Token::INT => "0",
+                Token::FLOAT => "0.0",
+                Token::BOOL => "false",
+                _ => "\"\"",
+            };
+            self.line(format!(
+                "{} {} = {};",
+                c_type(&type_specifier),
+                variable(&name),
+                zero
+            ));
+        }
+        emit_stmts(program, |line, ctrl_stmt| {
+            self.base = line;
+            self.emit_ctrl_stmt(ctrl_stmt)
+        })?;
+        self.line("return 0;".to_string());
+        Ok(format!("{}\nint main(void) {{\n{}}}\n", RUNTIME, self.code))
+    }
+
+    fn line(&mut self, line: String) {
+        self.code.push_str(&"    ".repeat(self.indent));
+        self.code.push_str(&line);
+        self.code.push('\n');
+    }
+
+    // Declares a temporary holding `value`.
+    fn temp(&mut self, type_specifier: Token, value: String) -> Operand {
+        let name = format!("t{}", self.temps);
+        self.temps += 1;
+        self.line(format!("{} {} = {};", c_type(&type_specifier), name, value));
+        (name, type_specifier)
+    }
+
+    fn emit_stmt_list(&mut self, stmt_list: &StmtList) -> Result<(), CodegenError> {
+        for stmt in &stmt_list.stmts {
+            // Variables were declared at the top of main.
+            if let Stmt::Ctrl(ctrl_stmt) = stmt {
+                self.emit_ctrl_stmt(ctrl_stmt)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_ctrl_stmt(&mut self, ctrl_stmt: &CtrlStmt) -> Result<(), CodegenError> {
+        self.emit_stmt_line(ctrl_stmt.line());
+        match ctrl_stmt {
+            CtrlStmt::Assign(assign_stmt) => self.emit_assign_stmt(assign_stmt),
+            CtrlStmt::Print(print_stmt) => self.emit_print_stmt(print_stmt),
+            CtrlStmt::Read(read_stmt) => self.emit_read_stmt(read_stmt),
+            CtrlStmt::If(if_stmt) => self.emit_if_stmt(if_stmt),
+            CtrlStmt::Loop(loop_stmt) => self.emit_loop_stmt(loop_stmt),
+        }
+    }
+
+    fn emit_stmt_line(&mut self, line: u32) {
+        self.line(format!("ox_stmt_line = {};", self.base + line));
+    }
+
+    fn emit_assign_stmt(&mut self, assign_stmt: &AssignStmt) -> Result<(), CodegenError> {
+        let name = ident_name(&assign_stmt.ident);
+        let line = assign_stmt.line;
+        let declared = self.variable_type(name, line)?;
+        let (value, value_type) = match operators::compound_assignment(&assign_stmt.op) {
+            Some(op) => {
+                let current = (variable(name), declared.clone());
+                let rhs = self.emit_expr(&assign_stmt.expr)?;
+                self.emit_binary_op(op, current, rhs, line)?
+            }
+            None => self.emit_expr(&assign_stmt.expr)?,
+        };
+        if value_type != declared {
+            return Err(CodegenError::new(
+                line,
+                format!(
+                    "Cannot assign {} to '{}' declared as {:?}.",
+                    token_text(&value_type),
+                    name,
+                    declared
+                ),
+            ));
+        }
+        self.line(format!("{} = {};", variable(name), value));
+        Ok(())
+    }
+
+    // Evaluates every value before printing any, like the interpreter.
+    fn emit_print_stmt(&mut self, print_stmt: &PrintStmt) -> Result<(), CodegenError> {
+        let mut values = vec![];
+        for expr in &print_stmt.exprs {
+            values.push(self.emit_expr(expr)?);
+        }
+        for (value, type_specifier) in values {
+            self.line(format!(
+                "ox_print_{}({});",
+                token_text(&type_specifier),
+                value
+            ));
+        }
+        self.line("putchar('\\n');".to_string());
+        Ok(())
+    }
+
+    fn emit_read_stmt(&mut self, read_stmt: &ReadStmt) -> Result<(), CodegenError> {
+        for ident in &read_stmt.idents {
+            let name = ident_name(ident);
+            let type_specifier = self.variable_type(name, read_stmt.line)?;
+            self.line(format!(
+                "{} = ox_read_{}({});",
+                variable(name),
+                token_text(&type_specifier),
+                string_literal(name)
+            ));
+        }
+        Ok(())
+    }
+
+    fn emit_if_stmt(&mut self, if_stmt: &IfStmt) -> Result<(), CodegenError> {
+        let condition = self.emit_condition(&if_stmt.condition)?;
+        self.line(format!("if ({}) {{", condition));
+        self.indent += 1;
+        self.emit_stmt_list(&if_stmt.then_stmts)?;
+        self.indent -= 1;
+        if let Some(else_stmts) = &if_stmt.else_stmts {
+            self.line("} else {".to_string());
+            self.indent += 1;
+            self.emit_stmt_list(else_stmts)?;
+            self.indent -= 1;
+        }
+        self.line("}".to_string());
+        Ok(())
+    }
+
+    // The condition can take statements to evaluate, so it goes inside the loop, which is
+    // back on its own line for it after the body.
+    fn emit_loop_stmt(&mut self, loop_stmt: &LoopStmt) -> Result<(), CodegenError> {
+        self.line("for (;;) {".to_string());
+        self.indent += 1;
+        self.emit_stmt_line(loop_stmt.line);
+        let condition = self.emit_condition(&loop_stmt.condition)?;
+        self.line(format!("if (!{}) {{", condition));
+        self.line("    break;".to_string());
+        self.line("}".to_string());
+        self.emit_stmt_list(&loop_stmt.stmts)?;
+        self.indent -= 1;
+        self.line("}".to_string());
+        Ok(())
+    }
+
+    fn emit_condition(&mut self, condition: &Expr) -> Result<String, CodegenError> {
+        match self.emit_expr(condition)? {
+            (value, Token::BOOL) => Ok(value),
+            (_, type_specifier) => Err(CodegenError::new(
+                condition.position().0,
+                format!(
+                    "Condition must be bool, found {}.",
+                    token_text(&type_specifier)
+                ),
+            )),
+        }
+    }
+
+    fn variable_type(&self, name: &str, line: u32) -> Result<Token, CodegenError> {
+        match self.symbols.get(name) {
+            Some(symbol) => Ok(symbol.type_specifier.clone()),
+            None => Err(CodegenError::new(
+                line,
+                format!("Use of undeclared variable '{}'.", name),
+            )),
+        }
+    }
+
+    fn emit_expr(&mut self, expr: &Expr) -> Result<Operand, CodegenError> {
+        let line = expr.position().0;
+        match expr {
+            Expr::Primary(primary) => match Value::from_literal(&primary.constant) {
+                Some(value) => Ok(literal(&value)),
+                None if primary.constant == Token::EOF => {
+                    Ok(self.temp(Token::BOOL, "ox_eof()".to_string()))
+                }
+                None => {
+                    let name = ident_name(&primary.constant);
+                    match self.symbols.constant(name) {
+                        Some(value) => Ok(literal(value)),
+                        None => Ok((variable(name), self.variable_type(name, line)?)),
+                    }
+                }
+            },
+            Expr::Paren(paren) => self.emit_expr(&paren.expr),
+            Expr::Prefix(PrefixExpr { op, expr, .. }) | Expr::Postfix(PostfixExpr { op, expr }) => {
+                let (operand, operand_type) = self.emit_expr(expr)?;
+                let result_type =
+                    unary_type(op, &operand_type).map_err(|msg| CodegenError::new(line, msg))?;
+                let value = match (op, &operand_type) {
+                    (Token::MINUS, Token::INT) => format!("ox_int_neg({})", operand),
+                    (Token::MINUS, _) => format!("-{}", operand),
+                    (Token::NOT, _) => format!("!{}", operand),
+                    (Token::BITNOT, _) => format!("~{}", operand),
+                    _ => return Ok((operand, operand_type)),
+                };
+                Ok(self.temp(result_type, value))
+            }
+            Expr::Binary(binary) => self.emit_binary_expr(binary, line),
+            Expr::Cast(cast) => self.emit_cast_expr(cast),
+            Expr::Call(call) => self.emit_call_expr(call),
+        }
+    }
+
+    fn emit_binary_expr(
+        &mut self,
+        binary: &BinaryExpr,
+        line: u32,
+    ) -> Result<Operand, CodegenError> {
+        let lhs = self.emit_expr(&binary.lhs)?;
+        if !matches!(binary.op, Token::ANDOP | Token::OROP) {
+            let rhs = self.emit_expr(&binary.rhs)?;
+            return self.emit_binary_op(&binary.op, lhs, rhs, line);
+        }
+        // The right operand is only evaluated when the left one doesn't decide the result.
+        binary_type(&binary.op, &lhs.1, &Token::BOOL)
+            .map_err(|msg| CodegenError::new(line, msg))?;
+        let (result, _) = self.temp(Token::BOOL, lhs.0);
+        let decisive = binary.op == Token::OROP;
+        self.line(format!(
+            "if ({}{}) {{",
+            if decisive { "!" } else { "" },
+            result
+        ));
+        self.indent += 1;
+        let rhs = self.emit_expr(&binary.rhs)?;
+        binary_type(&binary.op, &Token::BOOL, &rhs.1)
+            .map_err(|msg| CodegenError::new(line, msg))?;
+        self.line(format!("{} = {};", result, rhs.0));
+        self.indent -= 1;
+        self.line("}".to_string());
+        Ok((result, Token::BOOL))
+    }
+
+    fn emit_binary_op(
+        &mut self,
+        op: &Token,
+        lhs: Operand,
+        rhs: Operand,
+        line: u32,
+    ) -> Result<Operand, CodegenError> {
+        let (lhs_type, rhs_type) = promote(op, &lhs.1, &rhs.1);
+        let result_type =
+            binary_type(op, &lhs_type, &rhs_type).map_err(|msg| CodegenError::new(line, msg))?;
+        let widen = |(value, type_specifier): Operand, to: &Token| {
+            if &type_specifier == to {
+                value
+            } else {
+                format!("(double){}", value)
+            }
+        };
+        let (a, b) = (widen(lhs, &lhs_type), widen(rhs, &rhs_type));
+        let value = match (op, &lhs_type, &rhs_type) {
+            (Token::EQUALOP, Token::STRING, _) => format!("ox_str_eq({}, {})", a, b),
+            (Token::LTHAN, Token::STRING, _) => format!("ox_str_cmp({}, {}) < 0", a, b),
+            (Token::GTHAN, Token::STRING, _) => format!("ox_str_cmp({}, {}) > 0", a, b),
+            (Token::PLUS, Token::STRING, _) => format!("ox_concat({}, {})", a, b),
+            (Token::EQUALOP, ..) => format!("{} == {}", a, b),
+            (Token::LTHAN, ..) => format!("{} < {}", a, b),
+            (Token::GTHAN, ..) => format!("{} > {}", a, b),
+            (Token::BITAND, ..) => format!("{} & {}", a, b),
+            (Token::BITOR, ..) => format!("{} | {}", a, b),
+            (Token::BITXOR, ..) => format!("{} ^ {}", a, b),
+            (Token::POW, Token::FLOAT, Token::INT) => format!("ox_float_powi({}, {})", a, b),
+            (Token::POW, Token::FLOAT, _) => format!("pow({}, {})", a, b),
+            (Token::MOD, Token::FLOAT, _) => format!("fmod({}, {})", a, b),
+            (Token::IDIV, Token::FLOAT, _) => format!("floor({} / {})", a, b),
+            (Token::IMOD, Token::FLOAT, _) => format!("ox_float_imod({}, {})", a, b),
+            (Token::PLUS, Token::FLOAT, _) => format!("{} + {}", a, b),
+            (Token::MINUS, Token::FLOAT, _) => format!("{} - {}", a, b),
+            (Token::MULT, Token::FLOAT, _) => format!("{} * {}", a, b),
+            (Token::DIV, Token::FLOAT, _) => format!("{} / {}", a, b),
+            // What is left are the int operations that check for overflow.
+            _ => format!("ox_int_{}({}, {})", int_function(op), a, b),
+        };
+        Ok(self.temp(result_type, value))
+    }
+
+    fn emit_cast_expr(&mut self, cast: &CastExpr) -> Result<Operand, CodegenError> {
+        let (value, from) = self.emit_expr(&cast.expr)?;
+        let to = cast.type_specifier.clone();
+        let converted = match (&from, &to) {
+            _ if from == to => return Ok((value, from)),
+            (Token::FLOAT, Token::INT) => format!("ox_float_to_int({})", value),
+            (Token::BOOL, Token::INT) => format!("(int32_t){}", value),
+            (Token::STRING, Token::INT) => format!("ox_str_to_int({})", value),
+            (_, Token::FLOAT) if from != Token::STRING => format!("(double){}", value),
+            (Token::STRING, Token::FLOAT) => format!("ox_str_to_float({})", value),
+            (Token::STRING, Token::BOOL) => format!("ox_str_to_bool({})", value),
+            (_, Token::BOOL) => format!("{} != 0", value),
+            _ => format!("ox_{}_to_str({})", token_text(&from), value),
+        };
+        Ok(self.temp(to, converted))
+    }
+
+    fn emit_call_expr(&mut self, call: &CallExpr) -> Result<Operand, CodegenError> {
+        let name = ident_name(&call.name);
+        if self.symbols.natives().get(name).is_some() {
+            return Err(CodegenError::new(
+                call.line,
+                format!("The native function '{}' can't be compiled.", name),
+            ));
+        }
+        let mut args = vec![];
+        for arg in &call.args {
+            args.push(self.emit_expr(arg)?);
+        }
+        let arg_types: Vec<Option<Token>> = args.iter().map(|(_, t)| Some(t.clone())).collect();
+        let builtin = builtins::resolve(name, &arg_types)
+            .map_err(|msg| CodegenError::new(call.line, msg))?
+            .expect("argument types are known");
+        let args: Vec<String> = args
+            .into_iter()
+            .zip(builtin.params)
+            .map(|((value, arg_type), param)| match (arg_type, param) {
+                (Token::INT, Token::FLOAT) => format!("(double){}", value),
+                _ => value,
+            })
+            .collect();
+        // Overloads get the type of their first parameter added, ox_abs_int.
+        let overloaded = BUILTINS.iter().filter(|other| other.name == name).count() > 1;
+        let function = match builtin.params.first() {
+            Some(param) if overloaded => format!("ox_{}_{}", name, token_text(param)),
+            _ => format!("ox_{}", name),
+        };
+        let value = format!("{}({})", function, args.join(", "));
+        Ok(self.temp(builtin.returns.clone(), value))
+    }
+}
+
+fn c_type(type_specifier: &Token) -> &'static str {
+    match type_specifier {
+        Token::INT => "int32_t",
+        Token::FLOAT => "double",
+        Token::BOOL => "bool",
+        _ => "ox_str",
+    }
+}
+
+// Prefixed so an oxide variable can't clash with C keywords or the runtime.
+fn variable(name: &str) -> String {
+    format!("v_{}", name)
+}
+
+fn int_function(op: &Token) -> &'static str {
+    match op {
+        Token::PLUS => "add",
+        Token::MINUS => "sub",
+        Token::MULT => "mul",
+        Token::DIV => "div",
+        Token::MOD => "mod",
+        Token::IDIV => "idiv",
+        Token::IMOD => "imod",
+        Token::POW => "pow",
+        Token::SHL => "shl",
+        _ => "shr",
+    }
+}
+
+fn literal(value: &Value) -> Operand {
+    let literal = match value {
+        Value::Int(i32::MIN) => "INT32_MIN".to_string(),
+        Value::Int(i) if *i < 0 => format!("({})", i),
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) if f.is_nan() => "NAN".to_string(),
+        Value::Float(f) if f.is_infinite() => {
+            format!("{}INFINITY", if *f < 0.0 { "-" } else { "" })
+        }
+        // Debug formatting reads back as the same double.
+        Value::Float(f) if f.is_sign_negative() => format!("({:?})", f),
+        Value::Float(f) => format!("{:?}", f),
+        Value::Bool(b) => b.to_string(),
+        Value::Str(s) => string_literal(s),
+    };
+    (literal, value.type_specifier())
+}
+
+// Anything but printable ASCII is written as an octal escape, and ? is escaped so it
+// can't start a trigraph.
+fn string_literal(s: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in s.bytes() {
+        match byte {
+            b'"' | b'\\' | b'?' => {
+                literal.push('\\');
+                literal.push(byte as char);
+            }
+            b' '..=b'~' => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
@@ -0,0 +1,91 @@
+use crate::{
+    parser::{CtrlStmt, Program, Stmt, StmtList},
+    semantic::{ident_name, SymbolTable},
+    Token,
+};
+use std::{error::Error, fmt};
+
+pub mod asm;
+pub mod c;
+pub mod wasm;
+
+// Backends compiling a program that passed semantic analysis to another language. The
+// analyzer has checked the type of every expression, and backends work out the type of
+// each operand as they go with the same rules from types.rs. A CodegenError is a
+// program the backend can't compile, like one with floats for a backend without them.
+
+#[derive(Debug)]
+pub struct CodegenError {
+    pub line: u32,
+    msg: String,
+}
+
+impl Error for CodegenError {}
+
+impl CodegenError {
+    pub fn new(line: u32, msg: String) -> Self {
+        CodegenError { line, msg }
+    }
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+// The variables a program declares with their types, in the order they're declared.
+// Constants are left out, their folded values are in the symbol table.
+pub(crate) fn variables(program: &Program) -> Vec<(String, Token)> {
+    fn collect(stmt_list: &StmtList, variables: &mut Vec<(String, Token)>) {
+        for stmt in &stmt_list.stmts {
+            match stmt {
+                Stmt::Decl(decl_stmt) if !decl_stmt.is_const => {
+                    for ident in &decl_stmt.idents {
+                        let name = ident_name(ident).to_string();
+                        variables.push((name, decl_stmt.type_specifier.clone()));
+                    }
+                }
+                Stmt::Decl(_) => {}
+                Stmt::Ctrl(CtrlStmt::If(if_stmt)) => {
+                    collect(&if_stmt.then_stmts, variables);
+                    if let Some(else_stmts) = &if_stmt.else_stmts {
+                        collect(else_stmts, variables);
+                    }
+                }
+                Stmt::Ctrl(CtrlStmt::Loop(loop_stmt)) => collect(&loop_stmt.stmts, variables),
+                Stmt::Ctrl(_) => {}
+            }
+        }
+    }
+    let mut variables = vec![];
+    collect(&program.stmt_list, &mut variables);
+    variables
+}
+
+// Emits each top level control statement with `emit`, which is also given the line the
+// statement starts on. Lines in a statement count from there (see Program::stmts), so
+// its errors are moved to the line in the program. Declarations are left to variables.
+pub(crate) fn emit_stmts(
+    program: &Program,
+    mut emit: impl FnMut(u32, &CtrlStmt) -> Result<(), CodegenError>,
+) -> Result<(), CodegenError> {
+    for (line, stmt) in program.stmts() {
+        if let Stmt::Ctrl(ctrl_stmt) = stmt {
+            emit(line, ctrl_stmt)
+                .map_err(|error| CodegenError::new(line + error.line, error.msg))?;
+        }
+    }
+    Ok(())
+}
+
+// A compiled program has no host to provide globals or native functions.
+pub(crate) fn check_standalone(symbols: &SymbolTable) -> Result<(), CodegenError> {
+    match symbols.globals().iter().next() {
+        Some((name, _)) => Err(CodegenError::new(
+            0,
+            format!("The host global '{}' can't be compiled.", name),
+        )),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,801 @@
+// The runtime of C programs generated by oxide, mirroring the interpreter: the same
+// checked arithmetic, casts, builtins, input handling and float formatting. Strings are
+// NUL terminated UTF-8 and never freed.
+
+#include <ctype.h>
+#include <inttypes.h>
+#include <locale.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <wctype.h>
+
+typedef const char *ox_str;
+
+// The line of the statement being executed, where runtime errors are reported.
+static int ox_stmt_line;
+
+// Runtime errors end the program with exit status 1, after the output so far.
+static inline void ox_fail(const char *format, ...) {
+    va_list args;
+    fflush(stdout);
+    fprintf(stderr, "Runtime error: Line %d: ", ox_stmt_line);
+    va_start(args, format);
+    vfprintf(stderr, format, args);
+    va_end(args);
+    fputc('\n', stderr);
+    exit(1);
+}
+
+static inline char *ox_alloc(size_t size) {
+    char *memory = malloc(size);
+    if (memory == NULL) {
+        ox_fail("Out of memory.");
+    }
+    return memory;
+}
+
+static inline char *ox_copy(const char *s, size_t length) {
+    char *copy = ox_alloc(length + 1);
+    memcpy(copy, s, length);
+    copy[length] = '\0';
+    return copy;
+}
+
+// Integer arithmetic, overflow is an error.
+
+static inline int32_t ox_checked(int64_t result, const char *op) {
+    if (result < INT32_MIN || result > INT32_MAX) {
+        ox_fail("Integer overflow in %s.", op);
+    }
+    return (int32_t)result;
+}
+
+static inline int32_t ox_int_add(int32_t a, int32_t b) {
+    return ox_checked((int64_t)a + b, "PLUS");
+}
+
+static inline int32_t ox_int_sub(int32_t a, int32_t b) {
+    return ox_checked((int64_t)a - b, "MINUS");
+}
+
+static inline int32_t ox_int_mul(int32_t a, int32_t b) {
+    return ox_checked((int64_t)a * b, "MULT");
+}
+
+static inline void ox_check_divisor(int32_t a, int32_t b, const char *op) {
+    if (b == 0) {
+        ox_fail("Division by zero.");
+    }
+    if (a == INT32_MIN && b == -1) {
+        ox_fail("Integer overflow in %s.", op);
+    }
+}
+
+static inline int32_t ox_int_div(int32_t a, int32_t b) {
+    ox_check_divisor(a, b, "DIV");
+    return a / b;
+}
+
+static inline int32_t ox_int_mod(int32_t a, int32_t b) {
+    ox_check_divisor(a, b, "MOD");
+    return a % b;
+}
+
+// Rounds toward negative infinity where DIV truncates.
+static inline int32_t ox_int_idiv(int32_t a, int32_t b) {
+    ox_check_divisor(a, b, "IDIV");
+    int32_t q = a / b;
+    if (a % b != 0 && (a < 0) != (b < 0)) {
+        q -= 1;
+    }
+    return q;
+}
+
+// Takes the sign of the divisor where MOD takes the dividend's.
+static inline int32_t ox_int_imod(int32_t a, int32_t b) {
+    ox_check_divisor(a, b, "IMOD");
+    int32_t r = a % b;
+    if (r != 0 && (r < 0) != (b < 0)) {
+        r += b;
+    }
+    return r;
+}
+
+static inline double ox_float_imod(double a, double b) {
+    double r = fmod(a, b);
+    if (r != 0.0 && (r < 0.0) != (b < 0.0)) {
+        r += b;
+    }
+    return r;
+}
+
+// Squares the base only while bits of the exponent are left, like Rust's checked_pow.
+static inline int32_t ox_int_pow(int32_t a, int32_t b) {
+    if (b < 0) {
+        ox_fail("Negative exponent in integer POW, use a float base.");
+    }
+    int32_t acc = 1;
+    while (b > 0) {
+        if (b & 1) {
+            acc = ox_checked((int64_t)acc * a, "POW");
+            if (b == 1) {
+                break;
+            }
+        }
+        b /= 2;
+        a = ox_checked((int64_t)a * a, "POW");
+    }
+    return acc;
+}
+
+static inline int32_t ox_int_neg(int32_t a) {
+    if (a == INT32_MIN) {
+        ox_fail("Integer overflow in MINUS.");
+    }
+    return -a;
+}
+
+static inline void ox_check_shift(int32_t b, const char *op) {
+    if (b < 0 || b >= 32) {
+        ox_fail("Shift amount %" PRId32 " out of range in %s.", b, op);
+    }
+}
+
+// Bits shifted out are dropped.
+static inline int32_t ox_int_shl(int32_t a, int32_t b) {
+    ox_check_shift(b, "SHL");
+    return (int32_t)((uint32_t)a << b);
+}
+
+// Keeps the sign.
+static inline int32_t ox_int_shr(int32_t a, int32_t b) {
+    ox_check_shift(b, "SHR");
+    return a < 0 ? ~(~a >> b) : a >> b;
+}
+
+// A float raised to an int, multiplied out like Rust's powi.
+static inline double ox_float_powi(double x, int32_t m) {
+    uint32_t n = m < 0 ? -(uint32_t)m : (uint32_t)m;
+    double y = n % 2 ? x : 1;
+    while (n >>= 1) {
+        x = x * x;
+        if (n % 2) {
+            y = y * x;
+        }
+    }
+    return m < 0 ? 1 / y : y;
+}
+
+// Strings.
+
+static inline ox_str ox_concat(ox_str a, ox_str b) {
+    size_t la = strlen(a), lb = strlen(b);
+    char *s = ox_alloc(la + lb + 1);
+    memcpy(s, a, la);
+    memcpy(s + la, b, lb + 1);
+    return s;
+}
+
+static inline bool ox_str_eq(ox_str a, ox_str b) {
+    return strcmp(a, b) == 0;
+}
+
+static inline int ox_str_cmp(ox_str a, ox_str b) {
+    return strcmp(a, b);
+}
+
+static inline bool ox_is_continuation(char c) {
+    return ((unsigned char)c & 0xC0) == 0x80;
+}
+
+// Number of characters.
+static inline size_t ox_char_count(ox_str s) {
+    size_t count = 0;
+    for (; *s; s++) {
+        count += !ox_is_continuation(*s);
+    }
+    return count;
+}
+
+// The byte offset of character `index`.
+static inline size_t ox_char_offset(ox_str s, size_t index) {
+    size_t offset = 0;
+    while (s[offset] && index > 0) {
+        offset++;
+        while (ox_is_continuation(s[offset])) {
+            offset++;
+        }
+        index--;
+    }
+    return offset;
+}
+
+// Decodes the character at *p and moves past it.
+static inline uint32_t ox_decode(const char **p) {
+    const unsigned char *bytes = (const unsigned char *)*p;
+    int extra = bytes[0] < 0x80 ? 0 : bytes[0] >= 0xF0 ? 3 : bytes[0] >= 0xE0 ? 2 : 1;
+    uint32_t c = extra == 0 ? bytes[0] : bytes[0] & (0x3F >> extra);
+    for (int k = 1; k <= extra; k++) {
+        c = (c << 6) | (bytes[k] & 0x3F);
+    }
+    *p += extra + 1;
+    return c;
+}
+
+// Encodes a character into out, returns the number of bytes written.
+static inline size_t ox_encode(uint32_t c, char *out) {
+    if (c < 0x80) {
+        out[0] = (char)c;
+        return 1;
+    }
+    if (c < 0x800) {
+        out[0] = (char)(0xC0 | (c >> 6));
+        out[1] = (char)(0x80 | (c & 0x3F));
+        return 2;
+    }
+    if (c < 0x10000) {
+        out[0] = (char)(0xE0 | (c >> 12));
+        out[1] = (char)(0x80 | ((c >> 6) & 0x3F));
+        out[2] = (char)(0x80 | (c & 0x3F));
+        return 3;
+    }
+    out[0] = (char)(0xF0 | (c >> 18));
+    out[1] = (char)(0x80 | ((c >> 12) & 0x3F));
+    out[2] = (char)(0x80 | ((c >> 6) & 0x3F));
+    out[3] = (char)(0x80 | (c & 0x3F));
+    return 4;
+}
+
+// The way print shows values.
+
+static inline ox_str ox_int_to_str(int32_t i) {
+    char buffer[16];
+    snprintf(buffer, sizeof buffer, "%" PRId32, i);
+    return ox_copy(buffer, strlen(buffer));
+}
+
+static inline ox_str ox_bool_to_str(bool b) {
+    return b ? "true" : "false";
+}
+
+// The shortest digits that read back as the same float, written out without an
+// exponent like Rust prints floats: 1e20 is 100000000000000000000, 0.1 is 0.1 and 3.0
+// is 3.
+static inline ox_str ox_float_to_str(double x) {
+    if (isnan(x)) {
+        return "NaN";
+    }
+    if (isinf(x)) {
+        return x < 0 ? "-inf" : "inf";
+    }
+    if (x == 0) {
+        return signbit(x) ? "-0" : "0";
+    }
+    char scientific[32];
+    for (int precision = 1; precision <= 17; precision++) {
+        snprintf(scientific, sizeof scientific, "%.*e", precision - 1, x);
+        if (strtod(scientific, NULL) == x) {
+            break;
+        }
+    }
+    // scientific is [-]d[.ddd]e[+-]xx
+    char digits[32];
+    size_t count = 0;
+    const char *p = scientific;
+    bool negative = *p == '-';
+    if (negative) {
+        p++;
+    }
+    for (; *p != 'e'; p++) {
+        if (isdigit((unsigned char)*p)) {
+            digits[count++] = *p;
+        }
+    }
+    while (count > 1 && digits[count - 1] == '0') {
+        count--;
+    }
+    int exponent = atoi(p + 1);
+    char *s = ox_alloc(count + abs(exponent) + 4);
+    char *out = s;
+    if (negative) {
+        *out++ = '-';
+    }
+    if (exponent < 0) {
+        *out++ = '0';
+        *out++ = '.';
+        for (int i = 0; i < -exponent - 1; i++) {
+            *out++ = '0';
+        }
+        memcpy(out, digits, count);
+        out += count;
+    } else {
+        for (int i = 0; i <= exponent; i++) {
+            *out++ = (size_t)i < count ? digits[i] : '0';
+        }
+        if (count > (size_t)exponent + 1) {
+            *out++ = '.';
+            memcpy(out, digits + exponent + 1, count - exponent - 1);
+            out += count - exponent - 1;
+        }
+    }
+    *out = '\0';
+    return s;
+}
+
+// Like ox_float_to_str, with a fraction on whole numbers, for error messages.
+static inline ox_str ox_float_debug(double x) {
+    ox_str s = ox_float_to_str(x);
+    if (isfinite(x) && strchr(s, '.') == NULL) {
+        return ox_concat(s, ".0");
+    }
+    return s;
+}
+
+static inline void ox_print_string(ox_str s) {
+    fputs(s, stdout);
+}
+
+static inline void ox_print_int(int32_t i) {
+    printf("%" PRId32, i);
+}
+
+static inline void ox_print_float(double x) {
+    fputs(ox_float_to_str(x), stdout);
+}
+
+static inline void ox_print_bool(bool b) {
+    fputs(ox_bool_to_str(b), stdout);
+}
+
+// Casts, failing like value::cast does.
+
+static inline void ox_cast_failed(const char *from, ox_str shown, const char *to) {
+    ox_fail("Cannot convert %s %s to %s.", from, shown, to);
+}
+
+static inline void ox_str_cast_failed(ox_str s, const char *to) {
+    char *shown = ox_alloc(strlen(s) + 3);
+    sprintf(shown, "\"%s\"", s);
+    ox_cast_failed("string", shown, to);
+}
+
+static inline int32_t ox_float_to_int(double x) {
+    double truncated = trunc(x);
+    if (!(truncated >= INT32_MIN && truncated <= INT32_MAX)) {
+        ox_cast_failed("float", ox_float_to_str(x), "int");
+    }
+    return (int32_t)truncated;
+}
+
+// An optional sign and digits, nothing else.
+static inline bool ox_parse_int(ox_str s, int32_t *result) {
+    const char *p = s;
+    bool negative = *p == '-';
+    if (*p == '+' || *p == '-') {
+        p++;
+    }
+    if (*p == '\0') {
+        return false;
+    }
+    int64_t value = 0;
+    for (; *p; p++) {
+        if (!isdigit((unsigned char)*p)) {
+            return false;
+        }
+        value = value * 10 + (*p - '0');
+        if (value > (int64_t)INT32_MAX + 1) {
+            return false;
+        }
+    }
+    value = negative ? -value : value;
+    if (value < INT32_MIN || value > INT32_MAX) {
+        return false;
+    }
+    *result = (int32_t)value;
+    return true;
+}
+
+static inline bool ox_equals_ignoring_case(const char *s, const char *lower) {
+    for (; *s && *lower; s++, lower++) {
+        if (tolower((unsigned char)*s) != *lower) {
+            return false;
+        }
+    }
+    return *s == *lower;
+}
+
+// The syntax Rust parses floats with: an optional sign, then inf, infinity or nan in
+// any case, or digits with an optional fraction and exponent.
+static inline bool ox_parse_float(ox_str s, double *result) {
+    const char *p = s;
+    if (*p == '+' || *p == '-') {
+        p++;
+    }
+    bool special = ox_equals_ignoring_case(p, "inf") || ox_equals_ignoring_case(p, "infinity")
+        || ox_equals_ignoring_case(p, "nan");
+    if (!special) {
+        int digits = 0;
+        for (; isdigit((unsigned char)*p); p++) {
+            digits++;
+        }
+        if (*p == '.') {
+            for (p++; isdigit((unsigned char)*p); p++) {
+                digits++;
+            }
+        }
+        if (digits == 0) {
+            return false;
+        }
+        if (*p == 'e' || *p == 'E') {
+            p++;
+            if (*p == '+' || *p == '-') {
+                p++;
+            }
+            if (!isdigit((unsigned char)*p)) {
+                return false;
+            }
+            while (isdigit((unsigned char)*p)) {
+                p++;
+            }
+        }
+        if (*p != '\0') {
+            return false;
+        }
+    }
+    *result = strtod(s, NULL);
+    return true;
+}
+
+static inline bool ox_parse_bool(ox_str s, bool *result) {
+    if (strcmp(s, "true") == 0 || strcmp(s, "false") == 0) {
+        *result = s[0] == 't';
+        return true;
+    }
+    return false;
+}
+
+static inline int32_t ox_str_to_int(ox_str s) {
+    int32_t i = 0;
+    if (!ox_parse_int(s, &i)) {
+        ox_str_cast_failed(s, "int");
+    }
+    return i;
+}
+
+static inline double ox_str_to_float(ox_str s) {
+    double x = 0;
+    if (!ox_parse_float(s, &x)) {
+        ox_str_cast_failed(s, "float");
+    }
+    return x;
+}
+
+static inline bool ox_str_to_bool(ox_str s) {
+    bool b = false;
+    if (!ox_parse_bool(s, &b)) {
+        ox_str_cast_failed(s, "bool");
+    }
+    return b;
+}
+
+// Input, read a line at a time. ox_rest is what hasn't been used of the current line.
+
+static char *ox_line = NULL;
+static size_t ox_line_capacity = 0;
+static const char *ox_rest = "";
+
+// Replaces the rest with the next line, returns false at the end of the input.
+static inline bool ox_next_line(void) {
+    size_t length = 0;
+    int c;
+    while ((c = getchar()) != EOF) {
+        if (length + 2 > ox_line_capacity) {
+            ox_line_capacity = ox_line_capacity * 2 + 64;
+            ox_line = realloc(ox_line, ox_line_capacity);
+            if (ox_line == NULL) {
+                ox_fail("Out of memory.");
+            }
+        }
+        ox_line[length++] = (char)c;
+        if (c == '\n') {
+            break;
+        }
+    }
+    if (length == 0) {
+        ox_rest = "";
+        return false;
+    }
+    ox_line[length] = '\0';
+    ox_rest = ox_line;
+    return true;
+}
+
+// Skips whitespace, line breaks included, returns false if the input ran out first.
+static inline bool ox_skip_whitespace(void) {
+    for (;;) {
+        while (isspace((unsigned char)*ox_rest)) {
+            ox_rest++;
+        }
+        if (*ox_rest) {
+            return true;
+        }
+        if (!ox_next_line()) {
+            return false;
+        }
+    }
+}
+
+static inline bool ox_eof(void) {
+    return !ox_skip_whitespace();
+}
+
+// The next word, words being separated by any whitespace. NULL at the end of the input.
+static inline ox_str ox_next_word(void) {
+    if (!ox_skip_whitespace()) {
+        return NULL;
+    }
+    size_t length = 0;
+    while (ox_rest[length] && !isspace((unsigned char)ox_rest[length])) {
+        length++;
+    }
+    ox_str word = ox_copy(ox_rest, length);
+    ox_rest += length;
+    return word;
+}
+
+static inline ox_str ox_read_word(const char *name) {
+    ox_str word = ox_next_word();
+    if (word == NULL) {
+        ox_fail("Unexpected end of input reading '%s'.", name);
+    }
+    return word;
+}
+
+static inline void ox_malformed_input(const char *name, const char *type, ox_str word) {
+    ox_fail("Malformed input for '%s': expected %s, got \"%s\".", name, type, word);
+}
+
+static inline int32_t ox_read_int(const char *name) {
+    ox_str word = ox_read_word(name);
+    int32_t i = 0;
+    if (!ox_parse_int(word, &i)) {
+        ox_malformed_input(name, "int", word);
+    }
+    return i;
+}
+
+static inline double ox_read_float(const char *name) {
+    ox_str word = ox_read_word(name);
+    double x = 0;
+    if (!ox_parse_float(word, &x)) {
+        ox_malformed_input(name, "float", word);
+    }
+    return x;
+}
+
+static inline bool ox_read_bool(const char *name) {
+    ox_str word = ox_read_word(name);
+    bool b = false;
+    if (!ox_parse_bool(word, &b)) {
+        ox_malformed_input(name, "bool", word);
+    }
+    return b;
+}
+
+static inline ox_str ox_read_string(const char *name) {
+    return ox_read_word(name);
+}
+
+// Builtins, named after the builtin with the parameter type added for overloads.
+
+static inline int32_t ox_abs_int(int32_t i) {
+    if (i == INT32_MIN) {
+        ox_fail("Integer overflow in 'abs'.");
+    }
+    return i < 0 ? -i : i;
+}
+
+static inline double ox_abs_float(double x) {
+    return fabs(x);
+}
+
+static inline int32_t ox_min_int(int32_t a, int32_t b) {
+    return a < b ? a : b;
+}
+
+static inline double ox_min_float(double a, double b) {
+    return fmin(a, b);
+}
+
+static inline int32_t ox_max_int(int32_t a, int32_t b) {
+    return a > b ? a : b;
+}
+
+static inline double ox_max_float(double a, double b) {
+    return fmax(a, b);
+}
+
+static inline double ox_sqrt(double x) {
+    if (x < 0) {
+        ox_fail("Square root of the negative number %s.", ox_float_debug(x));
+    }
+    return sqrt(x);
+}
+
+static inline int32_t ox_pow_int(int32_t a, int32_t b) {
+    return ox_int_pow(a, b);
+}
+
+static inline double ox_pow_float(double a, double b) {
+    return pow(a, b);
+}
+
+static inline double ox_floor(double x) {
+    return floor(x);
+}
+
+static inline double ox_ceil(double x) {
+    return ceil(x);
+}
+
+// Halves round away from zero.
+static inline double ox_round(double x) {
+    return round(x);
+}
+
+static inline int32_t ox_len(ox_str s) {
+    size_t count = ox_char_count(s);
+    if (count > INT32_MAX) {
+        ox_fail("Integer overflow in 'len'.");
+    }
+    return (int32_t)count;
+}
+
+static inline ox_str ox_substr(ox_str s, int32_t i, int32_t n) {
+    size_t count = ox_char_count(s);
+    if (i < 0 || n < 0 || (size_t)i + (size_t)n > count) {
+        ox_fail("substr(%" PRId32 ", %" PRId32 ") is out of range for a string of length %zu.", i, n,
+            count);
+    }
+    size_t start = ox_char_offset(s, (size_t)i);
+    size_t end = start + ox_char_offset(s + start, (size_t)n);
+    return ox_copy(s + start, end - start);
+}
+
+// Maps every character with towupper or towlower, which know about letters beyond
+// ASCII in a UTF-8 locale. Unlike Rust, a character never maps to several, ß stays ß.
+static inline ox_str ox_map_case(ox_str s, wint_t (*map)(wint_t)) {
+    static bool locale_set = false;
+    if (!locale_set) {
+        if (setlocale(LC_CTYPE, "C.UTF-8") == NULL) {
+            setlocale(LC_CTYPE, "en_US.UTF-8");
+        }
+        locale_set = true;
+    }
+    char *mapped = ox_alloc(strlen(s) * 2 + 1);
+    char *out = mapped;
+    while (*s) {
+        uint32_t c = ox_decode(&s);
+        uint32_t m = (uint32_t)map((wint_t)c);
+        // Case mappings don't leave the range of valid characters, but check anyway.
+        out += ox_encode(m <= 0x10FFFF ? m : c, out);
+    }
+    *out = '\0';
+    return mapped;
+}
+
+static inline ox_str ox_upper(ox_str s) {
+    return ox_map_case(s, towupper);
+}
+
+static inline ox_str ox_lower(ox_str s) {
+    return ox_map_case(s, towlower);
+}
+
+static inline ox_str ox_trim(ox_str s) {
+    while (isspace((unsigned char)*s)) {
+        s++;
+    }
+    size_t length = strlen(s);
+    while (length > 0 && isspace((unsigned char)s[length - 1])) {
+        length--;
+    }
+    return ox_copy(s, length);
+}
+
+static inline bool ox_contains(ox_str s, ox_str t) {
+    return strstr(s, t) != NULL;
+}
+
+static inline ox_str ox_replace(ox_str s, ox_str from, ox_str to) {
+    size_t lf = strlen(from), lt = strlen(to);
+    if (lf == 0) {
+        ox_fail("replace pattern can't be empty.");
+    }
+    size_t count = 0;
+    for (const char *p = strstr(s, from); p; p = strstr(p + lf, from)) {
+        count++;
+    }
+    char *replaced = ox_alloc(strlen(s) + count * lt + 1);
+    char *out = replaced;
+    const char *p;
+    while ((p = strstr(s, from)) != NULL) {
+        memcpy(out, s, (size_t)(p - s));
+        out += p - s;
+        memcpy(out, to, lt);
+        out += lt;
+        s = p + lf;
+    }
+    strcpy(out, s);
+    return replaced;
+}
+
+// Field i of s split at every separator.
+static inline ox_str ox_split(ox_str s, ox_str separator, int32_t i) {
+    size_t ls = strlen(separator);
+    if (ls == 0) {
+        ox_fail("split separator can't be empty.");
+    }
+    size_t count = 1;
+    for (const char *p = strstr(s, separator); p; p = strstr(p + ls, separator)) {
+        count++;
+    }
+    if (i < 0 || (size_t)i >= count) {
+        ox_fail("split field %" PRId32 " is out of range, there are %zu.", i, count);
+    }
+    const char *start = s;
+    for (int32_t field = 0; field < i; field++) {
+        start = strstr(start, separator) + ls;
+    }
+    const char *end = strstr(start, separator);
+    return ox_copy(start, end ? (size_t)(end - start) : strlen(start));
+}
+
+static inline int32_t ox_ord(ox_str s) {
+    if (ox_char_count(s) != 1) {
+        ox_fail("ord takes a single character, got \"%s\".", s);
+    }
+    return (int32_t)ox_decode(&s);
+}
+
+static inline ox_str ox_chr(int32_t i) {
+    if (i < 0 || i > 0x10FFFF || (i >= 0xD800 && i <= 0xDFFF)) {
+        ox_fail("%" PRId32 " is not a character code point.", i);
+    }
+    char s[4];
+    return ox_copy(s, ox_encode((uint32_t)i, s));
+}
+
+// The rest of the line a read stopped in if anything but whitespace is left of it, the
+// next line otherwise.
+static inline ox_str ox_readline(void) {
+    const char *p = ox_rest;
+    while (isspace((unsigned char)*p)) {
+        p++;
+    }
+    if (*p == '\0') {
+        if (!ox_next_line()) {
+            ox_fail("Unexpected end of input in 'readline'.");
+        }
+        p = ox_rest;
+        while (isspace((unsigned char)*p)) {
+            p++;
+        }
+    }
+    size_t length = strlen(p);
+    while (length > 0 && (p[length - 1] == '\n' || p[length - 1] == '\r')) {
+        length--;
+    }
+    ox_rest = "";
+    return ox_copy(p, length);
+}
+
+static inline int32_t ox_write(ox_str s) {
+    fputs(s, stdout);
+    return ox_len(s);
+}
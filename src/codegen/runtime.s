@@ -0,0 +1,261 @@
+# The runtime of x86-64 programs generated by oxide, mirroring the interpreter for ints
+# and bools. Output goes through printf, runtime errors end the program with exit status
+# 1 after printing "Runtime error: Line <n>: <message>" to stderr, like the C backend.
+# Code jumps to the error labels from anywhere, the stack is realigned before calling libc.
+
+    .section .rodata
+.Lformat_int:
+    .string "%d"
+.Lformat_str:
+    .string "%s"
+.Ltrue:
+    .string "true"
+.Lfalse:
+    .string "false"
+.Lformat_error:
+    .string "Runtime error: Line %d: %s\n"
+.Lformat_shift:
+    .string "Runtime error: Line %d: Shift amount %d out of range in %s.\n"
+.Lshl:
+    .string "SHL"
+.Lshr:
+    .string "SHR"
+.Ldivision_by_zero:
+    .string "Division by zero."
+.Loverflow_plus:
+    .string "Integer overflow in PLUS."
+.Loverflow_minus:
+    .string "Integer overflow in MINUS."
+.Loverflow_mult:
+    .string "Integer overflow in MULT."
+.Loverflow_div:
+    .string "Integer overflow in DIV."
+.Loverflow_mod:
+    .string "Integer overflow in MOD."
+.Loverflow_idiv:
+    .string "Integer overflow in IDIV."
+.Loverflow_imod:
+    .string "Integer overflow in IMOD."
+.Loverflow_pow:
+    .string "Integer overflow in POW."
+.Loverflow_abs:
+    .string "Integer overflow in 'abs'."
+.Lnegative_exponent:
+    .string "Negative exponent in integer POW, use a float base."
+
+    .data
+# The line of the statement being run, set before each one.
+ox_stmt_line:
+    .long 0
+
+    .text
+
+# Prints the message at %rdi and exits. Never returns, so %rbx is free to keep it.
+ox_fail:
+    movq %rdi, %rbx
+    andq $-16, %rsp
+    xorl %edi, %edi
+    call fflush@PLT
+    movl $2, %edi
+    leaq .Lformat_error(%rip), %rsi
+    movl ox_stmt_line(%rip), %edx
+    movq %rbx, %rcx
+    xorl %eax, %eax
+    call dprintf@PLT
+    movl $1, %edi
+    call exit@PLT
+
+# The shift amount is in %edi, the name of the operator at %rsi.
+ox_fail_shift:
+    movl %edi, %ebx
+    movq %rsi, %r12
+    andq $-16, %rsp
+    xorl %edi, %edi
+    call fflush@PLT
+    movl $2, %edi
+    leaq .Lformat_shift(%rip), %rsi
+    movl ox_stmt_line(%rip), %edx
+    movl %ebx, %ecx
+    movq %r12, %r8
+    xorl %eax, %eax
+    call dprintf@PLT
+    movl $1, %edi
+    call exit@PLT
+
+# Jump targets for runtime errors.
+
+ox_division_by_zero:
+    leaq .Ldivision_by_zero(%rip), %rdi
+    jmp ox_fail
+ox_overflow_plus:
+    leaq .Loverflow_plus(%rip), %rdi
+    jmp ox_fail
+ox_overflow_minus:
+    leaq .Loverflow_minus(%rip), %rdi
+    jmp ox_fail
+ox_overflow_mult:
+    leaq .Loverflow_mult(%rip), %rdi
+    jmp ox_fail
+ox_overflow_div:
+    leaq .Loverflow_div(%rip), %rdi
+    jmp ox_fail
+ox_overflow_mod:
+    leaq .Loverflow_mod(%rip), %rdi
+    jmp ox_fail
+ox_overflow_idiv:
+    leaq .Loverflow_idiv(%rip), %rdi
+    jmp ox_fail
+ox_overflow_imod:
+    leaq .Loverflow_imod(%rip), %rdi
+    jmp ox_fail
+ox_overflow_pow:
+    leaq .Loverflow_pow(%rip), %rdi
+    jmp ox_fail
+ox_overflow_abs:
+    leaq .Loverflow_abs(%rip), %rdi
+    jmp ox_fail
+ox_negative_exponent:
+    leaq .Lnegative_exponent(%rip), %rdi
+    jmp ox_fail
+
+# The shift amount is in %ecx.
+ox_shl_range:
+    movl %ecx, %edi
+    leaq .Lshl(%rip), %rsi
+    jmp ox_fail_shift
+ox_shr_range:
+    movl %ecx, %edi
+    leaq .Lshr(%rip), %rsi
+    jmp ox_fail_shift
+
+# Printing, the value is in %esi.
+
+ox_print_int:
+    leaq .Lformat_int(%rip), %rdi
+    xorl %eax, %eax
+    jmp printf@PLT
+
+ox_print_bool:
+    testl %esi, %esi
+    leaq .Ltrue(%rip), %rax
+    leaq .Lfalse(%rip), %rsi
+    cmovnz %rax, %rsi
+    leaq .Lformat_str(%rip), %rdi
+    xorl %eax, %eax
+    jmp printf@PLT
+
+# The string is at %rsi.
+ox_print_string:
+    leaq .Lformat_str(%rip), %rdi
+    xorl %eax, %eax
+    jmp printf@PLT
+
+# Integer division, the operands are in %edi and %esi and the result goes in %eax.
+
+ox_int_div:
+    movl %edi, %eax
+    testl %esi, %esi
+    je ox_division_by_zero
+    cmpl $-1, %esi
+    jne 1f
+    cmpl $-2147483648, %edi
+    je ox_overflow_div
+1:  cltd
+    idivl %esi
+    ret
+
+ox_int_mod:
+    movl %edi, %eax
+    testl %esi, %esi
+    je ox_division_by_zero
+    cmpl $-1, %esi
+    jne 1f
+    cmpl $-2147483648, %edi
+    je ox_overflow_mod
+1:  cltd
+    idivl %esi
+    movl %edx, %eax
+    ret
+
+# Rounds toward negative infinity where DIV truncates: one less when the remainder is
+# nonzero and its sign, the sign of the dividend, isn't the sign of the divisor.
+ox_int_idiv:
+    movl %edi, %eax
+    testl %esi, %esi
+    je ox_division_by_zero
+    cmpl $-1, %esi
+    jne 1f
+    cmpl $-2147483648, %edi
+    je ox_overflow_idiv
+1:  cltd
+    idivl %esi
+    testl %edx, %edx
+    je 2f
+    xorl %esi, %edx
+    jns 2f
+    decl %eax
+2:  ret
+
+# Takes the sign of the divisor where MOD takes the dividend's: the divisor is added to
+# a nonzero remainder of the other sign.
+ox_int_imod:
+    movl %edi, %eax
+    testl %esi, %esi
+    je ox_division_by_zero
+    cmpl $-1, %esi
+    jne 1f
+    cmpl $-2147483648, %edi
+    je ox_overflow_imod
+1:  cltd
+    idivl %esi
+    movl %edx, %eax
+    testl %edx, %edx
+    je 2f
+    xorl %esi, %edx
+    jns 2f
+    addl %esi, %eax
+2:  ret
+
+# Squares the base only while bits of the exponent are left, like Rust's checked_pow.
+ox_int_pow:
+    testl %esi, %esi
+    js ox_negative_exponent
+    movl $1, %eax
+1:  testl %esi, %esi
+    je 3f
+    testl $1, %esi
+    je 2f
+    imull %edi, %eax
+    jo ox_overflow_pow
+    cmpl $1, %esi
+    je 3f
+2:  shrl %esi
+    imull %edi, %edi
+    jo ox_overflow_pow
+    jmp 1b
+3:  ret
+
+# Builtins, named after the builtin with the parameter type added for overloads.
+
+ox_abs_int:
+    cmpl $-2147483648, %edi
+    je ox_overflow_abs
+    movl %edi, %eax
+    negl %eax
+    cmovs %edi, %eax
+    ret
+
+ox_min_int:
+    movl %edi, %eax
+    cmpl %esi, %edi
+    cmovg %esi, %eax
+    ret
+
+ox_max_int:
+    movl %edi, %eax
+    cmpl %esi, %edi
+    cmovl %esi, %eax
+    ret
+
+ox_pow_int:
+    jmp ox_int_pow
@@ -0,0 +1,1427 @@
+  ;; The runtime of WebAssembly modules generated by oxide, mirroring the interpreter: the
+  ;; same checked arithmetic, casts, builtins and float formatting. A string is the
+  ;; address of its length in bytes, an i32, followed by its UTF-8 bytes. Strings are
+  ;; never freed, $ox_heap is where the next one goes.
+  ;;
+  ;; The generator defines $ox_heap, $ox_stmt_line, set to the line of each statement
+  ;; before it runs, $ox_scratch, the address of the memory floats are converted in, and
+  ;; the $ox_str_* globals, the addresses of the strings used here, and imports the
+  ;; host's print and error functions.
+
+  ;; Runtime errors hand the line and message to the host and trap.
+  (func $ox_fail (param $message i32)
+    (call $ox_host_error
+      (global.get $ox_stmt_line)
+      (i32.add (local.get $message) (i32.const 4))
+      (i32.load (local.get $message)))
+    (unreachable))
+
+  (func $ox_alloc (param $size i32) (result i32)
+    (local $p i32)
+    (local $pages i32)
+    (local.set $p (global.get $ox_heap))
+    (if (i32.gt_u (local.get $size) (i32.sub (i32.const -65536) (local.get $p)))
+      (then (call $ox_fail (global.get $ox_str_out_of_memory))))
+    (global.set $ox_heap
+      (i32.and (i32.add (i32.add (local.get $p) (local.get $size)) (i32.const 3)) (i32.const -4)))
+    (local.set $pages
+      (i32.shr_u (i32.add (global.get $ox_heap) (i32.const 65535)) (i32.const 16)))
+    (if (i32.gt_u (local.get $pages) (memory.size))
+      (then
+        (if (i32.eq (memory.grow (i32.sub (local.get $pages) (memory.size))) (i32.const -1))
+          (then (call $ox_fail (global.get $ox_str_out_of_memory))))))
+    (local.get $p))
+
+  ;; A string of `length` bytes, to be filled in.
+  (func $ox_str_new (param $length i32) (result i32)
+    (local $s i32)
+    (local.set $s (call $ox_alloc (i32.add (local.get $length) (i32.const 4))))
+    (i32.store (local.get $s) (local.get $length))
+    (local.get $s))
+
+  (func $ox_str_len (param $s i32) (result i32)
+    (i32.load (local.get $s)))
+
+  ;; The address of byte `offset` of s.
+  (func $ox_byte (param $s i32) (param $offset i32) (result i32)
+    (i32.add (i32.add (local.get $s) (i32.const 4)) (local.get $offset)))
+
+  ;; A string holding `length` bytes from address p.
+  (func $ox_copy (param $p i32) (param $length i32) (result i32)
+    (local $s i32)
+    (local.set $s (call $ox_str_new (local.get $length)))
+    (memory.copy (call $ox_byte (local.get $s) (i32.const 0)) (local.get $p) (local.get $length))
+    (local.get $s))
+
+  (func $ox_print (param $s i32)
+    (call $ox_host_print (call $ox_byte (local.get $s) (i32.const 0)) (call $ox_str_len (local.get $s))))
+
+  ;; Integer arithmetic, overflow is an error.
+
+  (func $ox_checked (param $result i64) (param $message i32) (result i32)
+    (if (i32.or
+          (i64.lt_s (local.get $result) (i64.const -2147483648))
+          (i64.gt_s (local.get $result) (i64.const 2147483647)))
+      (then (call $ox_fail (local.get $message))))
+    (i32.wrap_i64 (local.get $result)))
+
+  (func $ox_int_add (param $a i32) (param $b i32) (result i32)
+    (call $ox_checked
+      (i64.add (i64.extend_i32_s (local.get $a)) (i64.extend_i32_s (local.get $b)))
+      (global.get $ox_str_overflow_plus)))
+
+  (func $ox_int_sub (param $a i32) (param $b i32) (result i32)
+    (call $ox_checked
+      (i64.sub (i64.extend_i32_s (local.get $a)) (i64.extend_i32_s (local.get $b)))
+      (global.get $ox_str_overflow_minus)))
+
+  (func $ox_int_mul (param $a i32) (param $b i32) (result i32)
+    (call $ox_checked
+      (i64.mul (i64.extend_i32_s (local.get $a)) (i64.extend_i32_s (local.get $b)))
+      (global.get $ox_str_overflow_mult)))
+
+  (func $ox_check_divisor (param $a i32) (param $b i32) (param $overflow i32)
+    (if (i32.eqz (local.get $b))
+      (then (call $ox_fail (global.get $ox_str_division_by_zero))))
+    (if (i32.and
+          (i32.eq (local.get $a) (i32.const -2147483648))
+          (i32.eq (local.get $b) (i32.const -1)))
+      (then (call $ox_fail (local.get $overflow)))))
+
+  (func $ox_int_div (param $a i32) (param $b i32) (result i32)
+    (call $ox_check_divisor (local.get $a) (local.get $b) (global.get $ox_str_overflow_div))
+    (i32.div_s (local.get $a) (local.get $b)))
+
+  (func $ox_int_mod (param $a i32) (param $b i32) (result i32)
+    (call $ox_check_divisor (local.get $a) (local.get $b) (global.get $ox_str_overflow_mod))
+    (i32.rem_s (local.get $a) (local.get $b)))
+
+  ;; Rounds toward negative infinity where DIV truncates.
+  (func $ox_int_idiv (param $a i32) (param $b i32) (result i32)
+    (call $ox_check_divisor (local.get $a) (local.get $b) (global.get $ox_str_overflow_idiv))
+    (i32.sub
+      (i32.div_s (local.get $a) (local.get $b))
+      (i32.and
+        (i32.ne (i32.rem_s (local.get $a) (local.get $b)) (i32.const 0))
+        (i32.ne
+          (i32.lt_s (local.get $a) (i32.const 0))
+          (i32.lt_s (local.get $b) (i32.const 0))))))
+
+  ;; Takes the sign of the divisor where MOD takes the dividend's.
+  (func $ox_int_imod (param $a i32) (param $b i32) (result i32)
+    (local $r i32)
+    (call $ox_check_divisor (local.get $a) (local.get $b) (global.get $ox_str_overflow_imod))
+    (local.set $r (i32.rem_s (local.get $a) (local.get $b)))
+    (if (result i32)
+        (i32.and
+          (i32.ne (local.get $r) (i32.const 0))
+          (i32.ne
+            (i32.lt_s (local.get $r) (i32.const 0))
+            (i32.lt_s (local.get $b) (i32.const 0))))
+      (then (i32.add (local.get $r) (local.get $b)))
+      (else (local.get $r))))
+
+  ;; Squares the base only while bits of the exponent are left, like Rust's checked_pow.
+  (func $ox_int_pow (param $a i32) (param $b i32) (result i32)
+    (local $acc i32)
+    (if (i32.lt_s (local.get $b) (i32.const 0))
+      (then (call $ox_fail (global.get $ox_str_negative_exponent))))
+    (local.set $acc (i32.const 1))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $b)))
+        (if (i32.and (local.get $b) (i32.const 1))
+          (then
+            (local.set $acc
+              (call $ox_checked
+                (i64.mul (i64.extend_i32_s (local.get $acc)) (i64.extend_i32_s (local.get $a)))
+                (global.get $ox_str_overflow_pow)))
+            (br_if $done (i32.eq (local.get $b) (i32.const 1)))))
+        (local.set $b (i32.shr_u (local.get $b) (i32.const 1)))
+        (local.set $a
+          (call $ox_checked
+            (i64.mul (i64.extend_i32_s (local.get $a)) (i64.extend_i32_s (local.get $a)))
+            (global.get $ox_str_overflow_pow)))
+        (br $next)))
+    (local.get $acc))
+
+  (func $ox_int_neg (param $a i32) (result i32)
+    (if (i32.eq (local.get $a) (i32.const -2147483648))
+      (then (call $ox_fail (global.get $ox_str_overflow_minus))))
+    (i32.sub (i32.const 0) (local.get $a)))
+
+  (func $ox_check_shift (param $b i32) (param $message i32)
+    (if (i32.ge_u (local.get $b) (i32.const 32))
+      (then
+        (call $ox_fail
+          (call $ox_concat3
+            (global.get $ox_str_shift_amount)
+            (call $ox_int_to_str (local.get $b))
+            (local.get $message))))))
+
+  ;; Bits shifted out are dropped.
+  (func $ox_int_shl (param $a i32) (param $b i32) (result i32)
+    (call $ox_check_shift (local.get $b) (global.get $ox_str_shl_range))
+    (i32.shl (local.get $a) (local.get $b)))
+
+  ;; Keeps the sign.
+  (func $ox_int_shr (param $a i32) (param $b i32) (result i32)
+    (call $ox_check_shift (local.get $b) (global.get $ox_str_shr_range))
+    (i32.shr_s (local.get $a) (local.get $b)))
+
+  ;; Float arithmetic that has no instruction of its own.
+
+  ;; A float raised to an int, multiplied out like Rust's powi.
+  (func $ox_float_powi (param $x f64) (param $m i32) (result f64)
+    (local $n i32)
+    (local $y f64)
+    ;; The magnitude of m, unsigned so that it holds -2^31.
+    (local.set $n
+      (select (i32.sub (i32.const 0) (local.get $m)) (local.get $m) (i32.lt_s (local.get $m) (i32.const 0))))
+    (local.set $y (select (local.get $x) (f64.const 1) (i32.and (local.get $n) (i32.const 1))))
+    (block $done
+      (loop $next
+        (local.set $n (i32.shr_u (local.get $n) (i32.const 1)))
+        (br_if $done (i32.eqz (local.get $n)))
+        (local.set $x (f64.mul (local.get $x) (local.get $x)))
+        (if (i32.and (local.get $n) (i32.const 1))
+          (then (local.set $y (f64.mul (local.get $y) (local.get $x)))))
+        (br $next)))
+    (select
+      (f64.div (f64.const 1) (local.get $y))
+      (local.get $y)
+      (i32.lt_s (local.get $m) (i32.const 0))))
+
+  ;; The remainder with the sign of x, exact like C's fmod: the mantissas are divided
+  ;; bit by bit.
+  (func $ox_float_mod (param $x f64) (param $y f64) (result f64)
+    (local $ux i64)
+    (local $uy i64)
+    (local $ex i32)
+    (local $ey i32)
+    (local $sign i64)
+    (local $i i64)
+    (local.set $ux (i64.reinterpret_f64 (local.get $x)))
+    (local.set $uy (i64.reinterpret_f64 (local.get $y)))
+    (local.set $ex (i32.wrap_i64 (i64.and (i64.shr_u (local.get $ux) (i64.const 52)) (i64.const 0x7FF))))
+    (local.set $ey (i32.wrap_i64 (i64.and (i64.shr_u (local.get $uy) (i64.const 52)) (i64.const 0x7FF))))
+    (local.set $sign (i64.and (local.get $ux) (i64.const 0x8000000000000000)))
+    ;; NaN for a zero or NaN divisor and an infinite or NaN dividend.
+    (if (i32.or
+          (i32.or (i64.eqz (i64.shl (local.get $uy) (i64.const 1))) (f64.ne (local.get $y) (local.get $y)))
+          (i32.eq (local.get $ex) (i32.const 0x7FF)))
+      (then
+        (return (f64.div (f64.mul (local.get $x) (local.get $y)) (f64.mul (local.get $x) (local.get $y))))))
+    (if (i64.le_u (i64.shl (local.get $ux) (i64.const 1)) (i64.shl (local.get $uy) (i64.const 1)))
+      (then
+        (if (i64.eq (i64.shl (local.get $ux) (i64.const 1)) (i64.shl (local.get $uy) (i64.const 1)))
+          (then (return (f64.mul (f64.const 0) (local.get $x)))))
+        (return (local.get $x))))
+    ;; The mantissas with their leading bits at bit 52, subnormals normalized.
+    (if (local.get $ex)
+      (then
+        (local.set $ux
+          (i64.or (i64.and (local.get $ux) (i64.const 0xFFFFFFFFFFFFF)) (i64.const 0x10000000000000))))
+      (else
+        (local.set $ex (i32.sub (i32.const 0) (i32.wrap_i64 (i64.clz (i64.shl (local.get $ux) (i64.const 12))))))
+        (local.set $ux (i64.shl (local.get $ux) (i64.extend_i32_u (i32.sub (i32.const 1) (local.get $ex)))))))
+    (if (local.get $ey)
+      (then
+        (local.set $uy
+          (i64.or (i64.and (local.get $uy) (i64.const 0xFFFFFFFFFFFFF)) (i64.const 0x10000000000000))))
+      (else
+        (local.set $ey (i32.sub (i32.const 0) (i32.wrap_i64 (i64.clz (i64.shl (local.get $uy) (i64.const 12))))))
+        (local.set $uy (i64.shl (local.get $uy) (i64.extend_i32_u (i32.sub (i32.const 1) (local.get $ey)))))))
+    (block $divided
+      (loop $next
+        (local.set $i (i64.sub (local.get $ux) (local.get $uy)))
+        (if (i64.ge_s (local.get $i) (i64.const 0))
+          (then
+            (if (i64.eqz (local.get $i))
+              (then (return (f64.mul (f64.const 0) (local.get $x)))))
+            (local.set $ux (local.get $i))))
+        (br_if $divided (i32.le_s (local.get $ex) (local.get $ey)))
+        (local.set $ux (i64.shl (local.get $ux) (i64.const 1)))
+        (local.set $ex (i32.sub (local.get $ex) (i32.const 1)))
+        (br $next)))
+    (block $normal
+      (loop $shift
+        (br_if $normal (i64.ne (i64.shr_u (local.get $ux) (i64.const 52)) (i64.const 0)))
+        (local.set $ux (i64.shl (local.get $ux) (i64.const 1)))
+        (local.set $ex (i32.sub (local.get $ex) (i32.const 1)))
+        (br $shift)))
+    (if (i32.gt_s (local.get $ex) (i32.const 0))
+      (then
+        (local.set $ux
+          (i64.or
+            (i64.sub (local.get $ux) (i64.const 0x10000000000000))
+            (i64.shl (i64.extend_i32_u (local.get $ex)) (i64.const 52)))))
+      (else
+        (local.set $ux (i64.shr_u (local.get $ux) (i64.extend_i32_u (i32.sub (i32.const 1) (local.get $ex)))))))
+    (f64.reinterpret_i64 (i64.or (local.get $ux) (local.get $sign))))
+
+  ;; Takes the sign of the divisor where MOD takes the dividend's.
+  (func $ox_float_imod (param $a f64) (param $b f64) (result f64)
+    (local $r f64)
+    (local.set $r (call $ox_float_mod (local.get $a) (local.get $b)))
+    (if (result f64)
+        (i32.and
+          (f64.ne (local.get $r) (f64.const 0))
+          (i32.ne (f64.lt (local.get $r) (f64.const 0)) (f64.lt (local.get $b) (f64.const 0))))
+      (then (f64.add (local.get $r) (local.get $b)))
+      (else (local.get $r))))
+
+  ;; Strings.
+
+  (func $ox_concat (param $a i32) (param $b i32) (result i32)
+    (local $s i32)
+    (local.set $s
+      (call $ox_str_new
+        (i32.add (call $ox_str_len (local.get $a)) (call $ox_str_len (local.get $b)))))
+    (memory.copy
+      (call $ox_byte (local.get $s) (i32.const 0))
+      (call $ox_byte (local.get $a) (i32.const 0))
+      (call $ox_str_len (local.get $a)))
+    (memory.copy
+      (call $ox_byte (local.get $s) (call $ox_str_len (local.get $a)))
+      (call $ox_byte (local.get $b) (i32.const 0))
+      (call $ox_str_len (local.get $b)))
+    (local.get $s))
+
+  (func $ox_concat3 (param $a i32) (param $b i32) (param $c i32) (result i32)
+    (call $ox_concat (call $ox_concat (local.get $a) (local.get $b)) (local.get $c)))
+
+  ;; Compares bytes like Rust compares strings: -1, 0 or 1.
+  (func $ox_str_cmp (param $a i32) (param $b i32) (result i32)
+    (local $la i32)
+    (local $lb i32)
+    (local $k i32)
+    (local $ca i32)
+    (local $cb i32)
+    (local.set $la (call $ox_str_len (local.get $a)))
+    (local.set $lb (call $ox_str_len (local.get $b)))
+    (block $done
+      (loop $next
+        (br_if $done
+          (i32.ge_u (local.get $k)
+            (select (local.get $la) (local.get $lb) (i32.lt_u (local.get $la) (local.get $lb)))))
+        (local.set $ca (i32.load8_u (call $ox_byte (local.get $a) (local.get $k))))
+        (local.set $cb (i32.load8_u (call $ox_byte (local.get $b) (local.get $k))))
+        (if (i32.ne (local.get $ca) (local.get $cb))
+          (then
+            (return
+              (select (i32.const -1) (i32.const 1) (i32.lt_u (local.get $ca) (local.get $cb))))))
+        (local.set $k (i32.add (local.get $k) (i32.const 1)))
+        (br $next)))
+    (i32.sub
+      (i32.gt_u (local.get $la) (local.get $lb))
+      (i32.lt_u (local.get $la) (local.get $lb))))
+
+  (func $ox_str_eq (param $a i32) (param $b i32) (result i32)
+    (i32.eqz (call $ox_str_cmp (local.get $a) (local.get $b))))
+
+  (func $ox_is_continuation (param $p i32) (result i32)
+    (i32.eq (i32.and (i32.load8_u (local.get $p)) (i32.const 0xC0)) (i32.const 0x80)))
+
+  ;; Number of characters.
+  (func $ox_char_count (param $s i32) (result i32)
+    (local $k i32)
+    (local $count i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $k) (call $ox_str_len (local.get $s))))
+        (if (i32.eqz (call $ox_is_continuation (call $ox_byte (local.get $s) (local.get $k))))
+          (then (local.set $count (i32.add (local.get $count) (i32.const 1)))))
+        (local.set $k (i32.add (local.get $k) (i32.const 1)))
+        (br $next)))
+    (local.get $count))
+
+  ;; The byte offset `index` characters after byte `offset` of s.
+  (func $ox_char_offset (param $s i32) (param $offset i32) (param $index i32) (result i32)
+    (block $done
+      (loop $next
+        (br_if $done
+          (i32.or
+            (i32.ge_u (local.get $offset) (call $ox_str_len (local.get $s)))
+            (i32.eqz (local.get $index))))
+        (local.set $offset (i32.add (local.get $offset) (i32.const 1)))
+        (block $char
+          (loop $continuation
+            (br_if $char (i32.ge_u (local.get $offset) (call $ox_str_len (local.get $s))))
+            (br_if $char
+              (i32.eqz (call $ox_is_continuation (call $ox_byte (local.get $s) (local.get $offset)))))
+            (local.set $offset (i32.add (local.get $offset) (i32.const 1)))
+            (br $continuation)))
+        (local.set $index (i32.sub (local.get $index) (i32.const 1)))
+        (br $next)))
+    (local.get $offset))
+
+  ;; Decodes the character at address p.
+  (func $ox_decode (param $p i32) (result i32)
+    (local $c i32)
+    (local $extra i32)
+    (local $k i32)
+    (local.set $c (i32.load8_u (local.get $p)))
+    (if (i32.lt_u (local.get $c) (i32.const 0x80))
+      (then (return (local.get $c))))
+    (local.set $extra
+      (select
+        (i32.const 3)
+        (select (i32.const 2) (i32.const 1) (i32.ge_u (local.get $c) (i32.const 0xE0)))
+        (i32.ge_u (local.get $c) (i32.const 0xF0))))
+    (local.set $c (i32.and (local.get $c) (i32.shr_u (i32.const 0x3F) (local.get $extra))))
+    (local.set $k (i32.const 1))
+    (block $done
+      (loop $next
+        (br_if $done (i32.gt_u (local.get $k) (local.get $extra)))
+        (local.set $c
+          (i32.or
+            (i32.shl (local.get $c) (i32.const 6))
+            (i32.and
+              (i32.load8_u (i32.add (local.get $p) (local.get $k)))
+              (i32.const 0x3F))))
+        (local.set $k (i32.add (local.get $k) (i32.const 1)))
+        (br $next)))
+    (local.get $c))
+
+  ;; Whether t occurs in s at byte offset k, which leaves room for it.
+  (func $ox_matches (param $s i32) (param $k i32) (param $t i32) (result i32)
+    (local $j i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $j) (call $ox_str_len (local.get $t))))
+        (if (i32.ne
+              (i32.load8_u (call $ox_byte (local.get $s) (i32.add (local.get $k) (local.get $j))))
+              (i32.load8_u (call $ox_byte (local.get $t) (local.get $j))))
+          (then (return (i32.const 0))))
+        (local.set $j (i32.add (local.get $j) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  ;; The byte offset of the first t in s at or after byte offset `from`, -1 if there
+  ;; is none.
+  (func $ox_find (param $s i32) (param $t i32) (param $from i32) (result i32)
+    (local $k i32)
+    (local.set $k (local.get $from))
+    (block $done
+      (loop $next
+        (br_if $done
+          (i32.gt_u
+            (i32.add (local.get $k) (call $ox_str_len (local.get $t)))
+            (call $ox_str_len (local.get $s))))
+        (if (call $ox_matches (local.get $s) (local.get $k) (local.get $t))
+          (then (return (local.get $k))))
+        (local.set $k (i32.add (local.get $k) (i32.const 1)))
+        (br $next)))
+    (i32.const -1))
+
+  ;; The number of non-overlapping ts in s, t isn't empty.
+  (func $ox_occurrences (param $s i32) (param $t i32) (result i32)
+    (local $k i32)
+    (local $count i32)
+    (block $done
+      (loop $next
+        (local.set $k (call $ox_find (local.get $s) (local.get $t) (local.get $k)))
+        (br_if $done (i32.lt_s (local.get $k) (i32.const 0)))
+        (local.set $count (i32.add (local.get $count) (i32.const 1)))
+        (local.set $k (i32.add (local.get $k) (call $ox_str_len (local.get $t))))
+        (br $next)))
+    (local.get $count))
+
+  ;; Numbers of up to 4096 bits for formatting and parsing floats exactly, 128 u32 limbs
+  ;; from the least significant. Bignum n is at $ox_big(n), there is room for six at
+  ;; $ox_scratch, followed by the digits of a float being formatted. The operations only
+  ;; look at the low $ox_big_size bytes, which each conversion sets to what its numbers
+  ;; can grow to.
+
+  (global $ox_big_size (mut i32) (i32.const 512))
+
+  (func $ox_big_reserve (param $bits i32)
+    (global.set $ox_big_size
+      (select
+        (i32.const 512)
+        (i32.shl (i32.add (i32.shr_u (local.get $bits) (i32.const 5)) (i32.const 1)) (i32.const 2))
+        (i32.gt_u (local.get $bits) (i32.const 4064)))))
+
+  (func $ox_big (param $n i32) (result i32)
+    (i32.add (global.get $ox_scratch) (i32.mul (local.get $n) (i32.const 512))))
+
+  (func $ox_big_set (param $a i32) (param $x i64)
+    (memory.fill (local.get $a) (i32.const 0) (i32.const 512))
+    (i64.store (local.get $a) (local.get $x)))
+
+  ;; a = a * m + c
+  (func $ox_big_mul_add (param $a i32) (param $m i32) (param $c i32)
+    (local $i i32)
+    (local $p i64)
+    ;; The carry is the high half of p.
+    (local.set $p (i64.shl (i64.extend_i32_u (local.get $c)) (i64.const 32)))
+    (loop $next
+      (local.set $p
+        (i64.add
+          (i64.mul
+            (i64.load32_u (i32.add (local.get $a) (local.get $i)))
+            (i64.extend_i32_u (local.get $m)))
+          (i64.shr_u (local.get $p) (i64.const 32))))
+      (i64.store32 (i32.add (local.get $a) (local.get $i)) (local.get $p))
+      (local.set $i (i32.add (local.get $i) (i32.const 4)))
+      (br_if $next (i32.lt_u (local.get $i) (global.get $ox_big_size)))))
+
+  (func $ox_big_mul_pow10 (param $a i32) (param $n i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.lt_u (local.get $n) (i32.const 9)))
+        (call $ox_big_mul_add (local.get $a) (i32.const 1000000000) (i32.const 0))
+        (local.set $n (i32.sub (local.get $n) (i32.const 9)))
+        (br $next)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $n)))
+        (call $ox_big_mul_add (local.get $a) (i32.const 10) (i32.const 0))
+        (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+        (br $next))))
+
+  ;; dst = a + b, dst can be either.
+  (func $ox_big_add (param $dst i32) (param $a i32) (param $b i32)
+    (local $i i32)
+    (local $sum i64)
+    (loop $next
+      (local.set $sum
+        (i64.add
+          (i64.add
+            (i64.load32_u (i32.add (local.get $a) (local.get $i)))
+            (i64.load32_u (i32.add (local.get $b) (local.get $i))))
+          (i64.shr_u (local.get $sum) (i64.const 32))))
+      (i64.store32 (i32.add (local.get $dst) (local.get $i)) (local.get $sum))
+      (local.set $i (i32.add (local.get $i) (i32.const 4)))
+      (br_if $next (i32.lt_u (local.get $i) (global.get $ox_big_size)))))
+
+  ;; a = a - b, b isn't larger.
+  (func $ox_big_sub (param $a i32) (param $b i32)
+    (local $i i32)
+    (local $difference i64)
+    (loop $next
+      (local.set $difference
+        (i64.sub
+          (i64.sub
+            (i64.load32_u (i32.add (local.get $a) (local.get $i)))
+            (i64.load32_u (i32.add (local.get $b) (local.get $i))))
+          (i64.shr_u (local.get $difference) (i64.const 63))))
+      (i64.store32 (i32.add (local.get $a) (local.get $i)) (local.get $difference))
+      (local.set $i (i32.add (local.get $i) (i32.const 4)))
+      (br_if $next (i32.lt_u (local.get $i) (global.get $ox_big_size)))))
+
+  ;; -1, 0 or 1.
+  (func $ox_big_cmp (param $a i32) (param $b i32) (result i32)
+    (local $i i32)
+    (local $x i32)
+    (local $y i32)
+    (local.set $i (global.get $ox_big_size))
+    (loop $next
+      (local.set $i (i32.sub (local.get $i) (i32.const 4)))
+      (local.set $x (i32.load (i32.add (local.get $a) (local.get $i))))
+      (local.set $y (i32.load (i32.add (local.get $b) (local.get $i))))
+      (if (i32.ne (local.get $x) (local.get $y))
+        (then (return (select (i32.const -1) (i32.const 1) (i32.lt_u (local.get $x) (local.get $y))))))
+      (br_if $next (local.get $i)))
+    (i32.const 0))
+
+  ;; a = a * 2^n
+  (func $ox_big_shl (param $a i32) (param $n i32)
+    (local $limbs i32)
+    (local $bits i64)
+    (local $i i32)
+    (local $j i32)
+    (local $v i64)
+    ;; Whole limbs as a byte offset, then bits.
+    (local.set $limbs (i32.shl (i32.shr_u (local.get $n) (i32.const 5)) (i32.const 2)))
+    (local.set $bits (i64.extend_i32_u (i32.and (local.get $n) (i32.const 31))))
+    (local.set $i (global.get $ox_big_size))
+    (loop $next
+      (local.set $i (i32.sub (local.get $i) (i32.const 4)))
+      ;; The limb that moves to i above the one below it, shifted up by the bits.
+      (local.set $j (i32.sub (local.get $i) (local.get $limbs)))
+      (local.set $v (i64.const 0))
+      (if (i32.ge_s (local.get $j) (i32.const 0))
+        (then
+          (local.set $v
+            (i64.shl (i64.load32_u (i32.add (local.get $a) (local.get $j))) (i64.const 32)))))
+      (if (i32.ge_s (local.get $j) (i32.const 4))
+        (then
+          (local.set $v
+            (i64.or
+              (local.get $v)
+              (i64.load32_u (i32.add (local.get $a) (i32.sub (local.get $j) (i32.const 4))))))))
+      (i64.store32
+        (i32.add (local.get $a) (local.get $i))
+        (i64.shr_u (i64.shl (local.get $v) (local.get $bits)) (i64.const 32)))
+      (br_if $next (local.get $i))))
+
+  ;; a = a / 2, rounded down.
+  (func $ox_big_shr1 (param $a i32)
+    (local $i i32)
+    (local $v i64)
+    (loop $next
+      (local.set $v (i64.load32_u (i32.add (local.get $a) (local.get $i))))
+      (if (i32.lt_u (i32.add (local.get $i) (i32.const 4)) (global.get $ox_big_size))
+        (then
+          (local.set $v
+            (i64.or
+              (local.get $v)
+              (i64.shl (i64.load32_u (i32.add (local.get $a) (i32.add (local.get $i) (i32.const 4))))
+                (i64.const 32))))))
+      (i64.store32 (i32.add (local.get $a) (local.get $i)) (i64.shr_u (local.get $v) (i64.const 1)))
+      (local.set $i (i32.add (local.get $i) (i32.const 4)))
+      (br_if $next (i32.lt_u (local.get $i) (global.get $ox_big_size)))))
+
+  ;; The number of bits up to the highest one.
+  (func $ox_big_bits (param $a i32) (result i32)
+    (local $i i32)
+    (local $x i32)
+    (local.set $i (global.get $ox_big_size))
+    (loop $next
+      (local.set $i (i32.sub (local.get $i) (i32.const 4)))
+      (local.set $x (i32.load (i32.add (local.get $a) (local.get $i))))
+      (if (local.get $x)
+        (then
+          (return
+            (i32.sub (i32.add (i32.shl (local.get $i) (i32.const 3)) (i32.const 32)) (i32.clz (local.get $x))))))
+      (br_if $next (local.get $i)))
+    (i32.const 0))
+
+  (func $ox_big_copy (param $dst i32) (param $a i32)
+    (memory.copy (local.get $dst) (local.get $a) (i32.const 512)))
+
+  ;; The way print shows values.
+
+  (func $ox_int_to_str (param $i i32) (result i32)
+    (local $v i64)
+    (local $t i64)
+    (local $n i32)
+    (local $s i32)
+    (local $p i32)
+    (local.set $v (i64.extend_i32_s (local.get $i)))
+    (if (i64.lt_s (local.get $v) (i64.const 0))
+      (then
+        (local.set $v (i64.sub (i64.const 0) (local.get $v)))
+        (local.set $n (i32.const 1))))
+    (local.set $t (local.get $v))
+    (loop $count
+      (local.set $n (i32.add (local.get $n) (i32.const 1)))
+      (local.set $t (i64.div_u (local.get $t) (i64.const 10)))
+      (br_if $count (i64.ne (local.get $t) (i64.const 0))))
+    (local.set $s (call $ox_str_new (local.get $n)))
+    (local.set $p (call $ox_byte (local.get $s) (local.get $n)))
+    (loop $digit
+      (local.set $p (i32.sub (local.get $p) (i32.const 1)))
+      (i32.store8 (local.get $p)
+        (i32.add (i32.wrap_i64 (i64.rem_u (local.get $v) (i64.const 10))) (i32.const 48)))
+      (local.set $v (i64.div_u (local.get $v) (i64.const 10)))
+      (br_if $digit (i64.ne (local.get $v) (i64.const 0))))
+    (if (i32.lt_s (local.get $i) (i32.const 0))
+      (then (i32.store8 (call $ox_byte (local.get $s) (i32.const 0)) (i32.const 45))))
+    (local.get $s))
+
+  (func $ox_bool_to_str (param $b i32) (result i32)
+    (select (global.get $ox_str_true) (global.get $ox_str_false) (local.get $b)))
+
+  ;; The shortest digits of a positive float that read back as it, the way Rust finds
+  ;; them (Burger and Dybvig's algorithm): x is 0.d1d2... times 10^k. The digits are
+  ;; stored after the bignums, the count and k are returned.
+  (func $ox_float_digits (param $x f64) (result i32 i32)
+    (local $bits i32)
+    (local $binary i64)
+    (local $f i64)
+    (local $biased i32)
+    (local $e i32)
+    (local $even i32)
+    (local $r i32)
+    (local $s i32)
+    (local $high i32)
+    (local $low i32)
+    (local $sum i32)
+    (local $k i32)
+    (local $count i32)
+    (local $digit i32)
+    (local $stop_low i32)
+    (local $stop_high i32)
+    (local.set $binary (i64.reinterpret_f64 (local.get $x)))
+    (local.set $f (i64.and (local.get $binary) (i64.const 0xFFFFFFFFFFFFF)))
+    (local.set $biased (i32.wrap_i64 (i64.shr_u (local.get $binary) (i64.const 52))))
+    (local.set $e (i32.const -1074))
+    (if (local.get $biased)
+      (then
+        (local.set $f (i64.or (local.get $f) (i64.const 0x10000000000000)))
+        (local.set $e (i32.sub (local.get $biased) (i32.const 1075)))))
+    (local.set $even (i64.eqz (i64.and (local.get $f) (i64.const 1))))
+    ;; An estimate of k that is never too large, corrected below.
+    (local.set $k
+      (i32.trunc_f64_s
+        (f64.ceil
+          (f64.sub
+            (f64.mul
+              (f64.convert_i32_s
+                (i32.sub
+                  (i32.add (local.get $e) (i32.const 63))
+                  (i32.wrap_i64 (i64.clz (local.get $f)))))
+              (f64.const 0.30102999566398120))
+            (f64.const 1e-10)))))
+    ;; The numbers below take up to 2^|e| or 10^|k| and 17 digits more.
+    (local.set $bits (i32.shr_u (i32.mul (call $ox_abs_int (local.get $k)) (i32.const 7)) (i32.const 1)))
+    (if (i32.gt_u (call $ox_abs_int (local.get $e)) (local.get $bits))
+      (then (local.set $bits (call $ox_abs_int (local.get $e)))))
+    (call $ox_big_reserve (i32.add (local.get $bits) (i32.const 96)))
+    ;; x is r / s, the floats next to it are half a gap, high above and low below, away.
+    (local.set $r (call $ox_big (i32.const 0)))
+    (local.set $s (call $ox_big (i32.const 1)))
+    (local.set $high (call $ox_big (i32.const 2)))
+    (local.set $low (call $ox_big (i32.const 3)))
+    (local.set $sum (call $ox_big (i32.const 4)))
+    ;; Below a power of 2 the gap is half as wide.
+    (if (i32.and
+          (i64.eq (local.get $f) (i64.const 0x10000000000000))
+          (i32.gt_u (local.get $biased) (i32.const 1)))
+      (then
+        (call $ox_big_set (local.get $r) (i64.shl (local.get $f) (i64.const 2)))
+        (call $ox_big_set (local.get $s) (i64.const 4))
+        (call $ox_big_set (local.get $high) (i64.const 2)))
+      (else
+        (call $ox_big_set (local.get $r) (i64.shl (local.get $f) (i64.const 1)))
+        (call $ox_big_set (local.get $s) (i64.const 2))
+        (call $ox_big_set (local.get $high) (i64.const 1))))
+    (call $ox_big_set (local.get $low) (i64.const 1))
+    (if (i32.ge_s (local.get $e) (i32.const 0))
+      (then
+        (call $ox_big_shl (local.get $r) (local.get $e))
+        (call $ox_big_shl (local.get $high) (local.get $e))
+        (call $ox_big_shl (local.get $low) (local.get $e)))
+      (else (call $ox_big_shl (local.get $s) (i32.sub (i32.const 0) (local.get $e)))))
+    (if (i32.ge_s (local.get $k) (i32.const 0))
+      (then (call $ox_big_mul_pow10 (local.get $s) (local.get $k)))
+      (else
+        (call $ox_big_mul_pow10 (local.get $r) (i32.sub (i32.const 0) (local.get $k)))
+        (call $ox_big_mul_pow10 (local.get $high) (i32.sub (i32.const 0) (local.get $k)))
+        (call $ox_big_mul_pow10 (local.get $low) (i32.sub (i32.const 0) (local.get $k)))))
+    ;; The upper bound of what reads back as x has to be below s, or reach it when x is
+    ;; even and rounding to even would read it back as x.
+    (block $done
+      (loop $next
+        (call $ox_big_add (local.get $sum) (local.get $r) (local.get $high))
+        (br_if $done
+          (i32.lt_s (call $ox_big_cmp (local.get $sum) (local.get $s)) (i32.eqz (local.get $even))))
+        (call $ox_big_mul_add (local.get $s) (i32.const 10) (i32.const 0))
+        (local.set $k (i32.add (local.get $k) (i32.const 1)))
+        (br $next)))
+    ;; Digits until the ones so far read back as x, the last one rounded.
+    (loop $next
+      (call $ox_big_mul_add (local.get $r) (i32.const 10) (i32.const 0))
+      (call $ox_big_mul_add (local.get $high) (i32.const 10) (i32.const 0))
+      (call $ox_big_mul_add (local.get $low) (i32.const 10) (i32.const 0))
+      (local.set $digit (i32.const 0))
+      (block $divided
+        (loop $subtract
+          (br_if $divided (i32.lt_s (call $ox_big_cmp (local.get $r) (local.get $s)) (i32.const 0)))
+          (call $ox_big_sub (local.get $r) (local.get $s))
+          (local.set $digit (i32.add (local.get $digit) (i32.const 1)))
+          (br $subtract)))
+      (local.set $stop_low
+        (i32.lt_s (call $ox_big_cmp (local.get $r) (local.get $low)) (local.get $even)))
+      (call $ox_big_add (local.get $sum) (local.get $r) (local.get $high))
+      (local.set $stop_high
+        (i32.gt_s
+          (call $ox_big_cmp (local.get $sum) (local.get $s))
+          (i32.sub (i32.const 0) (local.get $even))))
+      ;; Both digits read back as x, the nearer one is taken, the higher one on a tie.
+      (if (i32.and (local.get $stop_low) (local.get $stop_high))
+        (then
+          (call $ox_big_add (local.get $sum) (local.get $r) (local.get $r))
+          (local.set $stop_high
+            (i32.ge_s (call $ox_big_cmp (local.get $sum) (local.get $s)) (i32.const 0)))))
+      (i32.store8
+        (i32.add (call $ox_big (i32.const 6)) (local.get $count))
+        (i32.add (i32.add (local.get $digit) (local.get $stop_high)) (i32.const 48)))
+      (local.set $count (i32.add (local.get $count) (i32.const 1)))
+      (br_if $next (i32.eqz (i32.or (local.get $stop_low) (local.get $stop_high)))))
+    (local.get $count)
+    (local.get $k))
+
+  ;; The shortest digits that read back as the same float, written out without an
+  ;; exponent like Rust prints floats: 1e20 is 100000000000000000000, 0.1 is 0.1 and 3.0
+  ;; is 3.
+  (func $ox_float_to_str (param $x f64) (result i32)
+    (local $negative i32)
+    (local $count i32)
+    (local $k i32)
+    (local $digits i32)
+    (local $s i32)
+    (local $p i32)
+    (if (f64.ne (local.get $x) (local.get $x))
+      (then (return (global.get $ox_str_nan))))
+    (local.set $negative (i64.lt_s (i64.reinterpret_f64 (local.get $x)) (i64.const 0)))
+    (if (f64.eq (f64.abs (local.get $x)) (f64.const inf))
+      (then
+        (return
+          (select (global.get $ox_str_neg_inf) (global.get $ox_str_inf) (local.get $negative)))))
+    (if (f64.eq (local.get $x) (f64.const 0))
+      (then
+        (return
+          (select (global.get $ox_str_neg_zero) (global.get $ox_str_zero) (local.get $negative)))))
+    (call $ox_float_digits (f64.abs (local.get $x)))
+    (local.set $k)
+    (local.set $count)
+    (local.set $digits (call $ox_big (i32.const 6)))
+    (if (i32.le_s (local.get $k) (i32.const 0))
+      (then
+        ;; 0.000ddd
+        (local.set $s
+          (call $ox_str_new
+            (i32.add
+              (i32.add (local.get $negative) (i32.const 2))
+              (i32.sub (local.get $count) (local.get $k)))))
+        (local.set $p (call $ox_byte (local.get $s) (local.get $negative)))
+        (i32.store16 (local.get $p) (i32.const 0x2E30))
+        (memory.fill
+          (i32.add (local.get $p) (i32.const 2))
+          (i32.const 48)
+          (i32.sub (i32.const 0) (local.get $k)))
+        (memory.copy
+          (i32.sub (i32.add (local.get $p) (i32.const 2)) (local.get $k))
+          (local.get $digits)
+          (local.get $count)))
+      (else
+        (if (i32.ge_s (local.get $k) (local.get $count))
+          (then
+            ;; ddd000
+            (local.set $s (call $ox_str_new (i32.add (local.get $negative) (local.get $k))))
+            (local.set $p (call $ox_byte (local.get $s) (local.get $negative)))
+            (memory.copy (local.get $p) (local.get $digits) (local.get $count))
+            (memory.fill
+              (i32.add (local.get $p) (local.get $count))
+              (i32.const 48)
+              (i32.sub (local.get $k) (local.get $count))))
+          (else
+            ;; ddd.ddd
+            (local.set $s
+              (call $ox_str_new
+                (i32.add (i32.add (local.get $negative) (local.get $count)) (i32.const 1))))
+            (local.set $p (call $ox_byte (local.get $s) (local.get $negative)))
+            (memory.copy (local.get $p) (local.get $digits) (local.get $k))
+            (i32.store8 (i32.add (local.get $p) (local.get $k)) (i32.const 46))
+            (memory.copy
+              (i32.add (i32.add (local.get $p) (local.get $k)) (i32.const 1))
+              (i32.add (local.get $digits) (local.get $k))
+              (i32.sub (local.get $count) (local.get $k)))))))
+    (if (local.get $negative)
+      (then (i32.store8 (call $ox_byte (local.get $s) (i32.const 0)) (i32.const 45))))
+    (local.get $s))
+
+  ;; The way Rust debug prints a float, for error messages: whole numbers get a fraction,
+  ;; and numbers below 1e-4 or from 1e16 up an exponent, 1.5e-7.
+  (func $ox_float_debug (param $x f64) (result i32)
+    (local $abs f64)
+    (local $count i32)
+    (local $k i32)
+    (local $negative i32)
+    (local $s i32)
+    (local $p i32)
+    (local.set $abs (f64.abs (local.get $x)))
+    (if (i32.and
+          (f64.lt (local.get $abs) (f64.const inf))
+          (i32.or
+            (f64.ge (local.get $abs) (f64.const 1e16))
+            (i32.and (f64.ne (local.get $abs) (f64.const 0)) (f64.lt (local.get $abs) (f64.const 1e-4)))))
+      (then
+        (call $ox_float_digits (local.get $abs))
+        (local.set $k)
+        (local.set $count)
+        ;; d or d.ddd, then the exponent.
+        (local.set $negative (f64.lt (local.get $x) (f64.const 0)))
+        (local.set $s
+          (call $ox_str_new
+            (i32.add
+              (i32.add (local.get $negative) (local.get $count))
+              (i32.gt_u (local.get $count) (i32.const 1)))))
+        (local.set $p (call $ox_byte (local.get $s) (local.get $negative)))
+        (i32.store8 (local.get $p) (i32.load8_u (call $ox_big (i32.const 6))))
+        (if (i32.gt_u (local.get $count) (i32.const 1))
+          (then
+            (i32.store8 (i32.add (local.get $p) (i32.const 1)) (i32.const 46))
+            (memory.copy
+              (i32.add (local.get $p) (i32.const 2))
+              (i32.add (call $ox_big (i32.const 6)) (i32.const 1))
+              (i32.sub (local.get $count) (i32.const 1)))))
+        (if (local.get $negative)
+          (then (i32.store8 (call $ox_byte (local.get $s) (i32.const 0)) (i32.const 45))))
+        (return
+          (call $ox_concat3
+            (local.get $s)
+            (global.get $ox_str_exponent)
+            (call $ox_int_to_str (i32.sub (local.get $k) (i32.const 1)))))))
+    (local.set $s (call $ox_float_to_str (local.get $x)))
+    (if (i32.and
+          (f64.lt (local.get $abs) (f64.const inf))
+          (i32.lt_s (call $ox_find (local.get $s) (global.get $ox_str_period) (i32.const 0)) (i32.const 0)))
+      (then (return (call $ox_concat (local.get $s) (global.get $ox_str_point_zero)))))
+    (local.get $s))
+
+  ;; Casts, failing like value::cast does.
+
+  (func $ox_cast_failed (param $s i32) (param $to i32)
+    (call $ox_fail
+      (call $ox_concat3 (global.get $ox_str_cannot_convert) (local.get $s) (local.get $to))))
+
+  ;; An optional sign and digits, nothing else.
+  (func $ox_str_to_int (param $s i32) (result i32)
+    (local $p i32)
+    (local $end i32)
+    (local $c i32)
+    (local $negative i32)
+    (local $value i64)
+    (local.set $p (call $ox_byte (local.get $s) (i32.const 0)))
+    (local.set $end (call $ox_byte (local.get $s) (call $ox_str_len (local.get $s))))
+    (block $malformed
+      (if (i32.lt_u (local.get $p) (local.get $end))
+        (then
+          (local.set $c (i32.load8_u (local.get $p)))
+          (local.set $negative (i32.eq (local.get $c) (i32.const 45)))
+          (if (i32.or (local.get $negative) (i32.eq (local.get $c) (i32.const 43)))
+            (then (local.set $p (i32.add (local.get $p) (i32.const 1)))))))
+      (br_if $malformed (i32.ge_u (local.get $p) (local.get $end)))
+      (loop $digit
+        (local.set $c (i32.sub (i32.load8_u (local.get $p)) (i32.const 48)))
+        (br_if $malformed (i32.gt_u (local.get $c) (i32.const 9)))
+        (local.set $value
+          (i64.add
+            (i64.mul (local.get $value) (i64.const 10))
+            (i64.extend_i32_u (local.get $c))))
+        (br_if $malformed (i64.gt_s (local.get $value) (i64.const 2147483648)))
+        (local.set $p (i32.add (local.get $p) (i32.const 1)))
+        (br_if $digit (i32.lt_u (local.get $p) (local.get $end))))
+      (if (local.get $negative)
+        (then (local.set $value (i64.sub (i64.const 0) (local.get $value)))))
+      (br_if $malformed (i64.gt_s (local.get $value) (i64.const 2147483647)))
+      (return (i32.wrap_i64 (local.get $value))))
+    (call $ox_cast_failed (local.get $s) (global.get $ox_str_to_int))
+    (unreachable))
+
+  (func $ox_float_to_int (param $x f64) (result i32)
+    (local $truncated f64)
+    (local.set $truncated (f64.trunc (local.get $x)))
+    (if (i32.eqz
+          (i32.and
+            (f64.ge (local.get $truncated) (f64.const -2147483648))
+            (f64.le (local.get $truncated) (f64.const 2147483647))))
+      (then
+        (call $ox_fail
+          (call $ox_concat3
+            (global.get $ox_str_cannot_convert_float)
+            (call $ox_float_to_str (local.get $x))
+            (global.get $ox_str_float_to_int)))))
+    (i32.trunc_f64_s (local.get $truncated)))
+
+  ;; Whether the bytes from p to end are the lowercase word in any case.
+  (func $ox_is_word (param $p i32) (param $end i32) (param $word i32) (result i32)
+    (local $k i32)
+    (if (i32.ne (i32.sub (local.get $end) (local.get $p)) (call $ox_str_len (local.get $word)))
+      (then (return (i32.const 0))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $k) (call $ox_str_len (local.get $word))))
+        (if (i32.ne
+              (i32.or (i32.load8_u (i32.add (local.get $p) (local.get $k))) (i32.const 32))
+              (i32.load8_u (call $ox_byte (local.get $word) (local.get $k))))
+          (then (return (i32.const 0))))
+        (local.set $k (i32.add (local.get $k) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  ;; The float nearest to bignum 0, a number of `count` digits, times 10^e10. The
+  ;; quotient of it and a power of 2 is divided out to 53 bits, fewer for subnormals, and
+  ;; rounded half to even.
+  (func $ox_decimal_to_float (param $count i32) (param $e10 i32) (result f64)
+    (local $n i32)
+    (local $d i32)
+    (local $t i32)
+    (local $b i32)
+    (local $u i32)
+    (local $i i32)
+    (local $q i64)
+    (local $order i32)
+    (local.set $n (call $ox_big (i32.const 0)))
+    (local.set $d (call $ox_big (i32.const 1)))
+    (local.set $t (call $ox_big (i32.const 2)))
+    ;; The numbers below take up to 10^(count + |e10|) and 2^53 more.
+    (call $ox_big_reserve
+      (i32.add
+        (i32.shr_u
+          (i32.mul
+            (i32.add (local.get $count) (call $ox_abs_int (local.get $e10)))
+            (i32.const 7))
+          (i32.const 1))
+        (i32.const 160)))
+    (call $ox_big_set (local.get $d) (i64.const 1))
+    (if (i32.ge_s (local.get $e10) (i32.const 0))
+      (then (call $ox_big_mul_pow10 (local.get $n) (local.get $e10)))
+      (else (call $ox_big_mul_pow10 (local.get $d) (i32.sub (i32.const 0) (local.get $e10)))))
+    ;; 2^b <= n / d < 2^(b + 1)
+    (local.set $b (i32.sub (call $ox_big_bits (local.get $n)) (call $ox_big_bits (local.get $d))))
+    (if (i32.ge_s (local.get $b) (i32.const 0))
+      (then
+        (call $ox_big_copy (local.get $t) (local.get $d))
+        (call $ox_big_shl (local.get $t) (local.get $b))
+        (if (i32.lt_s (call $ox_big_cmp (local.get $n) (local.get $t)) (i32.const 0))
+          (then (local.set $b (i32.sub (local.get $b) (i32.const 1))))))
+      (else
+        (call $ox_big_copy (local.get $t) (local.get $n))
+        (call $ox_big_shl (local.get $t) (i32.sub (i32.const 0) (local.get $b)))
+        (if (i32.lt_s (call $ox_big_cmp (local.get $t) (local.get $d)) (i32.const 0))
+          (then (local.set $b (i32.sub (local.get $b) (i32.const 1)))))))
+    ;; The float is q times 2^u.
+    (local.set $u
+      (i32.sub
+        (select (local.get $b) (i32.const -1022) (i32.gt_s (local.get $b) (i32.const -1022)))
+        (i32.const 52)))
+    (if (i32.ge_s (local.get $u) (i32.const 0))
+      (then (call $ox_big_shl (local.get $d) (local.get $u)))
+      (else (call $ox_big_shl (local.get $n) (i32.sub (i32.const 0) (local.get $u)))))
+    (call $ox_big_copy (local.get $t) (local.get $d))
+    (call $ox_big_shl (local.get $t) (i32.const 52))
+    (local.set $i (i32.const 53))
+    (loop $bit
+      (local.set $q (i64.shl (local.get $q) (i64.const 1)))
+      (if (i32.ge_s (call $ox_big_cmp (local.get $n) (local.get $t)) (i32.const 0))
+        (then
+          (call $ox_big_sub (local.get $n) (local.get $t))
+          (local.set $q (i64.or (local.get $q) (i64.const 1)))))
+      (call $ox_big_shr1 (local.get $t))
+      (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+      (br_if $bit (local.get $i)))
+    ;; n is the remainder now.
+    (call $ox_big_add (local.get $n) (local.get $n) (local.get $n))
+    (local.set $order (call $ox_big_cmp (local.get $n) (local.get $d)))
+    (if (i32.or
+          (i32.gt_s (local.get $order) (i32.const 0))
+          (i32.and (i32.eqz (local.get $order)) (i32.wrap_i64 (i64.and (local.get $q) (i64.const 1)))))
+      (then (local.set $q (i64.add (local.get $q) (i64.const 1)))))
+    (if (i64.eq (local.get $q) (i64.const 0x20000000000000))
+      (then
+        (local.set $q (i64.const 0x10000000000000))
+        (local.set $u (i32.add (local.get $u) (i32.const 1)))))
+    ;; Subnormal, with an exponent field of 0.
+    (if (i64.lt_u (local.get $q) (i64.const 0x10000000000000))
+      (then (return (f64.reinterpret_i64 (local.get $q)))))
+    (if (i32.ge_s (i32.add (local.get $u) (i32.const 1075)) (i32.const 2047))
+      (then (return (f64.const inf))))
+    (f64.reinterpret_i64
+      (i64.or
+        (i64.shl (i64.extend_i32_u (i32.add (local.get $u) (i32.const 1075))) (i64.const 52))
+        (i64.and (local.get $q) (i64.const 0xFFFFFFFFFFFFF)))))
+
+  ;; The syntax Rust parses floats with: an optional sign, then inf, infinity or nan in
+  ;; any case, or digits with an optional fraction and exponent. The first 800
+  ;; significant digits are kept, enough to round right once a digit stands in for the
+  ;; nonzero ones after them.
+  (func $ox_str_to_float (param $s i32) (result f64)
+    (local $p i32)
+    (local $end i32)
+    (local $c i32)
+    (local $negative i32)
+    (local $digits i32)
+    (local $count i32)
+    (local $point i32)
+    (local $e10 i32)
+    (local $sticky i32)
+    (local $exponent i32)
+    (local $exponent_negative i32)
+    (local $x f64)
+    (local.set $p (call $ox_byte (local.get $s) (i32.const 0)))
+    (local.set $end (call $ox_byte (local.get $s) (call $ox_str_len (local.get $s))))
+    (block $malformed
+      (if (i32.lt_u (local.get $p) (local.get $end))
+        (then
+          (local.set $c (i32.load8_u (local.get $p)))
+          (local.set $negative (i32.eq (local.get $c) (i32.const 45)))
+          (if (i32.or (local.get $negative) (i32.eq (local.get $c) (i32.const 43)))
+            (then (local.set $p (i32.add (local.get $p) (i32.const 1)))))))
+      (if (i32.or
+            (call $ox_is_word (local.get $p) (local.get $end) (global.get $ox_str_inf))
+            (call $ox_is_word (local.get $p) (local.get $end) (global.get $ox_str_infinity)))
+        (then
+          (return (select (f64.const -inf) (f64.const inf) (local.get $negative)))))
+      (if (call $ox_is_word (local.get $p) (local.get $end) (global.get $ox_str_word_nan))
+        (then (return (f64.const nan))))
+      ;; The significant digits make up bignum 0, the number is it times 10^e10.
+      (call $ox_big_set (call $ox_big (i32.const 0)) (i64.const 0))
+      (block $mantissa
+        (loop $next
+          (br_if $mantissa (i32.ge_u (local.get $p) (local.get $end)))
+          (local.set $c (i32.load8_u (local.get $p)))
+          (local.set $p (i32.add (local.get $p) (i32.const 1)))
+          (if (i32.and (i32.eq (local.get $c) (i32.const 46)) (i32.eqz (local.get $point)))
+            (then
+              (local.set $point (i32.const 1))
+              (br $next)))
+          (local.set $c (i32.sub (local.get $c) (i32.const 48)))
+          (if (i32.gt_u (local.get $c) (i32.const 9))
+            (then
+              (local.set $p (i32.sub (local.get $p) (i32.const 1)))
+              (br $mantissa)))
+          (local.set $digits (i32.add (local.get $digits) (i32.const 1)))
+          (if (i32.or (local.get $count) (local.get $c))
+            (then
+              (if (i32.lt_u (local.get $count) (i32.const 800))
+                (then
+                  (call $ox_big_reserve (i32.add (i32.shl (local.get $count) (i32.const 2)) (i32.const 32)))
+                  (call $ox_big_mul_add (call $ox_big (i32.const 0)) (i32.const 10) (local.get $c))
+                  (local.set $count (i32.add (local.get $count) (i32.const 1)))
+                  (local.set $e10 (i32.sub (local.get $e10) (local.get $point))))
+                (else
+                  (local.set $sticky (i32.or (local.get $sticky) (local.get $c)))
+                  (local.set $e10 (i32.add (local.get $e10) (i32.eqz (local.get $point)))))))
+            ;; A leading zero.
+            (else (local.set $e10 (i32.sub (local.get $e10) (local.get $point)))))
+          (br $next)))
+      (br_if $malformed (i32.eqz (local.get $digits)))
+      (if (i32.and
+            (i32.lt_u (local.get $p) (local.get $end))
+            (i32.eq (i32.or (i32.load8_u (local.get $p)) (i32.const 32)) (i32.const 101)))
+        (then
+          (local.set $p (i32.add (local.get $p) (i32.const 1)))
+          (if (i32.lt_u (local.get $p) (local.get $end))
+            (then
+              (local.set $c (i32.load8_u (local.get $p)))
+              (local.set $exponent_negative (i32.eq (local.get $c) (i32.const 45)))
+              (if (i32.or (local.get $exponent_negative) (i32.eq (local.get $c) (i32.const 43)))
+                (then (local.set $p (i32.add (local.get $p) (i32.const 1)))))))
+          (br_if $malformed (i32.ge_u (local.get $p) (local.get $end)))
+          ;; Past 100000 every number is 0 or infinite anyway.
+          (loop $digit
+            (local.set $c (i32.sub (i32.load8_u (local.get $p)) (i32.const 48)))
+            (br_if $malformed (i32.gt_u (local.get $c) (i32.const 9)))
+            (local.set $exponent
+              (i32.add (i32.mul (local.get $exponent) (i32.const 10)) (local.get $c)))
+            (if (i32.gt_s (local.get $exponent) (i32.const 100000))
+              (then (local.set $exponent (i32.const 100000))))
+            (local.set $p (i32.add (local.get $p) (i32.const 1)))
+            (br_if $digit (i32.lt_u (local.get $p) (local.get $end))))
+          (local.set $e10
+            (i32.add
+              (local.get $e10)
+              (select
+                (i32.sub (i32.const 0) (local.get $exponent))
+                (local.get $exponent)
+                (local.get $exponent_negative))))))
+      (br_if $malformed (i32.ne (local.get $p) (local.get $end)))
+      (if (local.get $sticky)
+        (then
+          (call $ox_big_reserve (i32.add (i32.shl (local.get $count) (i32.const 2)) (i32.const 32)))
+          (call $ox_big_mul_add (call $ox_big (i32.const 0)) (i32.const 10) (i32.const 1))
+          (local.set $count (i32.add (local.get $count) (i32.const 1)))
+          (local.set $e10 (i32.sub (local.get $e10) (i32.const 1)))))
+      ;; The number is below 10^(count + e10).
+      (local.set $x
+        (if (result f64) (i32.eqz (local.get $count))
+          (then (f64.const 0))
+          (else
+            (if (result f64) (i32.gt_s (i32.add (local.get $count) (local.get $e10)) (i32.const 310))
+              (then (f64.const inf))
+              (else
+                (if (result f64)
+                  (i32.lt_s (i32.add (local.get $count) (local.get $e10)) (i32.const -324))
+                  (then (f64.const 0))
+                  (else (call $ox_decimal_to_float (local.get $count) (local.get $e10)))))))))
+      (return (select (f64.neg (local.get $x)) (local.get $x) (local.get $negative))))
+    (call $ox_cast_failed (local.get $s) (global.get $ox_str_to_float))
+    (unreachable))
+
+  (func $ox_str_to_bool (param $s i32) (result i32)
+    (if (call $ox_str_eq (local.get $s) (global.get $ox_str_true))
+      (then (return (i32.const 1))))
+    (if (call $ox_str_eq (local.get $s) (global.get $ox_str_false))
+      (then (return (i32.const 0))))
+    (call $ox_cast_failed (local.get $s) (global.get $ox_str_to_bool))
+    (unreachable))
+
+  ;; Builtins, named after the builtin with the parameter type added for overloads.
+
+  (func $ox_abs_int (param $i i32) (result i32)
+    (if (i32.eq (local.get $i) (i32.const -2147483648))
+      (then (call $ox_fail (global.get $ox_str_overflow_abs))))
+    (select
+      (i32.sub (i32.const 0) (local.get $i))
+      (local.get $i)
+      (i32.lt_s (local.get $i) (i32.const 0))))
+
+  (func $ox_min_int (param $a i32) (param $b i32) (result i32)
+    (select (local.get $a) (local.get $b) (i32.lt_s (local.get $a) (local.get $b))))
+
+  (func $ox_max_int (param $a i32) (param $b i32) (result i32)
+    (select (local.get $a) (local.get $b) (i32.gt_s (local.get $a) (local.get $b))))
+
+  (func $ox_pow_int (param $a i32) (param $b i32) (result i32)
+    (call $ox_int_pow (local.get $a) (local.get $b)))
+
+  (func $ox_abs_float (param $x f64) (result f64)
+    (f64.abs (local.get $x)))
+
+  ;; Like Rust's min and max, NaN only when both are.
+  (func $ox_min_float (param $a f64) (param $b f64) (result f64)
+    (if (f64.ne (local.get $a) (local.get $a))
+      (then (return (local.get $b))))
+    (if (f64.ne (local.get $b) (local.get $b))
+      (then (return (local.get $a))))
+    (f64.min (local.get $a) (local.get $b)))
+
+  (func $ox_max_float (param $a f64) (param $b f64) (result f64)
+    (if (f64.ne (local.get $a) (local.get $a))
+      (then (return (local.get $b))))
+    (if (f64.ne (local.get $b) (local.get $b))
+      (then (return (local.get $a))))
+    (f64.max (local.get $a) (local.get $b)))
+
+  (func $ox_sqrt (param $x f64) (result f64)
+    (if (f64.lt (local.get $x) (f64.const 0))
+      (then
+        (call $ox_fail
+          (call $ox_concat3
+            (global.get $ox_str_sqrt_negative)
+            (call $ox_float_debug (local.get $x))
+            (global.get $ox_str_period)))))
+    (f64.sqrt (local.get $x)))
+
+  (func $ox_floor (param $x f64) (result f64)
+    (f64.floor (local.get $x)))
+
+  (func $ox_ceil (param $x f64) (result f64)
+    (f64.ceil (local.get $x)))
+
+  ;; Halfway cases round away from zero, unlike f64.nearest.
+  (func $ox_round (param $x f64) (result f64)
+    (local $truncated f64)
+    (local.set $truncated (f64.trunc (local.get $x)))
+    (select
+      (f64.add (local.get $truncated) (f64.copysign (f64.const 1) (local.get $x)))
+      (local.get $truncated)
+      (f64.ge (f64.abs (f64.sub (local.get $x) (local.get $truncated))) (f64.const 0.5))))
+
+  (func $ox_len (param $s i32) (result i32)
+    (call $ox_char_count (local.get $s)))
+
+  (func $ox_substr (param $s i32) (param $i i32) (param $n i32) (result i32)
+    (local $count i32)
+    (local $start i32)
+    (local $end i32)
+    (local.set $count (call $ox_char_count (local.get $s)))
+    (if (i32.or
+          (i32.or
+            (i32.lt_s (local.get $i) (i32.const 0))
+            (i32.lt_s (local.get $n) (i32.const 0)))
+          (i64.gt_s
+            (i64.add (i64.extend_i32_s (local.get $i)) (i64.extend_i32_s (local.get $n)))
+            (i64.extend_i32_u (local.get $count))))
+      (then
+        (call $ox_fail
+          (call $ox_concat3
+            (call $ox_concat3
+              (global.get $ox_str_substr)
+              (call $ox_int_to_str (local.get $i))
+              (global.get $ox_str_comma))
+            (call $ox_concat3
+              (call $ox_int_to_str (local.get $n))
+              (global.get $ox_str_substr_range)
+              (call $ox_int_to_str (local.get $count)))
+            (global.get $ox_str_period)))))
+    (local.set $start (call $ox_char_offset (local.get $s) (i32.const 0) (local.get $i)))
+    (local.set $end (call $ox_char_offset (local.get $s) (local.get $start) (local.get $n)))
+    (call $ox_copy
+      (call $ox_byte (local.get $s) (local.get $start))
+      (i32.sub (local.get $end) (local.get $start))))
+
+  ;; Adds `shift` to every byte from `first` to `first` + 25. Only ASCII letters change
+  ;; case, unlike the interpreter.
+  (func $ox_map_case (param $s i32) (param $first i32) (param $shift i32) (result i32)
+    (local $mapped i32)
+    (local $p i32)
+    (local $end i32)
+    (local $c i32)
+    (local.set $mapped
+      (call $ox_copy (call $ox_byte (local.get $s) (i32.const 0)) (call $ox_str_len (local.get $s))))
+    (local.set $p (call $ox_byte (local.get $mapped) (i32.const 0)))
+    (local.set $end (call $ox_byte (local.get $mapped) (call $ox_str_len (local.get $mapped))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $p) (local.get $end)))
+        (local.set $c (i32.load8_u (local.get $p)))
+        (if (i32.le_u (i32.sub (local.get $c) (local.get $first)) (i32.const 25))
+          (then (i32.store8 (local.get $p) (i32.add (local.get $c) (local.get $shift)))))
+        (local.set $p (i32.add (local.get $p) (i32.const 1)))
+        (br $next)))
+    (local.get $mapped))
+
+  (func $ox_upper (param $s i32) (result i32)
+    (call $ox_map_case (local.get $s) (i32.const 97) (i32.const -32)))
+
+  (func $ox_lower (param $s i32) (result i32)
+    (call $ox_map_case (local.get $s) (i32.const 65) (i32.const 32)))
+
+  ;; ASCII whitespace, what isspace takes for whitespace in C.
+  (func $ox_is_space (param $p i32) (result i32)
+    (local $c i32)
+    (local.set $c (i32.load8_u (local.get $p)))
+    (i32.or
+      (i32.eq (local.get $c) (i32.const 32))
+      (i32.le_u (i32.sub (local.get $c) (i32.const 9)) (i32.const 4))))
+
+  (func $ox_trim (param $s i32) (result i32)
+    (local $start i32)
+    (local $end i32)
+    (local.set $end (call $ox_str_len (local.get $s)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $start) (local.get $end)))
+        (br_if $done (i32.eqz (call $ox_is_space (call $ox_byte (local.get $s) (local.get $start)))))
+        (local.set $start (i32.add (local.get $start) (i32.const 1)))
+        (br $next)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.le_u (local.get $end) (local.get $start)))
+        (br_if $done
+          (i32.eqz
+            (call $ox_is_space
+              (call $ox_byte (local.get $s) (i32.sub (local.get $end) (i32.const 1))))))
+        (local.set $end (i32.sub (local.get $end) (i32.const 1)))
+        (br $next)))
+    (call $ox_copy
+      (call $ox_byte (local.get $s) (local.get $start))
+      (i32.sub (local.get $end) (local.get $start))))
+
+  (func $ox_contains (param $s i32) (param $t i32) (result i32)
+    (i32.ge_s (call $ox_find (local.get $s) (local.get $t) (i32.const 0)) (i32.const 0)))
+
+  (func $ox_replace (param $s i32) (param $from i32) (param $to i32) (result i32)
+    (local $lf i32)
+    (local $lt i32)
+    (local $replaced i32)
+    (local $out i32)
+    (local $pos i32)
+    (local $k i32)
+    (local.set $lf (call $ox_str_len (local.get $from)))
+    (local.set $lt (call $ox_str_len (local.get $to)))
+    (if (i32.eqz (local.get $lf))
+      (then (call $ox_fail (global.get $ox_str_replace_empty))))
+    (local.set $replaced
+      (call $ox_str_new
+        (i32.add
+          (call $ox_str_len (local.get $s))
+          (i32.mul
+            (call $ox_occurrences (local.get $s) (local.get $from))
+            (i32.sub (local.get $lt) (local.get $lf))))))
+    (local.set $out (call $ox_byte (local.get $replaced) (i32.const 0)))
+    (block $done
+      (loop $next
+        (local.set $k (call $ox_find (local.get $s) (local.get $from) (local.get $pos)))
+        (br_if $done (i32.lt_s (local.get $k) (i32.const 0)))
+        (memory.copy
+          (local.get $out)
+          (call $ox_byte (local.get $s) (local.get $pos))
+          (i32.sub (local.get $k) (local.get $pos)))
+        (local.set $out (i32.add (local.get $out) (i32.sub (local.get $k) (local.get $pos))))
+        (memory.copy (local.get $out) (call $ox_byte (local.get $to) (i32.const 0)) (local.get $lt))
+        (local.set $out (i32.add (local.get $out) (local.get $lt)))
+        (local.set $pos (i32.add (local.get $k) (local.get $lf)))
+        (br $next)))
+    (memory.copy
+      (local.get $out)
+      (call $ox_byte (local.get $s) (local.get $pos))
+      (i32.sub (call $ox_str_len (local.get $s)) (local.get $pos)))
+    (local.get $replaced))
+
+  ;; Field i of s split at every separator.
+  (func $ox_split (param $s i32) (param $separator i32) (param $i i32) (result i32)
+    (local $count i32)
+    (local $start i32)
+    (local $end i32)
+    (local $field i32)
+    (if (i32.eqz (call $ox_str_len (local.get $separator)))
+      (then (call $ox_fail (global.get $ox_str_split_empty))))
+    (local.set $count
+      (i32.add (call $ox_occurrences (local.get $s) (local.get $separator)) (i32.const 1)))
+    (if (i32.ge_u (local.get $i) (local.get $count))
+      (then
+        (call $ox_fail
+          (call $ox_concat3
+            (call $ox_concat3
+              (global.get $ox_str_split_field)
+              (call $ox_int_to_str (local.get $i))
+              (global.get $ox_str_split_range))
+            (call $ox_int_to_str (local.get $count))
+            (global.get $ox_str_period)))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $field) (local.get $i)))
+        (local.set $start
+          (i32.add
+            (call $ox_find (local.get $s) (local.get $separator) (local.get $start))
+            (call $ox_str_len (local.get $separator))))
+        (local.set $field (i32.add (local.get $field) (i32.const 1)))
+        (br $next)))
+    (local.set $end (call $ox_find (local.get $s) (local.get $separator) (local.get $start)))
+    (if (i32.lt_s (local.get $end) (i32.const 0))
+      (then (local.set $end (call $ox_str_len (local.get $s)))))
+    (call $ox_copy
+      (call $ox_byte (local.get $s) (local.get $start))
+      (i32.sub (local.get $end) (local.get $start))))
+
+  (func $ox_ord (param $s i32) (result i32)
+    (if (i32.ne (call $ox_char_count (local.get $s)) (i32.const 1))
+      (then
+        (call $ox_fail
+          (call $ox_concat3
+            (global.get $ox_str_ord_single)
+            (local.get $s)
+            (global.get $ox_str_quote_period)))))
+    (call $ox_decode (call $ox_byte (local.get $s) (i32.const 0))))
+
+  (func $ox_chr (param $i i32) (result i32)
+    (local $n i32)
+    (local $s i32)
+    (local $k i32)
+    (local $c i32)
+    (if (i32.or
+          (i32.gt_u (local.get $i) (i32.const 0x10FFFF))
+          (i32.le_u (i32.sub (local.get $i) (i32.const 0xD800)) (i32.const 0x7FF)))
+      (then
+        (call $ox_fail
+          (call $ox_concat (call $ox_int_to_str (local.get $i)) (global.get $ox_str_chr_range)))))
+    (local.set $n
+      (select
+        (i32.const 1)
+        (select
+          (i32.const 2)
+          (select (i32.const 3) (i32.const 4) (i32.lt_u (local.get $i) (i32.const 0x10000)))
+          (i32.lt_u (local.get $i) (i32.const 0x800)))
+        (i32.lt_u (local.get $i) (i32.const 0x80))))
+    (local.set $s (call $ox_str_new (local.get $n)))
+    (local.set $c (local.get $i))
+    ;; Continuation bytes from the last, then the leading byte with what is left.
+    (local.set $k (i32.sub (local.get $n) (i32.const 1)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $k)))
+        (i32.store8 (call $ox_byte (local.get $s) (local.get $k))
+          (i32.or (i32.const 0x80) (i32.and (local.get $c) (i32.const 0x3F))))
+        (local.set $c (i32.shr_u (local.get $c) (i32.const 6)))
+        (local.set $k (i32.sub (local.get $k) (i32.const 1)))
+        (br $next)))
+    (i32.store8 (call $ox_byte (local.get $s) (i32.const 0))
+      (i32.or
+        (local.get $c)
+        (select
+          (i32.const 0)
+          (i32.and (i32.shl (i32.const 0xF0) (i32.sub (i32.const 4) (local.get $n))) (i32.const 0xF0))
+          (i32.eq (local.get $n) (i32.const 1)))))
+    (local.get $s))
+
+  (func $ox_write (param $s i32) (result i32)
+    (call $ox_print (local.get $s))
+    (call $ox_len (local.get $s)))
@@ -0,0 +1,614 @@
+use super::{check_standalone, emit_stmts, variables, CodegenError};
+use crate::{
+    builtins::{self, BUILTINS},
+    format::token_text,
+    operators,
+    parser::{
+        AssignStmt, BinaryExpr, CallExpr, CastExpr, CtrlStmt, Expr, IfStmt, LoopStmt, PostfixExpr,
+        PrefixExpr, PrintStmt, Program, Stmt, StmtList,
+    },
+    semantic::{ident_name, SymbolTable},
+    types::{binary_type, promote, unary_type},
+    value::Value,
+    Token,
+};
+use std::collections::HashMap;
+
+const RUNTIME: &str = include_str!("runtime.wat");
+
+// The strings runtime.wat uses, defined as $ox_str_<name> globals.
+const RUNTIME_STRINGS: &[(&str, &str)] = &[
+    ("true", "true"),
+    ("false", "false"),
+    ("out_of_memory", "Out of memory."),
+    ("division_by_zero", "Division by zero."),
+    ("overflow_plus", "Integer overflow in PLUS."),
+    ("overflow_minus", "Integer overflow in MINUS."),
+    ("overflow_mult", "Integer overflow in MULT."),
+    ("overflow_div", "Integer overflow in DIV."),
+    ("overflow_mod", "Integer overflow in MOD."),
+    ("overflow_idiv", "Integer overflow in IDIV."),
+    ("overflow_imod", "Integer overflow in IMOD."),
+    ("overflow_pow", "Integer overflow in POW."),
+    ("overflow_abs", "Integer overflow in 'abs'."),
+    (
+        "negative_exponent",
+        "Negative exponent in integer POW, use a float base.",
+    ),
+    ("shift_amount", "Shift amount "),
+    ("shl_range", " out of range in SHL."),
+    ("shr_range", " out of range in SHR."),
+    ("cannot_convert", "Cannot convert string \""),
+    ("to_int", "\" to int."),
+    ("to_bool", "\" to bool."),
+    ("substr", "substr("),
+    ("comma", ", "),
+    ("substr_range", ") is out of range for a string of length "),
+    ("period", "."),
+    ("replace_empty", "replace pattern can't be empty."),
+    ("split_empty", "split separator can't be empty."),
+    ("split_field", "split field "),
+    ("split_range", " is out of range, there are "),
+    ("ord_single", "ord takes a single character, got \""),
+    ("quote_period", "\"."),
+    ("chr_range", " is not a character code point."),
+    ("nan", "NaN"),
+    ("inf", "inf"),
+    ("neg_inf", "-inf"),
+    ("zero", "0"),
+    ("neg_zero", "-0"),
+    ("point_zero", ".0"),
+    ("exponent", "e"),
+    ("infinity", "infinity"),
+    ("word_nan", "nan"),
+    ("to_float", "\" to float."),
+    ("cannot_convert_float", "Cannot convert float "),
+    ("float_to_int", " to int."),
+    ("sqrt_negative", "Square root of the negative number "),
+];
+
+// Strings start after a few unused bytes, so no string is at address 0.
+const DATA_START: u32 = 16;
+const PAGE_SIZE: u32 = 65536;
+// The six bignums runtime.wat converts floats with and the digits of one.
+const SCRATCH_SIZE: u32 = 6 * 512 + 32;
+
+// Code leaving a value on the stack, and its type.
+type Operand = (String, Token);
+
+// Compiles a program to a WebAssembly text module. The module imports two functions from
+// the host, both taking the address and length of UTF-8 text in the exported memory, the
+// error its line first:
+//
+//     (import "env" "print" (func (param i32 i32)))      ; output, each print is one call
+//     (import "env" "error" (func (param i32 i32 i32)))  ; a runtime error, then main traps
+//
+// and exports `memory` and `main`, which runs the program. A float is an f64, every other
+// value an i32, a string being the address of its length followed by its bytes. Input
+// has nothing to map to without more from the host, and a float raised to a float
+// power can't be made to round like the interpreter's, both are reported as
+// CodegenErrors.
+pub struct Generator<'a> {
+    symbols: &'a SymbolTable,
+    code: String,
+    indent: usize,
+    labels: usize,
+    data_end: u32,
+    strings: HashMap<String, u32>,
+    // The line of the statement being emitted, lines in it count from here.
+    base: u32,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(symbols: &'a SymbolTable) -> Self {
+        Self {
+            symbols,
+            code: String::new(),
+            indent: 2,
+            labels: 0,
+            data_end: DATA_START,
+            strings: HashMap::new(),
+            base: 0,
+        }
+    }
+
+    pub fn emit_program(mut self, program: &Program) -> Result<String, CodegenError> {
+        check_standalone(self.symbols)?;
+        emit_stmts(program, |line, ctrl_stmt| {
+            self.base = line;
+            self.emit_ctrl_stmt(ctrl_stmt)
+        })?;
+        let globals: Vec<String> = RUNTIME_STRINGS
+            .iter()
+            .map(|(name, text)| {
+                let address = self.string(text);
+                format!("  (global $ox_str_{} i32 (i32.const {}))\n", name, address)
+            })
+            .collect();
+        let scratch = self.data_end.next_multiple_of(8);
+        let heap = scratch + SCRATCH_SIZE;
+        let mut module = String::from("(module\n");
+        let mut segments = vec![];
+        module.push_str("  (import \"env\" \"print\" (func $ox_host_print (param i32 i32)))\n");
+        module.push_str("  (import \"env\" \"error\" (func $ox_host_error (param i32 i32 i32)))\n");
+        module.push_str(&format!(
+            "  (memory (export \"memory\") {})\n",
+            heap.div_ceil(PAGE_SIZE)
+        ));
+        for (s, address) in &self.strings {
+            segments.push((*address, s.as_str()));
+        }
+        segments.sort();
+        for (address, s) in segments {
+            let mut bytes = (s.len() as u32).to_le_bytes().to_vec();
+            bytes.extend(s.as_bytes());
+            module.push_str(&format!(
+                "  (data (i32.const {}) {})\n",
+                address,
+                data_literal(&bytes)
+            ));
+        }
+        module.push_str(&globals.concat());
+        module.push_str(&format!(
+            "  (global $ox_scratch i32 (i32.const {}))\n",
+            scratch
+        ));
+        module.push_str(&format!(
+            "  (global $ox_heap (mut i32) (i32.const {}))\n",
+            heap
+        ));
+        module.push_str("  (global $ox_stmt_line (mut i32) (i32.const 0))\n\n");
+        module.push_str(RUNTIME);
+        module.push_str("\n  (func (export \"main\")\n");
+        // Definite assignment makes sure the zero a local starts with is never read.
+        for (name, type_specifier) in variables(program) {
+            module.push_str(&format!(
+                "    (local {} {})\n",
+                variable(&name),
+                wasm_type(&type_specifier)
+            ));
+        }
+        module.push_str(&self.code);
+        module.push_str("  )\n)\n");
+        Ok(module)
+    }
+
+    fn line(&mut self, line: String) {
+        let line = self.indented(line);
+        self.code.push_str(&line);
+    }
+
+    fn indented(&self, line: String) -> String {
+        format!("{}{}\n", "  ".repeat(self.indent), line)
+    }
+
+    // Emits the expression into code of its own, so what goes after it can be chosen
+    // once its type is known.
+    fn capture(&mut self, expr: &Expr) -> Result<Operand, CodegenError> {
+        let code = std::mem::take(&mut self.code);
+        let type_specifier = self.emit_expr(expr);
+        let captured = std::mem::replace(&mut self.code, code);
+        Ok((captured, type_specifier?))
+    }
+
+    // The address of a string literal, each one is stored once after the last.
+    fn string(&mut self, s: &str) -> u32 {
+        if let Some(address) = self.strings.get(s) {
+            return *address;
+        }
+        let address = self.data_end.next_multiple_of(4);
+        self.data_end = address + 4 + s.len() as u32;
+        self.strings.insert(s.to_string(), address);
+        address
+    }
+
+    fn label(&mut self) -> usize {
+        self.labels += 1;
+        self.labels
+    }
+
+    fn emit_stmt_list(&mut self, stmt_list: &StmtList) -> Result<(), CodegenError> {
+        for stmt in &stmt_list.stmts {
+            // Variables were declared as locals of main.
+            if let Stmt::Ctrl(ctrl_stmt) = stmt {
+                self.emit_ctrl_stmt(ctrl_stmt)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_ctrl_stmt(&mut self, ctrl_stmt: &CtrlStmt) -> Result<(), CodegenError> {
+        self.emit_stmt_line(ctrl_stmt.line());
+        match ctrl_stmt {
+            CtrlStmt::Assign(assign_stmt) => self.emit_assign_stmt(assign_stmt),
+            CtrlStmt::Print(print_stmt) => self.emit_print_stmt(print_stmt),
+            CtrlStmt::Read(read_stmt) => Err(no_input(read_stmt.line)),
+            CtrlStmt::If(if_stmt) => self.emit_if_stmt(if_stmt),
+            CtrlStmt::Loop(loop_stmt) => self.emit_loop_stmt(loop_stmt),
+        }
+    }
+
+    fn emit_assign_stmt(&mut self, assign_stmt: &AssignStmt) -> Result<(), CodegenError> {
+        let name = ident_name(&assign_stmt.ident);
+        let line = assign_stmt.line;
+        let declared = self.variable_type(name, line)?;
+        let value_type = match operators::compound_assignment(&assign_stmt.op) {
+            Some(op) => {
+                let lhs = self.indented(format!("local.get {}", variable(name)));
+                let rhs = self.capture(&assign_stmt.expr)?;
+                self.emit_binary_op(op, (lhs, declared.clone()), rhs, line)?
+            }
+            None => self.emit_expr(&assign_stmt.expr)?,
+        };
+        if value_type != declared {
+            return Err(CodegenError::new(
+                line,
+                format!(
+                    "Cannot assign {} to '{}' declared as {:?}.",
+                    token_text(&value_type),
+                    name,
+                    declared
+                ),
+            ));
+        }
+        self.line(format!("local.set {}", variable(name)));
+        Ok(())
+    }
+
+    // Joins the values into one line, so the host gets a print in a single call.
+    fn emit_print_stmt(&mut self, print_stmt: &PrintStmt) -> Result<(), CodegenError> {
+        let empty = self.string("");
+        self.line(format!("i32.const {}", empty));
+        for expr in &print_stmt.exprs {
+            match self.emit_expr(expr)? {
+                Token::INT => self.line("call $ox_int_to_str".to_string()),
+                Token::FLOAT => self.line("call $ox_float_to_str".to_string()),
+                Token::BOOL => self.line("call $ox_bool_to_str".to_string()),
+                _ => {}
+            }
+            self.line("call $ox_concat".to_string());
+        }
+        let newline = self.string("\n");
+        self.line(format!("i32.const {}", newline));
+        self.line("call $ox_concat".to_string());
+        self.line("call $ox_print".to_string());
+        Ok(())
+    }
+
+    fn emit_if_stmt(&mut self, if_stmt: &IfStmt) -> Result<(), CodegenError> {
+        self.emit_condition(&if_stmt.condition)?;
+        self.line("if".to_string());
+        self.indent += 1;
+        self.emit_stmt_list(&if_stmt.then_stmts)?;
+        self.indent -= 1;
+        if let Some(else_stmts) = &if_stmt.else_stmts {
+            self.line("else".to_string());
+            self.indent += 1;
+            self.emit_stmt_list(else_stmts)?;
+            self.indent -= 1;
+        }
+        self.line("end".to_string());
+        Ok(())
+    }
+
+    fn emit_stmt_line(&mut self, line: u32) {
+        self.line(format!(
+            "(global.set $ox_stmt_line (i32.const {}))",
+            self.base + line
+        ));
+    }
+
+    fn emit_loop_stmt(&mut self, loop_stmt: &LoopStmt) -> Result<(), CodegenError> {
+        let label = self.label();
+        self.line(format!("block $done{}", label));
+        self.line(format!("  loop $next{}", label));
+        self.indent += 2;
+        self.emit_stmt_line(loop_stmt.line);
+        self.emit_condition(&loop_stmt.condition)?;
+        self.line("i32.eqz".to_string());
+        self.line(format!("br_if $done{}", label));
+        self.emit_stmt_list(&loop_stmt.stmts)?;
+        self.line(format!("br $next{}", label));
+        self.indent -= 2;
+        self.line("  end".to_string());
+        self.line("end".to_string());
+        Ok(())
+    }
+
+    fn emit_condition(&mut self, condition: &Expr) -> Result<(), CodegenError> {
+        match self.emit_expr(condition)? {
+            Token::BOOL => Ok(()),
+            type_specifier => Err(CodegenError::new(
+                condition.position().0,
+                format!(
+                    "Condition must be bool, found {}.",
+                    token_text(&type_specifier)
+                ),
+            )),
+        }
+    }
+
+    fn variable_type(&self, name: &str, line: u32) -> Result<Token, CodegenError> {
+        match self.symbols.get(name) {
+            Some(symbol) => Ok(symbol.type_specifier.clone()),
+            None => Err(CodegenError::new(
+                line,
+                format!("Use of undeclared variable '{}'.", name),
+            )),
+        }
+    }
+
+    // Leaves the value of the expression on the stack and returns its type.
+    fn emit_expr(&mut self, expr: &Expr) -> Result<Token, CodegenError> {
+        let line = expr.position().0;
+        match expr {
+            Expr::Primary(primary) => match Value::from_literal(&primary.constant) {
+                Some(value) => Ok(self.emit_literal(&value)),
+                None if primary.constant == Token::EOF => Err(no_input(line)),
+                None => {
+                    let name = ident_name(&primary.constant);
+                    match self.symbols.constant(name) {
+                        Some(value) => Ok(self.emit_literal(&value.clone())),
+                        None => {
+                            let type_specifier = self.variable_type(name, line)?;
+                            self.line(format!("local.get {}", variable(name)));
+                            Ok(type_specifier)
+                        }
+                    }
+                }
+            },
+            Expr::Paren(paren) => self.emit_expr(&paren.expr),
+            Expr::Prefix(PrefixExpr { op, expr, .. }) | Expr::Postfix(PostfixExpr { op, expr }) => {
+                let operand_type = self.emit_expr(expr)?;
+                let result_type =
+                    unary_type(op, &operand_type).map_err(|msg| CodegenError::new(line, msg))?;
+                match op {
+                    Token::MINUS if operand_type == Token::FLOAT => {
+                        self.line("f64.neg".to_string())
+                    }
+                    Token::MINUS => self.line("call $ox_int_neg".to_string()),
+                    Token::NOT => self.line("i32.eqz".to_string()),
+                    Token::BITNOT => {
+                        self.line("i32.const -1".to_string());
+                        self.line("i32.xor".to_string());
+                    }
+                    _ => {}
+                }
+                Ok(result_type)
+            }
+            Expr::Binary(binary) => self.emit_binary_expr(binary, line),
+            Expr::Cast(cast) => self.emit_cast_expr(cast),
+            Expr::Call(call) => self.emit_call_expr(call),
+        }
+    }
+
+    fn emit_literal(&mut self, value: &Value) -> Token {
+        let instruction = match value {
+            Value::Int(i) => format!("i32.const {}", i),
+            Value::Float(f) => format!("f64.const {}", float_literal(*f)),
+            Value::Bool(b) => format!("i32.const {}", *b as i32),
+            Value::Str(s) => format!("i32.const {}", self.string(s)),
+        };
+        self.line(instruction);
+        value.type_specifier()
+    }
+
+    fn emit_binary_expr(&mut self, binary: &BinaryExpr, line: u32) -> Result<Token, CodegenError> {
+        if !matches!(binary.op, Token::ANDOP | Token::OROP) {
+            let lhs = self.capture(&binary.lhs)?;
+            let rhs = self.capture(&binary.rhs)?;
+            return self.emit_binary_op(&binary.op, lhs, rhs, line);
+        }
+        let lhs = self.emit_expr(&binary.lhs)?;
+        // The right operand is only evaluated when the left one doesn't decide the result.
+        binary_type(&binary.op, &lhs, &Token::BOOL).map_err(|msg| CodegenError::new(line, msg))?;
+        self.line("if (result i32)".to_string());
+        if binary.op == Token::OROP {
+            self.line("  i32.const 1".to_string());
+            self.line("else".to_string());
+        }
+        self.indent += 1;
+        let rhs = self.emit_expr(&binary.rhs)?;
+        binary_type(&binary.op, &Token::BOOL, &rhs).map_err(|msg| CodegenError::new(line, msg))?;
+        self.indent -= 1;
+        if binary.op == Token::ANDOP {
+            self.line("else".to_string());
+            self.line("  i32.const 0".to_string());
+        }
+        self.line("end".to_string());
+        Ok(Token::BOOL)
+    }
+
+    // Emits both operands, the int one of an int and a float widened right after it, then
+    // the operation.
+    fn emit_binary_op(
+        &mut self,
+        op: &Token,
+        lhs: Operand,
+        rhs: Operand,
+        line: u32,
+    ) -> Result<Token, CodegenError> {
+        let (lhs_type, rhs_type) = promote(op, &lhs.1, &rhs.1);
+        let result_type =
+            binary_type(op, &lhs_type, &rhs_type).map_err(|msg| CodegenError::new(line, msg))?;
+        if (op, &lhs_type, &rhs_type) == (&Token::POW, &Token::FLOAT, &Token::FLOAT) {
+            return Err(float_pow(line));
+        }
+        for ((code, type_specifier), to) in [(lhs, &lhs_type), (rhs, &rhs_type)] {
+            self.code.push_str(&code);
+            if &type_specifier != to {
+                self.line("f64.convert_i32_s".to_string());
+            }
+        }
+        match (op, &lhs_type) {
+            (Token::EQUALOP, Token::STRING) => self.line("call $ox_str_eq".to_string()),
+            (Token::LTHAN | Token::GTHAN, Token::STRING) => {
+                self.line("call $ox_str_cmp".to_string());
+                self.line("i32.const 0".to_string());
+                self.line(comparison(op).to_string());
+            }
+            (Token::PLUS, Token::STRING) => self.line("call $ox_concat".to_string()),
+            (Token::POW, Token::FLOAT) => self.line("call $ox_float_powi".to_string()),
+            (Token::MOD, Token::FLOAT) => self.line("call $ox_float_mod".to_string()),
+            (Token::IMOD, Token::FLOAT) => self.line("call $ox_float_imod".to_string()),
+            (Token::IDIV, Token::FLOAT) => {
+                self.line("f64.div".to_string());
+                self.line("f64.floor".to_string());
+            }
+            (_, Token::FLOAT) => self.line(format!("f64.{}", float_instruction(op))),
+            (Token::EQUALOP, _) => self.line("i32.eq".to_string()),
+            (Token::LTHAN | Token::GTHAN, _) => self.line(comparison(op).to_string()),
+            (Token::BITAND, _) => self.line("i32.and".to_string()),
+            (Token::BITOR, _) => self.line("i32.or".to_string()),
+            (Token::BITXOR, _) => self.line("i32.xor".to_string()),
+            // What is left are the int operations that check for overflow.
+            _ => self.line(format!("call $ox_int_{}", int_function(op))),
+        }
+        Ok(result_type)
+    }
+
+    fn emit_cast_expr(&mut self, cast: &CastExpr) -> Result<Token, CodegenError> {
+        let from = self.emit_expr(&cast.expr)?;
+        let to = cast.type_specifier.clone();
+        match (&from, &to) {
+            _ if from == to => {}
+            // A bool already is 0 or 1.
+            (Token::BOOL, Token::INT) => {}
+            (Token::FLOAT, Token::INT) => self.line("call $ox_float_to_int".to_string()),
+            (Token::INT | Token::BOOL, Token::FLOAT) => self.line("f64.convert_i32_s".to_string()),
+            (Token::INT, Token::BOOL) => {
+                self.line("i32.const 0".to_string());
+                self.line("i32.ne".to_string());
+            }
+            (Token::FLOAT, Token::BOOL) => {
+                self.line("f64.const 0".to_string());
+                self.line("f64.ne".to_string());
+            }
+            (Token::STRING, _) => self.line(format!("call $ox_str_to_{}", token_text(&to))),
+            _ => self.line(format!("call $ox_{}_to_str", token_text(&from))),
+        }
+        Ok(to)
+    }
+
+    fn emit_call_expr(&mut self, call: &CallExpr) -> Result<Token, CodegenError> {
+        let name = ident_name(&call.name);
+        if self.symbols.natives().get(name).is_some() {
+            return Err(CodegenError::new(
+                call.line,
+                format!("The native function '{}' can't be compiled.", name),
+            ));
+        }
+        let mut args = vec![];
+        for arg in &call.args {
+            args.push(self.capture(arg)?);
+        }
+        let arg_types: Vec<Option<Token>> = args.iter().map(|(_, t)| Some(t.clone())).collect();
+        let builtin = builtins::resolve(name, &arg_types)
+            .map_err(|msg| CodegenError::new(call.line, msg))?
+            .expect("argument types are known");
+        match (builtin.name, &builtin.returns) {
+            ("readline", _) => return Err(no_input(call.line)),
+            ("pow", Token::FLOAT) => return Err(float_pow(call.line)),
+            _ => {}
+        }
+        // An int argument for a float parameter is widened.
+        for ((code, arg_type), param) in args.into_iter().zip(builtin.params) {
+            self.code.push_str(&code);
+            if (&arg_type, param) == (&Token::INT, &Token::FLOAT) {
+                self.line("f64.convert_i32_s".to_string());
+            }
+        }
+        // Overloads get the type of their first parameter added, $ox_abs_int.
+        let overloaded = BUILTINS.iter().filter(|other| other.name == name).count() > 1;
+        match builtin.params.first() {
+            Some(param) if overloaded => {
+                self.line(format!("call $ox_{}_{}", name, token_text(param)))
+            }
+            _ => self.line(format!("call $ox_{}", name)),
+        }
+        Ok(builtin.returns.clone())
+    }
+}
+
+// powf comes from the platform's libm, which rounds differently from one to the next.
+fn float_pow(line: u32) -> CodegenError {
+    CodegenError::new(
+        line,
+        "Float powers with a float exponent can't be compiled to WebAssembly.".to_string(),
+    )
+}
+
+fn no_input(line: u32) -> CodegenError {
+    CodegenError::new(
+        line,
+        "Input can't be read by a WebAssembly module.".to_string(),
+    )
+}
+
+fn wasm_type(type_specifier: &Token) -> &'static str {
+    match type_specifier {
+        Token::FLOAT => "f64",
+        _ => "i32",
+    }
+}
+
+// Prefixed so an oxide variable can't clash with the runtime.
+fn variable(name: &str) -> String {
+    format!("$v_{}", name)
+}
+
+fn comparison(op: &Token) -> &'static str {
+    match op {
+        Token::LTHAN => "i32.lt_s",
+        _ => "i32.gt_s",
+    }
+}
+
+fn float_instruction(op: &Token) -> &'static str {
+    match op {
+        Token::PLUS => "add",
+        Token::MINUS => "sub",
+        Token::MULT => "mul",
+        Token::DIV => "div",
+        Token::EQUALOP => "eq",
+        Token::LTHAN => "lt",
+        _ => "gt",
+    }
+}
+
+fn int_function(op: &Token) -> &'static str {
+    match op {
+        Token::PLUS => "add",
+        Token::MINUS => "sub",
+        Token::MULT => "mul",
+        Token::DIV => "div",
+        Token::MOD => "mod",
+        Token::IDIV => "idiv",
+        Token::IMOD => "imod",
+        Token::POW => "pow",
+        Token::SHL => "shl",
+        _ => "shr",
+    }
+}
+
+// Debug formatting reads back as the same f64.
+fn float_literal(f: f64) -> String {
+    match f {
+        f if f.is_nan() => "nan".to_string(),
+        f if f.is_infinite() => format!("{}inf", if f < 0.0 { "-" } else { "" }),
+        f => format!("{:?}", f),
+    }
+}
+
+// Anything but printable ASCII is written as a hex escape, as are " and \.
+fn data_literal(data: &[u8]) -> String {
+    let mut literal = String::from("\"");
+    for byte in data {
+        match byte {
+            b' '..=b'~' if *byte != b'"' && *byte != b'\\' => literal.push(*byte as char),
+            _ => literal.push_str(&format!("\\{:02x}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
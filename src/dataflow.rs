@@ -0,0 +1,137 @@
+use crate::{
+    operators,
+    parser::{CtrlStmt, Expr, IfStmt, LoopStmt, Program, Stmt, StmtList},
+    semantic::{ident_name, SemanticError, SymbolTable},
+    Token,
+};
+use std::collections::HashSet;
+
+// Definite assignment analysis. Tracks the set of variables that are assigned on every
+// path reaching a statement and reports reads of variables outside that set.
+//
+// - An if statement assigns a variable only when both branches do, a missing else
+//   branch counts as a branch that assigns nothing.
+// - A loop body may run zero times, so nothing it assigns is definitely assigned
+//   afterwards. Reads inside the body are checked against the state before the loop.
+// - Conditions are not evaluated, so `if (true)` is treated like any other if.
+//
+// Constants and the host's globals are always assigned. Each variable is reported once,
+// at its first bad read.
+pub struct DefiniteAssignment<'a> {
+    symbols: &'a SymbolTable,
+    reported: HashSet<String>,
+    errors: Vec<SemanticError>,
+    // The line the top level statement being checked starts on, see Program::stmts.
+    line: u32,
+}
+
+impl<'a> DefiniteAssignment<'a> {
+    pub fn new(symbols: &'a SymbolTable) -> Self {
+        Self {
+            symbols,
+            reported: HashSet::new(),
+            errors: vec![],
+            line: 0,
+        }
+    }
+
+    pub fn check(mut self, program: &Program) -> Vec<SemanticError> {
+        let globals = self.symbols.globals().iter();
+        let mut assigned = globals.map(|(name, _)| name.clone()).collect();
+        for (line, stmt) in program.stmts() {
+            self.line = line;
+            assigned = self.check_stmt(stmt, assigned);
+        }
+        self.errors
+    }
+
+    // Returns the set of variables definitely assigned after the list runs.
+    fn check_stmt_list(
+        &mut self,
+        stmt_list: &StmtList,
+        mut assigned: HashSet<String>,
+    ) -> HashSet<String> {
+        for stmt in &stmt_list.stmts {
+            assigned = self.check_stmt(stmt, assigned);
+        }
+        assigned
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt, assigned: HashSet<String>) -> HashSet<String> {
+        match stmt {
+            Stmt::Ctrl(ctrl_stmt) => self.check_ctrl_stmt(ctrl_stmt, assigned),
+            Stmt::Decl(_) => assigned,
+        }
+    }
+
+    fn check_ctrl_stmt(
+        &mut self,
+        ctrl_stmt: &CtrlStmt,
+        mut assigned: HashSet<String>,
+    ) -> HashSet<String> {
+        match ctrl_stmt {
+            CtrlStmt::Assign(assign_stmt) => {
+                if operators::compound_assignment(&assign_stmt.op).is_some() {
+                    self.check_read(&assign_stmt.ident, assign_stmt.line, &assigned);
+                }
+                self.check_reads(&assign_stmt.expr, &assigned);
+                assigned.insert(ident_name(&assign_stmt.ident).to_string());
+                assigned
+            }
+            CtrlStmt::Print(print_stmt) => {
+                for expr in &print_stmt.exprs {
+                    self.check_reads(expr, &assigned);
+                }
+                assigned
+            }
+            CtrlStmt::Read(read_stmt) => {
+                for ident in &read_stmt.idents {
+                    assigned.insert(ident_name(ident).to_string());
+                }
+                assigned
+            }
+            CtrlStmt::If(if_stmt) => self.check_if_stmt(if_stmt, assigned),
+            CtrlStmt::Loop(loop_stmt) => self.check_loop_stmt(loop_stmt, assigned),
+        }
+    }
+
+    fn check_if_stmt(&mut self, if_stmt: &IfStmt, assigned: HashSet<String>) -> HashSet<String> {
+        self.check_reads(&if_stmt.condition, &assigned);
+        let after_then = self.check_stmt_list(&if_stmt.then_stmts, assigned.clone());
+        let after_else = match &if_stmt.else_stmts {
+            Some(else_stmts) => self.check_stmt_list(else_stmts, assigned),
+            None => assigned,
+        };
+        after_then.intersection(&after_else).cloned().collect()
+    }
+
+    fn check_loop_stmt(
+        &mut self,
+        loop_stmt: &LoopStmt,
+        assigned: HashSet<String>,
+    ) -> HashSet<String> {
+        self.check_reads(&loop_stmt.condition, &assigned);
+        self.check_stmt_list(&loop_stmt.stmts, assigned.clone());
+        assigned
+    }
+
+    fn check_reads(&mut self, expr: &Expr, assigned: &HashSet<String>) {
+        for primary in expr.idents() {
+            self.check_read(&primary.constant, primary.line, assigned);
+        }
+    }
+
+    fn check_read(&mut self, ident: &Token, line: u32, assigned: &HashSet<String>) {
+        let name = ident_name(ident);
+        let is_variable = self
+            .symbols
+            .get(name)
+            .is_some_and(|symbol| !symbol.is_const);
+        if is_variable && !assigned.contains(name) && self.reported.insert(name.to_string()) {
+            self.errors.push(SemanticError::new(
+                self.line + line,
+                format!("Variable '{}' may be used before being assigned.", name),
+            ));
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{
+    globals::Globals,
+    interpreter::{Interpreter, Limits, RuntimeError},
+    native::Natives,
+    optimizer::Optimizer,
+    output::{Capture, PrintFormat},
+    parser::{ParseError, Parser, Program},
+    semantic::{Analyzer, SemanticError},
+    value::Value,
+    Token,
+};
+use std::{collections::HashMap, error::Error, fmt};
+
+// Runs programs a host can't trust: analysis, optimization and interpretation in one go,
+// within the limits it was given. Output is captured rather than printed, and the input
+// is empty unless one is given with with_input, so a script never touches the host's
+// stdin or stdout.
+//
+//     let engine = Engine::new().with_limits(Limits {
+//         fuel: Some(100_000),
+//         ..Limits::default()
+//     });
+//     let outcome = engine.run_source(source)?;
+#[derive(Debug, Clone, Default)]
+pub struct Engine {
+    limits: Limits,
+    natives: Natives,
+    globals: Globals,
+    input: String,
+    print_format: PrintFormat,
+}
+
+// What a program that ran to halt left behind. The output is kept a line at a time, see
+// output::Capture, and the variables are every variable that was assigned a value, the
+// host's globals included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub output: Vec<String>,
+    pub variables: HashMap<String, Value>,
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    Parse(ParseError),
+    Semantic(Vec<SemanticError>),
+    Runtime(RuntimeError),
+}
+
+impl Error for EngineError {}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineError::Parse(error) => write!(f, "{}", error),
+            EngineError::Semantic(errors) => {
+                let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+            EngineError::Runtime(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    // The text read statements and readline read from.
+    pub fn with_input(mut self, input: impl Into<String>) -> Self {
+        self.input = input.into();
+        self
+    }
+
+    pub fn with_print_format(mut self, print_format: PrintFormat) -> Self {
+        self.print_format = print_format;
+        self
+    }
+
+    // Registers a native function for the programs this engine runs, see
+    // Natives::register.
+    pub fn register(
+        &mut self,
+        name: &str,
+        params: &[Token],
+        returns: Token,
+        function: impl Fn(&[Value]) -> Result<Value, RuntimeError> + Send + Sync + 'static,
+    ) -> Result<(), String> {
+        self.natives.register(name, params, returns, function)
+    }
+
+    // Declares a variable for the programs this engine runs, see Globals::set.
+    pub fn set(&mut self, name: &str, value: Value) -> Result<(), String> {
+        self.globals.set(name, value)
+    }
+
+    pub fn run_source(&self, source: &str) -> Result<Outcome, EngineError> {
+        let program = Parser::new(source)
+            .parse_program()
+            .map_err(EngineError::Parse)?;
+        self.run(program)
+    }
+
+    pub fn run(&self, program: Program) -> Result<Outcome, EngineError> {
+        let symbols = Analyzer::new()
+            .with_natives(&self.natives)
+            .with_globals(&self.globals)
+            .with_limits(self.limits)
+            .analyze(&program)
+            .map_err(EngineError::Semantic)?;
+        let program = Optimizer::new(&symbols)
+            .with_limits(self.limits)
+            .optimize_program(program);
+        let mut output = Capture::new();
+        let mut interpreter = Interpreter::new(&symbols)
+            .with_input(self.input.as_bytes())
+            .with_output(&mut output)
+            .with_print_format(self.print_format.clone())
+            .with_limits(self.limits);
+        interpreter.run(&program).map_err(EngineError::Runtime)?;
+        let variables = interpreter.variables().clone();
+        drop(interpreter);
+        Ok(Outcome {
+            output: output.into_lines(),
+            variables,
+        })
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    builtins::{self, Io},
+    parser::{BinaryExpr, CallExpr, Expr, PostfixExpr, PrefixExpr, PrimaryExpr},
+    semantic::ident_name,
+    value::{self, Value},
+    Token,
+};
+
+// Expression evaluation shared by constant folding in the analyzer and the interpreter,
+// implementors only decide what an identifier evaluates to. Errors are plain strings
+// that the caller wraps into its own error type.
+//
+// Evaluation order is fixed: operands are evaluated left to right, and the operands of
+// && and || chains are only evaluated until the result is known. `b && 1 / x > 0` never
+// divides when b is false, and `b || 1 / x > 0` never divides when b is true.
+pub trait Evaluator {
+    fn lookup(&mut self, name: &str) -> Result<Value, String>;
+
+    // Whether the input is used up, only the interpreter has one.
+    fn eof(&mut self) -> Result<bool, String> {
+        Err("eof is not a constant.".to_string())
+    }
+
+    // Calls a native function registered by the host, None if there is none by the
+    // call's name.
+    fn call_native(&mut self, _call: &CallExpr, _args: &[Value]) -> Option<Result<Value, String>> {
+        None
+    }
+
+    // The input and output used by the I/O builtins, likewise only the interpreter's.
+    fn io(&mut self) -> Option<&mut dyn Io> {
+        None
+    }
+
+    // Called before each expression is evaluated, the interpreter counts them against
+    // its fuel limit.
+    fn step(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    // Called with the length in characters of every string an expression evaluates to,
+    // and of a builtin's result before it's built when that can be much longer than its
+    // arguments, see builtins::result_length. The interpreter checks it against its string
+    // length limit.
+    fn check_string_length(&mut self, _length: usize) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn eval_expr(&mut self, expr: &Expr) -> Result<Value, String> {
+        self.step()?;
+        let value = self.eval_operation(expr)?;
+        if let Value::Str(s) = &value {
+            self.check_string_length(s.chars().count())?;
+        }
+        Ok(value)
+    }
+
+    fn eval_operation(&mut self, expr: &Expr) -> Result<Value, String> {
+        match expr {
+            Expr::Primary(primary) => self.eval_primary_expr(primary),
+            Expr::Paren(paren) => self.eval_expr(&paren.expr),
+            Expr::Prefix(PrefixExpr { op, expr, .. }) | Expr::Postfix(PostfixExpr { op, expr }) => {
+                let value = self.eval_expr(expr)?;
+                value::unary_op(op, value)
+            }
+            Expr::Binary(binary) => self.eval_binary_expr(binary),
+            Expr::Cast(cast) => {
+                let value = self.eval_expr(&cast.expr)?;
+                value::cast(&cast.type_specifier, value)
+            }
+            Expr::Call(call) => {
+                let args = call
+                    .args
+                    .iter()
+                    .map(|arg| self.eval_expr(arg))
+                    .collect::<Result<Vec<Value>, String>>()?;
+                // Natives can't share a name with a builtin.
+                if let Some(result) = self.call_native(call, &args) {
+                    return result;
+                }
+                let name = ident_name(&call.name);
+                if let Some(length) = builtins::result_length(name, &args) {
+                    self.check_string_length(length)?;
+                }
+                builtins::call(name, args, self.io())
+            }
+        }
+    }
+
+    fn eval_binary_expr(&mut self, expr: &BinaryExpr) -> Result<Value, String> {
+        let lhs = self.eval_expr(&expr.lhs)?;
+        match expr.op {
+            // && stops at the first false operand, || at the first true one.
+            Token::ANDOP | Token::OROP => {
+                let decisive = expr.op == Token::OROP;
+                if value::as_bool(&expr.op, &lhs)? == decisive {
+                    return Ok(Value::Bool(decisive));
+                }
+                let rhs = self.eval_expr(&expr.rhs)?;
+                Ok(Value::Bool(value::as_bool(&expr.op, &rhs)?))
+            }
+            _ => {
+                let rhs = self.eval_expr(&expr.rhs)?;
+                value::binary_op(&expr.op, lhs, rhs)
+            }
+        }
+    }
+
+    fn eval_primary_expr(&mut self, expr: &PrimaryExpr) -> Result<Value, String> {
+        match Value::from_literal(&expr.constant) {
+            Some(value) => Ok(value),
+            None if expr.constant == Token::EOF => self.eof().map(Value::Bool),
+            None => self.lookup(ident_name(&expr.constant)),
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{
+    parser::{CtrlStmt, DeclStmt, Expr, Program, Stmt, StmtList},
+    Token,
+};
+
+const INDENT: &str = "    ";
+
+// The source text of a token, as the lexer would read it back.
+pub fn token_text(token: &Token) -> String {
+    let text = match token {
+        Token::INIT => "init",
+        Token::HALT => "halt",
+        Token::PRINT => "print",
+        Token::READ => "read",
+        Token::IF => "if",
+        Token::THEN => "then",
+        Token::ELSE => "else",
+        Token::ENDIF => "endif",
+        Token::WHILE => "while",
+        Token::DO => "do",
+        Token::ENDWHILE => "endwhile",
+        Token::CONST => "const",
+        Token::INT => "int",
+        Token::FLOAT => "float",
+        Token::BOOL => "bool",
+        Token::STRING => "string",
+        Token::TRUE => "true",
+        Token::FALSE => "false",
+        Token::EOF => "eof",
+        Token::OPENPAREN => "(",
+        Token::CLOSEPAREN => ")",
+        Token::COMMA => ",",
+        Token::SEMICOL => ";",
+        Token::OROP => "||",
+        Token::ANDOP => "&&",
+        Token::NOT => "!",
+        Token::GTHAN => ">",
+        Token::LTHAN => "<",
+        Token::EQUALOP => "==",
+        Token::ASSOP => "=",
+        Token::PLUSASSOP => "+=",
+        Token::MINUSASSOP => "-=",
+        Token::MULTASSOP => "*=",
+        Token::DIVASSOP => "/=",
+        Token::MODASSOP => "%=",
+        Token::PLUS => "+",
+        Token::MINUS => "-",
+        Token::MULT => "*",
+        Token::DIV => "/",
+        Token::MOD => "%",
+        Token::POW => "**",
+        Token::IDIV => "div",
+        Token::IMOD => "mod",
+        Token::BITAND => "&",
+        Token::BITOR => "|",
+        Token::BITXOR => "^",
+        Token::BITNOT => "~",
+        Token::SHL => "<<",
+        Token::SHR => ">>",
+        // Debug keeps the decimal point on whole floats.
+        Token::FCONST(f) => return format!("{:?}", f),
+        Token::ICONST(i) => return i.to_string(),
+        Token::BCONST(b) => return b.to_string(),
+        Token::SCONST(s) => return format!("\"{}\"", s),
+        Token::IDENT(name) => return name.clone(),
+        Token::ERROR(msg) => return msg.clone(),
+        Token::DONE | Token::WHITESPACE | Token::COMMENT => "",
+    };
+    text.to_string()
+}
+
+// Reprints a parsed program in the canonical layout: one statement per line, four
+// spaces of indentation per block and single spaces around binary operators.
+//
+// Comments aren't part of the tree, they are passed in from Parser::comments and
+// placed by line: a comment on the same line as a statement trails it, any other
+// comment is printed on its own line before the next statement that follows it.
+pub struct Formatter<'a> {
+    comments: &'a [(u32, String)],
+    next_comment: usize,
+    out: String,
+    // The line the top level statement being printed starts on, see Program::stmts.
+    line: u32,
+}
+
+impl<'a> Formatter<'a> {
+    pub fn new(comments: &'a [(u32, String)]) -> Self {
+        Self {
+            comments,
+            next_comment: 0,
+            out: String::new(),
+            line: 0,
+        }
+    }
+
+    pub fn format_program(mut self, program: &Program) -> String {
+        self.out.push_str("init\n");
+        for (line, stmt) in program.stmts() {
+            self.line = line;
+            self.format_stmt(stmt, 1);
+        }
+        self.leading_comments(u32::MAX, 1);
+        self.out.push_str("halt\n");
+        self.out
+    }
+
+    fn leading_comments(&mut self, before_line: u32, depth: usize) {
+        while let Some((line, text)) = self.comments.get(self.next_comment) {
+            if *line >= before_line {
+                break;
+            }
+            self.out.push_str(&INDENT.repeat(depth));
+            self.out.push_str(&format!("//{}\n", text));
+            self.next_comment += 1;
+        }
+    }
+
+    // Ends the line started by a statement, with its trailing comment if it has one.
+    fn end_line(&mut self, line: u32) {
+        if let Some((comment_line, text)) = self.comments.get(self.next_comment) {
+            if *comment_line == line {
+                self.out.push_str(&format!(" //{}", text));
+                self.next_comment += 1;
+            }
+        }
+        self.out.push('\n');
+    }
+
+    fn format_stmt_list(&mut self, stmt_list: &StmtList, depth: usize) {
+        for stmt in &stmt_list.stmts {
+            self.format_stmt(stmt, depth);
+        }
+    }
+
+    fn format_stmt(&mut self, stmt: &Stmt, depth: usize) {
+        let line = self.line + stmt.line();
+        self.leading_comments(line, depth);
+        self.out.push_str(&INDENT.repeat(depth));
+        match stmt {
+            Stmt::Decl(decl_stmt) => {
+                self.out.push_str(&format_decl_stmt(decl_stmt));
+                self.out.push(';');
+                self.end_line(line);
+            }
+            Stmt::Ctrl(CtrlStmt::Assign(assign_stmt)) => {
+                self.out.push_str(&format!(
+                    "{} {} {};",
+                    token_text(&assign_stmt.ident),
+                    token_text(&assign_stmt.op),
+                    format_expr(&assign_stmt.expr)
+                ));
+                self.end_line(line);
+            }
+            Stmt::Ctrl(CtrlStmt::Print(print_stmt)) => {
+                let exprs: Vec<String> = print_stmt.exprs.iter().map(format_expr).collect();
+                self.out.push_str(&format!("print({});", exprs.join(", ")));
+                self.end_line(line);
+            }
+            Stmt::Ctrl(CtrlStmt::Read(read_stmt)) => {
+                let idents: Vec<String> = read_stmt.idents.iter().map(token_text).collect();
+                self.out.push_str(&format!("read({});", idents.join(", ")));
+                self.end_line(line);
+            }
+            Stmt::Ctrl(CtrlStmt::If(if_stmt)) => {
+                self.out
+                    .push_str(&format!("if ({}) then", format_expr(&if_stmt.condition)));
+                self.end_line(line);
+                self.format_stmt_list(&if_stmt.then_stmts, depth + 1);
+                if let Some(else_stmts) = &if_stmt.else_stmts {
+                    self.out.push_str(&INDENT.repeat(depth));
+                    self.out.push_str("else\n");
+                    self.format_stmt_list(else_stmts, depth + 1);
+                }
+                self.out.push_str(&INDENT.repeat(depth));
+                self.out.push_str("endif;\n");
+            }
+            Stmt::Ctrl(CtrlStmt::Loop(loop_stmt)) => {
+                self.out
+                    .push_str(&format!("while ({}) do", format_expr(&loop_stmt.condition)));
+                self.end_line(line);
+                self.format_stmt_list(&loop_stmt.stmts, depth + 1);
+                self.out.push_str(&INDENT.repeat(depth));
+                self.out.push_str("endwhile;\n");
+            }
+        }
+    }
+}
+
+pub fn format_decl_stmt(decl_stmt: &DeclStmt) -> String {
+    let idents: Vec<String> = decl_stmt.idents.iter().map(token_text).collect();
+    let mut text = format!(
+        "{}{} {}",
+        if decl_stmt.is_const { "const " } else { "" },
+        token_text(&decl_stmt.type_specifier),
+        idents.join(", ")
+    );
+    if let Some(initializer) = &decl_stmt.initializer {
+        text.push_str(&format!(" = {}", format_expr(initializer)));
+    }
+    text
+}
+
+pub fn format_expr(expr: &Expr) -> String {
+    match expr {
+        Expr::Primary(primary) => token_text(&primary.constant),
+        Expr::Paren(paren) => format!("({})", format_expr(&paren.expr)),
+        Expr::Prefix(prefix) => format!("{}{}", token_text(&prefix.op), format_expr(&prefix.expr)),
+        Expr::Cast(cast) => format!(
+            "{}({})",
+            token_text(&cast.type_specifier),
+            format_expr(&cast.expr)
+        ),
+        Expr::Call(call) => {
+            let args: Vec<String> = call.args.iter().map(format_expr).collect();
+            format!("{}({})", token_text(&call.name), args.join(", "))
+        }
+        Expr::Postfix(postfix) => {
+            format!("{}{}", format_expr(&postfix.expr), token_text(&postfix.op))
+        }
+        Expr::Binary(binary) => format!(
+            "{} {} {}",
+            format_expr(&binary.lhs),
+            token_text(&binary.op),
+            format_expr(&binary.rhs)
+        ),
+    }
+}
@@ -0,0 +1,35 @@
+use crate::{lexer, value::Value};
+use std::collections::HashMap;
+
+// Variables an embedding host declares for its scripts, with the values they start out
+// with. A script reads and assigns them like variables it declared itself, typed by
+// their value, and can't declare them again. Globals are handed to Analyzer::with_globals
+// and travel with the symbol table to the interpreter from there.
+#[derive(Debug, Clone, Default)]
+pub struct Globals {
+    values: HashMap<String, Value>,
+}
+
+impl Globals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Declares `name` with the type and initial value of `value`, replacing an earlier
+    // global of the same name. The name must be an identifier.
+    pub fn set(&mut self, name: &str, value: Value) -> Result<(), String> {
+        if !lexer::is_identifier(name) {
+            return Err(format!("'{}' is not an identifier.", name));
+        }
+        self.values.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.values.iter()
+    }
+}
@@ -0,0 +1,133 @@
+use std::ops::Range;
+
+use crate::parser::{ParseError, Parser, Program, Span};
+
+// Replace the bytes in `range` of the current text with `text`.
+#[derive(Debug, Clone)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+// Keeps a program parsed across edits to its source.
+//
+// Top level statements are the unit of reuse. An edit re-lexes and re-parses only the
+// statements it overlaps, plus any that share a line with the end of the edit so their
+// columns come out right. Every other statement keeps its tree as is, since its lines
+// count from its span (see parser::Spans), and the spans below the edit move together.
+// When the edited region doesn't parse on its own, or the last version didn't parse at
+// all, the whole text is parsed again, so the result is always the same as parsing the
+// text from scratch.
+pub struct IncrementalParser {
+    text: String,
+    parsed: Result<Parsed, ParseError>,
+}
+
+struct Parsed {
+    program: Program,
+    init: Span,
+}
+
+impl IncrementalParser {
+    pub fn new(text: String) -> Self {
+        let parsed = parse(&text);
+        Self { text, parsed }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn program(&self) -> Result<&Program, &ParseError> {
+        self.parsed.as_ref().map(|parsed| &parsed.program)
+    }
+
+    // Applies an edit and brings the program up to date. Returns whether the previous
+    // tree could be reused, for callers that want to measure it.
+    pub fn edit(&mut self, edit: TextEdit) -> bool {
+        let TextEdit { range, text } = edit;
+        self.text.replace_range(range.clone(), &text);
+        if let Ok(parsed) = &mut self.parsed {
+            if parsed.reparse(&self.text, range, text.len()) {
+                return true;
+            }
+        }
+        self.parsed = parse(&self.text);
+        false
+    }
+}
+
+fn parse(text: &str) -> Result<Parsed, ParseError> {
+    let (program, init) = Parser::new(text).parse_program_with_init()?;
+    Ok(Parsed { program, init })
+}
+
+impl Parsed {
+    // Re-parses the statements `range` of the old text overlapped, now `new_len` bytes
+    // long in `text`. Returns false, leaving self untouched, when that isn't enough.
+    fn reparse(&mut self, text: &str, range: Range<usize>, new_len: usize) -> bool {
+        let spans = &self.program.spans;
+        // Text touching init could merge with the keyword.
+        if range.start <= self.init.end {
+            return false;
+        }
+        // The first statement that ends after the edit starts. Anything between the
+        // statement before it and the edit is whitespace or comments.
+        let first = spans.partition_point(|span| span.end <= range.start);
+        if first == spans.len() {
+            return false;
+        }
+        let mut last = first;
+        let mut last_span = spans.get(first);
+        while last + 1 < spans.len() {
+            let next = spans.get(last + 1);
+            if last_span.end >= range.end && next.start_line != last_span.end_line {
+                break;
+            }
+            last += 1;
+            last_span = next;
+        }
+        if last_span.end < range.end {
+            return false;
+        }
+
+        let (region_start, line, col) = match first {
+            0 => (self.init.end, self.init.end_line, self.init.end_col),
+            _ => {
+                let before = spans.get(first - 1);
+                (before.end, before.end_line, before.end_col)
+            }
+        };
+        let delta = new_len as isize - range.len() as isize;
+        let region_end = last_span.end.wrapping_add_signed(delta);
+        let mut parser = Parser::new_at(&text[region_start..region_end], line, col);
+        let Ok((stmts, mut new_spans)) = parser.parse_stmts_with_spans() else {
+            return false;
+        };
+        let (end_line, _, _) = parser.position();
+        if parser
+            .comments()
+            .last()
+            .is_some_and(|(line, _)| *line == end_line)
+        {
+            // The region ends inside a comment, which also swallows whatever follows it
+            // on that line.
+            return false;
+        }
+        if stmts.is_empty() && first == 0 && last + 1 == spans.len() {
+            // Nothing left, let the full parse report the empty program.
+            return false;
+        }
+
+        for span in &mut new_spans {
+            span.start += region_start;
+            span.end += region_start;
+        }
+        let line_delta = end_line as i32 - last_span.end_line as i32;
+        let spans = &mut self.program.spans;
+        spans.shift(last + 1, delta, line_delta);
+        spans.splice(first..last + 1, new_spans);
+        self.program.stmt_list.stmts.splice(first..=last, stmts);
+        true
+    }
+}
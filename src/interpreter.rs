@@ -0,0 +1,557 @@
+use crate::{
+    builtins::Io,
+    eval::Evaluator,
+    format::token_text,
+    operators,
+    output::{Output, PrintFormat, Stdout},
+    parser::{
+        AssignStmt, CallExpr, CtrlStmt, Expr, IfStmt, LoopStmt, PrintStmt, Program, ReadStmt, Stmt,
+        StmtList,
+    },
+    semantic::{ident_name, SymbolTable},
+    value::{self, Value},
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    io::{self, BufRead},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+pub enum RuntimeError {
+    // The program went wrong, like dividing by zero or reading malformed input, at the
+    // statement on `line`. The error of a native function is placed at its call, with the
+    // column counted from 0 like the parser's. An error made with new, like the ones
+    // native functions return, has no position until the interpreter gives it one.
+    Failed {
+        msg: String,
+        line: Option<u32>,
+        col: Option<u32>,
+    },
+    // The program ran into one of the limits it was run with, at the statement on `line`.
+    LimitExceeded {
+        limit: Limit,
+        line: u32,
+    },
+}
+
+impl Error for RuntimeError {}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::Failed {
+                msg,
+                line: Some(line),
+                col: Some(col),
+            } => write!(f, "Line {}, column {}: {}", line, col + 1, msg),
+            RuntimeError::Failed {
+                msg,
+                line: Some(line),
+                col: None,
+            } => write!(f, "Line {}: {}", line, msg),
+            RuntimeError::Failed {
+                msg, line: None, ..
+            } => write!(f, "{}", msg),
+            RuntimeError::LimitExceeded { limit, line } => write!(f, "Line {}: {}", line, limit),
+        }
+    }
+}
+
+impl RuntimeError {
+    pub fn new(msg: String) -> Self {
+        RuntimeError::Failed {
+            msg,
+            line: None,
+            col: None,
+        }
+    }
+}
+
+impl From<String> for RuntimeError {
+    fn from(msg: String) -> Self {
+        RuntimeError::new(msg)
+    }
+}
+
+// A limit a program ran into, with the value it was set to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Fuel(u64),
+    LoopIterations(u64),
+    StringLength(usize),
+    Timeout(Duration),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Fuel(fuel) => write!(f, "Ran out of fuel after {} steps.", fuel),
+            Limit::LoopIterations(iterations) => {
+                write!(f, "Loop ran for more than {} iterations.", iterations)
+            }
+            Limit::StringLength(length) => {
+                write!(f, "String longer than {} characters.", length)
+            }
+            Limit::Timeout(timeout) => write!(f, "Timed out after {:?}.", timeout),
+        }
+    }
+}
+
+// What a program may use while it runs, None being no limit. A step of fuel is used by
+// every statement executed and every expression evaluated, loop iterations are counted
+// per run of a loop, and the string length is checked on every string an expression
+// evaluates to, its operands included, so the program never holds a longer one. What is
+// evaluated before the program runs, constant initializers and the optimizer's folds, is
+// held to the same fuel, string length and time, with a budget of its own.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    pub fuel: Option<u64>,
+    pub loop_iterations: Option<u64>,
+    pub string_length: Option<usize>,
+    pub timeout: Option<Duration>,
+}
+
+// The clock is only read every so many steps.
+const TIMEOUT_CHECK_INTERVAL: u64 = 64;
+
+// The fuel and time used of an evaluation's limits, counted from when it was created.
+#[derive(Debug, Clone)]
+pub(crate) struct Budget {
+    pub(crate) limits: Limits,
+    steps: u64,
+    started: Instant,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self::new(Limits::default())
+    }
+}
+
+impl Budget {
+    pub(crate) fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            steps: 0,
+            started: Instant::now(),
+        }
+    }
+
+    // Uses a step of fuel, and checks the clock every so often.
+    pub(crate) fn step(&mut self) -> Result<(), Limit> {
+        self.steps += 1;
+        if let Some(fuel) = self.limits.fuel {
+            if self.steps > fuel {
+                return Err(Limit::Fuel(fuel));
+            }
+        }
+        if let Some(timeout) = self.limits.timeout {
+            if self.steps.is_multiple_of(TIMEOUT_CHECK_INTERVAL) && self.started.elapsed() > timeout
+            {
+                return Err(Limit::Timeout(timeout));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_string_length(&self, length: usize) -> Result<(), Limit> {
+        match self.limits.string_length {
+            Some(limit) if length > limit => Err(Limit::StringLength(limit)),
+            _ => Ok(()),
+        }
+    }
+}
+
+// The input of read statements and readline, read a line at a time. `rest` is what
+// hasn't been used of the current line. Without a reader the input is stdin, which is
+// only locked while a line is read, so a program that never reads doesn't touch it.
+struct Input<'a> {
+    reader: Option<Box<dyn BufRead + 'a>>,
+    rest: String,
+}
+
+impl<'a> Input<'a> {
+    fn new(reader: impl BufRead + 'a) -> Self {
+        Self {
+            reader: Some(Box::new(reader)),
+            rest: String::new(),
+        }
+    }
+
+    fn stdin() -> Self {
+        Self {
+            reader: None,
+            rest: String::new(),
+        }
+    }
+
+    // Replaces rest with the next line, returns false at the end of the input.
+    fn next_line(&mut self) -> Result<bool, String> {
+        self.rest.clear();
+        let read = match &mut self.reader {
+            Some(reader) => reader.read_line(&mut self.rest),
+            None => io::stdin().lock().read_line(&mut self.rest),
+        };
+        match read {
+            Ok(read) => Ok(read > 0),
+            Err(error) => Err(format!("Couldn't read input: {}.", error)),
+        }
+    }
+
+    // Skips whitespace, line breaks included, returns false if the input ran out first.
+    fn skip_whitespace(&mut self) -> Result<bool, String> {
+        loop {
+            let start = self.rest.len() - self.rest.trim_start().len();
+            self.rest.drain(..start);
+            if !self.rest.is_empty() {
+                return Ok(true);
+            }
+            if !self.next_line()? {
+                return Ok(false);
+            }
+        }
+    }
+
+    // The next word, words being separated by any whitespace.
+    fn next_word(&mut self) -> Result<Option<String>, String> {
+        if !self.skip_whitespace()? {
+            return Ok(None);
+        }
+        let end = self
+            .rest
+            .find(char::is_whitespace)
+            .unwrap_or(self.rest.len());
+        Ok(Some(self.rest.drain(..end).collect()))
+    }
+
+    // The rest of the line a read stopped in if anything but whitespace is left of it,
+    // the next line otherwise.
+    fn read_line(&mut self) -> Result<Option<String>, String> {
+        if self.rest.trim().is_empty() && !self.next_line()? {
+            return Ok(None);
+        }
+        let line = self.rest.trim_start().trim_end_matches(['\n', '\r']);
+        let line = line.to_string();
+        self.rest.clear();
+        Ok(Some(line))
+    }
+}
+
+// Executes a program that has passed semantic analysis. Variables start out unassigned
+// except for the host's globals, constants are read straight out of the symbol table.
+// Read statements and readline take their input from stdin unless another reader is
+// given with with_input, and output goes to stdout unless another output is given with
+// with_output.
+pub struct Interpreter<'a> {
+    symbols: &'a SymbolTable,
+    variables: HashMap<String, Value>,
+    input: Input<'a>,
+    output: Box<dyn Output + 'a>,
+    print_format: PrintFormat,
+    budget: Budget,
+    // The line the top level statement being executed starts on, see Program::stmts, and
+    // the line of the statement itself, where limits are reported.
+    base: u32,
+    line: u32,
+    // A limit hit while evaluating an expression, whose error is only a plain string, and
+    // the line and column of a native function call that failed.
+    exceeded: Option<Limit>,
+    failed_call: Option<(u32, u32)>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(symbols: &'a SymbolTable) -> Self {
+        Self {
+            symbols,
+            variables: symbols
+                .globals()
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            input: Input::stdin(),
+            output: Box::new(Stdout),
+            print_format: PrintFormat::default(),
+            budget: Budget::default(),
+            base: 0,
+            line: 0,
+            exceeded: None,
+            failed_call: None,
+        }
+    }
+
+    pub fn with_input(mut self, reader: impl BufRead + 'a) -> Self {
+        self.input = Input::new(reader);
+        self
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.budget = Budget::new(limits);
+        self
+    }
+
+    // Sends output somewhere else than stdout. Passing `&mut capture` keeps the output
+    // around to be looked at after the run.
+    pub fn with_output(mut self, output: impl Output + 'a) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    pub fn with_print_format(mut self, print_format: PrintFormat) -> Self {
+        self.print_format = print_format;
+        self
+    }
+
+    // The variables that have been assigned, with their current values.
+    pub fn variables(&self) -> &HashMap<String, Value> {
+        &self.variables
+    }
+
+    pub fn run(&mut self, program: &Program) -> Result<(), RuntimeError> {
+        self.budget = Budget::new(self.budget.limits);
+        for (line, stmt) in program.stmts() {
+            self.base = line;
+            self.exec_stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn exec_stmt_list(&mut self, stmt_list: &StmtList) -> Result<(), RuntimeError> {
+        for stmt in &stmt_list.stmts {
+            self.exec_stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        // Declarations only matter to the analyzer.
+        if let Stmt::Ctrl(ctrl_stmt) = stmt {
+            self.line = self.base + stmt.line();
+            self.budget.step().map_err(|limit| self.exceeded(limit))?;
+            self.exec_ctrl_stmt(ctrl_stmt)
+                .map_err(|error| self.locate(error))?;
+        }
+        Ok(())
+    }
+
+    // Places an error without a position at the statement being executed. One from a
+    // statement nested in it already has one.
+    fn locate(&self, error: RuntimeError) -> RuntimeError {
+        match error {
+            RuntimeError::Failed {
+                msg, line: None, ..
+            } => RuntimeError::Failed {
+                msg,
+                line: Some(self.line),
+                col: None,
+            },
+            error => error,
+        }
+    }
+
+    fn exceeded(&self, limit: Limit) -> RuntimeError {
+        RuntimeError::LimitExceeded {
+            limit,
+            line: self.line,
+        }
+    }
+
+    // Notes a limit hit while evaluating an expression, for eval to report.
+    fn exceed(&mut self, limit: Limit) -> String {
+        let msg = limit.to_string();
+        self.exceeded = Some(limit);
+        msg
+    }
+
+    // Evaluates an expression for a statement, turning a limit hit on the way into a
+    // LimitExceeded error and placing a native function's error at its call. Every string
+    // evaluated on the way, not only the value, is checked against the string length
+    // limit.
+    fn eval(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.eval_expr(expr).map_err(
+            |msg| match (self.exceeded.take(), self.failed_call.take()) {
+                (Some(limit), _) => self.exceeded(limit),
+                (None, Some((line, col))) => RuntimeError::Failed {
+                    msg,
+                    line: Some(line),
+                    col: Some(col),
+                },
+                (None, None) => msg.into(),
+            },
+        )
+    }
+
+    fn check_length(&self, value: &Value) -> Result<(), RuntimeError> {
+        match value {
+            Value::Str(s) => self
+                .budget
+                .check_string_length(s.chars().count())
+                .map_err(|limit| self.exceeded(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    fn exec_ctrl_stmt(&mut self, ctrl_stmt: &CtrlStmt) -> Result<(), RuntimeError> {
+        match ctrl_stmt {
+            CtrlStmt::Assign(assign_stmt) => self.exec_assign_stmt(assign_stmt),
+            CtrlStmt::Print(print_stmt) => self.exec_print_stmt(print_stmt),
+            CtrlStmt::Read(read_stmt) => self.exec_read_stmt(read_stmt),
+            CtrlStmt::If(if_stmt) => self.exec_if_stmt(if_stmt),
+            CtrlStmt::Loop(loop_stmt) => self.exec_loop_stmt(loop_stmt),
+        }
+    }
+
+    fn exec_assign_stmt(&mut self, assign_stmt: &AssignStmt) -> Result<(), RuntimeError> {
+        let name = ident_name(&assign_stmt.ident);
+        // x op= e reads x once, then evaluates and applies e like x = x op e would.
+        let value = match operators::compound_assignment(&assign_stmt.op) {
+            Some(op) => {
+                let current = self.lookup(name)?;
+                let rhs = self.eval(&assign_stmt.expr)?;
+                let value = value::binary_op(op, current, rhs)?;
+                self.check_length(&value)?;
+                value
+            }
+            None => self.eval(&assign_stmt.expr)?,
+        };
+        let symbol = match self.symbols.get(name) {
+            Some(symbol) => symbol,
+            None => return Err(format!("Assignment to undeclared variable '{}'.", name).into()),
+        };
+        if !value.has_type(&symbol.type_specifier) {
+            return Err(format!(
+                "Cannot assign {} to '{}' declared as {:?}.",
+                value.type_name(),
+                name,
+                symbol.type_specifier
+            )
+            .into());
+        }
+        self.variables.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn exec_print_stmt(&mut self, print_stmt: &PrintStmt) -> Result<(), RuntimeError> {
+        let mut values = Vec::new();
+        for expr in &print_stmt.exprs {
+            values.push(self.eval(expr)?);
+        }
+        let text = self.print_format.format(&values);
+        self.output.write(&text)?;
+        Ok(())
+    }
+
+    // Reads one word per variable and converts it to the variable's type the way a cast
+    // would, so a string variable gets a single word.
+    fn exec_read_stmt(&mut self, read_stmt: &ReadStmt) -> Result<(), RuntimeError> {
+        for ident in &read_stmt.idents {
+            let name = ident_name(ident);
+            let type_specifier = match self.symbols.get(name) {
+                Some(symbol) => symbol.type_specifier.clone(),
+                None => return Err(format!("Read into undeclared variable '{}'.", name).into()),
+            };
+            let Some(word) = self.input.next_word()? else {
+                return Err(format!("Unexpected end of input reading '{}'.", name).into());
+            };
+            let value = value::cast(&type_specifier, Value::Str(word.clone())).map_err(|_| {
+                format!(
+                    "Malformed input for '{}': expected {}, got \"{}\".",
+                    name,
+                    token_text(&type_specifier),
+                    word
+                )
+            })?;
+            self.check_length(&value)?;
+            self.variables.insert(name.to_string(), value);
+        }
+        Ok(())
+    }
+
+    fn exec_if_stmt(&mut self, if_stmt: &IfStmt) -> Result<(), RuntimeError> {
+        if self.eval_condition(&if_stmt.condition)? {
+            self.exec_stmt_list(&if_stmt.then_stmts)
+        } else if let Some(else_stmts) = &if_stmt.else_stmts {
+            self.exec_stmt_list(else_stmts)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn exec_loop_stmt(&mut self, loop_stmt: &LoopStmt) -> Result<(), RuntimeError> {
+        let mut iterations = 0;
+        loop {
+            self.line = self.base + loop_stmt.line;
+            if !self.eval_condition(&loop_stmt.condition)? {
+                return Ok(());
+            }
+            iterations += 1;
+            if let Some(limit) = self.budget.limits.loop_iterations {
+                if iterations > limit {
+                    return Err(self.exceeded(Limit::LoopIterations(limit)));
+                }
+            }
+            self.exec_stmt_list(&loop_stmt.stmts)?;
+        }
+    }
+
+    fn eval_condition(&mut self, condition: &Expr) -> Result<bool, RuntimeError> {
+        match self.eval(condition)? {
+            Value::Bool(b) => Ok(b),
+            value => Err(format!("Condition must be bool, found {}.", value.type_name()).into()),
+        }
+    }
+}
+
+impl<'a> Io for Interpreter<'a> {
+    fn read_line(&mut self) -> Result<Option<String>, String> {
+        self.input.read_line()
+    }
+
+    fn write(&mut self, text: &str) -> Result<(), String> {
+        self.output.write(text)
+    }
+}
+
+impl<'a> Evaluator for Interpreter<'a> {
+    fn step(&mut self) -> Result<(), String> {
+        self.budget.step().map_err(|limit| self.exceed(limit))
+    }
+
+    fn check_string_length(&mut self, length: usize) -> Result<(), String> {
+        self.budget
+            .check_string_length(length)
+            .map_err(|limit| self.exceed(limit))
+    }
+
+    fn eof(&mut self) -> Result<bool, String> {
+        Ok(!self.input.skip_whitespace()?)
+    }
+
+    fn io(&mut self) -> Option<&mut dyn Io> {
+        Some(self)
+    }
+
+    // Errors from the host are reported where the script called it.
+    fn call_native(&mut self, call: &CallExpr, args: &[Value]) -> Option<Result<Value, String>> {
+        let native = self.symbols.natives().get(ident_name(&call.name))?;
+        Some(native.call(args).map_err(|msg| {
+            self.failed_call = Some((self.base + call.line, call.col));
+            format!("in '{}': {}", native.name, msg)
+        }))
+    }
+
+    fn lookup(&mut self, name: &str) -> Result<Value, String> {
+        if let Some(value) = self.symbols.constant(name) {
+            return Ok(value.clone());
+        }
+        match self.variables.get(name) {
+            Some(value) => Ok(value.clone()),
+            None if self.symbols.get(name).is_some() => {
+                Err(format!("Variable '{}' used before being assigned.", name))
+            }
+            None => Err(format!("Use of undeclared variable '{}'.", name)),
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use std::iter::Peekable;
-use std::mem;
 use std::str::Chars;
 
 use crate::Token;
 
+#[allow(clippy::upper_case_acronyms)]
 enum State {
     START,
     INID,
@@ -13,36 +13,164 @@ enum State {
     INCOMMENT,
 }
 
+fn keyword_or_ident(lexeme: String) -> Token {
+    match lexeme.as_str() {
+        "init" => Token::INIT,
+        "halt" => Token::HALT,
+        "print" => Token::PRINT,
+        "read" => Token::READ,
+        "eof" => Token::EOF,
+        "if" => Token::IF,
+        "then" => Token::THEN,
+        "else" => Token::ELSE,
+        "endif" => Token::ENDIF,
+        "while" => Token::WHILE,
+        "do" => Token::DO,
+        "endwhile" => Token::ENDWHILE,
+        "const" => Token::CONST,
+        "div" => Token::IDIV,
+        "mod" => Token::IMOD,
+        "int" => Token::INT,
+        "float" => Token::FLOAT,
+        "bool" => Token::BOOL,
+        "string" => Token::STRING,
+        "true" => Token::BCONST(true),
+        "false" => Token::BCONST(false),
+        _ => Token::IDENT(lexeme),
+    }
+}
+
+// Splits text the lexer skips between two tokens into whitespace and comment tokens. A
+// comment runs up to its newline, which is whitespace.
+pub fn trivia(text: &str) -> Vec<(Token, &str)> {
+    let mut pieces: Vec<(Token, &str)> = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (token, len) = if rest.starts_with("//") {
+            (Token::COMMENT, rest.find('\n').unwrap_or(rest.len()))
+        } else {
+            (Token::WHITESPACE, rest.find("//").unwrap_or(rest.len()))
+        };
+        pieces.push((token, &rest[..len]));
+        rest = &rest[len..];
+    }
+    pieces
+}
+
+// Whether text is a single identifier, and not a keyword or anything else.
+pub fn is_identifier(text: &str) -> bool {
+    let tokens: Vec<Token> = Lexer::new(text).collect();
+    tokens == [Token::IDENT(text.to_string())]
+}
+
 pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
     line: u32,
+    // Column of the next character, counted in chars from 0.
+    col: u32,
+    // Byte offset of the next character.
+    offset: usize,
+    // The line, column and byte offset the most recently returned token started on.
+    token_line: u32,
+    token_col: u32,
+    token_offset: usize,
+    // Line comments with the line they are on, in source order.
+    comments: Vec<(u32, String)>,
     pushed_back_token: Option<Token>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input_str: &'a str) -> Self {
+        Self::new_at(input_str, 1, 0)
+    }
+
+    // Lexes a slice of a larger source that starts at the given line and column, so that
+    // positions come out as they would when lexing the whole source. Byte offsets stay
+    // relative to the slice.
+    pub fn new_at(input_str: &'a str, line: u32, col: u32) -> Self {
         Self {
             input: input_str.chars().peekable(),
-            line: 1,
+            line,
+            col,
+            offset: 0,
+            token_line: line,
+            token_col: col,
+            token_offset: 0,
+            comments: vec![],
             pushed_back_token: None,
         }
     }
 
+    // Line of the last token returned by next(). A pushed back token is always the last
+    // one returned, so this stays correct across push_back.
+    pub fn token_line(&self) -> u32 {
+        self.token_line
+    }
+
+    pub fn token_col(&self) -> u32 {
+        self.token_col
+    }
+
+    pub fn token_offset(&self) -> usize {
+        self.token_offset
+    }
+
+    // Line, column and byte offset of the next character, i.e. just past the last token
+    // returned unless it was pushed back.
+    pub fn position(&self) -> (u32, u32, usize) {
+        (self.line, self.col, self.offset)
+    }
+
+    // Comments are skipped while lexing but kept here for tools that reprint the source.
+    pub fn comments(&self) -> &[(u32, String)] {
+        &self.comments
+    }
+
     pub fn push_back(&mut self, token: Token) {
         self.pushed_back_token = Some(token);
     }
 
     fn error(&self, lexeme: String) -> Token {
-        Token::ERROR(String::from(format!(
+        Token::ERROR(format!(
             "Unrecognized token at line {}: \'{}\'.",
             self.line, lexeme
-        )))
+        ))
+    }
+
+    // The lexeme holds digits, but may not fit an i32 or may use digits other than 0-9.
+    fn int_literal(&self, lexeme: String) -> Token {
+        match lexeme.parse::<i32>() {
+            Ok(value) => Token::ICONST(value),
+            Err(_) if lexeme.chars().all(|ch| ch.is_ascii_digit()) => Token::ERROR(format!(
+                "Integer literal out of range at line {}: \'{}\'.",
+                self.line, lexeme
+            )),
+            Err(_) => self.error(lexeme),
+        }
+    }
+
+    fn float_literal(&self, lexeme: String) -> Token {
+        match lexeme.parse::<f64>() {
+            Ok(value) => Token::FCONST(value),
+            Err(_) => self.error(lexeme),
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.input.next()?;
+        self.offset += ch.len_utf8();
+        if ch == '\n' {
+            self.col = 0;
+        } else {
+            self.col += 1;
+        }
+        Some(ch)
     }
 
     fn cmp_next_char(&mut self, current_char: &char) -> bool {
         if let Some(&next_char) = self.input.peek() {
             if *current_char == next_char {
-                self.input.next();
+                self.bump();
                 true
             } else {
                 false
@@ -56,32 +184,33 @@ impl<'a> Lexer<'a> {
 impl<'a> Iterator for Lexer<'a> {
     type Item = Token;
     fn next(&mut self) -> Option<Self::Item> {
-        match mem::replace(&mut self.pushed_back_token, None) {
-            Some(t) => return Some(t),
-            None => (),
+        if let Some(t) = self.pushed_back_token.take() {
+            return Some(t);
         }
         let mut state = State::START;
         let mut lexeme = String::from("");
         let mut digit_after_decimal_seen = false;
-        let mut digit_before_decimal_seen = false;
         while let Some(&ch) = self.input.peek() {
             match state {
                 State::START => {
-                    if ch == ' ' {
-                        self.input.next();
+                    if ch == ' ' || ch == '\t' || ch == '\r' {
+                        self.bump();
                         continue;
                     }
                     if ch == '\n' {
-                        self.input.next();
+                        self.bump();
                         self.line += 1;
                         continue;
                     }
+                    self.token_line = self.line;
+                    self.token_col = self.col;
+                    self.token_offset = self.offset;
                     if ch == '\"' {
-                        self.input.next();
+                        self.bump();
                         state = State::INSTRING;
                         continue;
                     }
-                    let ch = self.input.next().unwrap();
+                    let ch = self.bump().unwrap();
                     lexeme.push(ch);
                     if ch.is_numeric() {
                         state = State::ININT;
@@ -96,31 +225,74 @@ impl<'a> Iterator for Lexer<'a> {
                         ')' => Token::CLOSEPAREN,
                         ',' => Token::COMMA,
                         ';' => Token::SEMICOL,
-                        '+' => Token::PLUS,
-                        '-' => Token::MINUS,
-                        '*' => Token::MULT,
+                        '+' => {
+                            if self.cmp_next_char(&'=') {
+                                Token::PLUSASSOP
+                            } else {
+                                Token::PLUS
+                            }
+                        }
+                        '-' => {
+                            if self.cmp_next_char(&'=') {
+                                Token::MINUSASSOP
+                            } else {
+                                Token::MINUS
+                            }
+                        }
+                        '*' => {
+                            if self.cmp_next_char(&ch) {
+                                Token::POW
+                            } else if self.cmp_next_char(&'=') {
+                                Token::MULTASSOP
+                            } else {
+                                Token::MULT
+                            }
+                        }
                         '!' => Token::NOT,
-                        '>' => Token::GTHAN,
-                        '<' => Token::LTHAN,
-                        '%' => Token::MOD,
+                        '>' => {
+                            if self.cmp_next_char(&ch) {
+                                Token::SHR
+                            } else {
+                                Token::GTHAN
+                            }
+                        }
+                        '<' => {
+                            if self.cmp_next_char(&ch) {
+                                Token::SHL
+                            } else {
+                                Token::LTHAN
+                            }
+                        }
+                        '^' => Token::BITXOR,
+                        '~' => Token::BITNOT,
+                        '%' => {
+                            if self.cmp_next_char(&'=') {
+                                Token::MODASSOP
+                            } else {
+                                Token::MOD
+                            }
+                        }
                         '&' => {
                             if self.cmp_next_char(&ch) {
                                 Token::ANDOP
                             } else {
-                                self.error(lexeme)
+                                Token::BITAND
                             }
                         }
                         '|' => {
                             if self.cmp_next_char(&ch) {
                                 Token::OROP
                             } else {
-                                self.error(lexeme)
+                                Token::BITOR
                             }
                         }
                         '/' => {
                             if self.cmp_next_char(&ch) {
                                 state = State::INCOMMENT;
+                                lexeme.clear();
                                 continue;
+                            } else if self.cmp_next_char(&'=') {
+                                Token::DIVASSOP
                             } else {
                                 Token::DIV
                             }
@@ -137,41 +309,42 @@ impl<'a> Iterator for Lexer<'a> {
                     return Some(token);
                 }
                 State::INCOMMENT => {
+                    self.bump();
                     if ch == '\n' {
                         state = State::START;
-                        self.input.next();
+                        self.comments
+                            .push((self.token_line, lexeme.trim_end().to_string()));
+                        lexeme.clear();
+                        self.line += 1;
+                    } else {
+                        lexeme.push(ch);
                     }
                 }
                 State::ININT => {
                     if ch.is_numeric() {
-                        let ch = self.input.next().unwrap();
+                        let ch = self.bump().unwrap();
                         lexeme.push(ch);
-                        digit_before_decimal_seen = true;
                     } else if ch == '.' {
-                        if digit_before_decimal_seen {
-                            let ch = self.input.next().unwrap();
-                            lexeme.push(ch);
-                            state = State::INFLOAT;
-                        } else {
-                            return Some(self.error(lexeme));
-                        }
+                        let ch = self.bump().unwrap();
+                        lexeme.push(ch);
+                        state = State::INFLOAT;
                     } else {
-                        return Some(Token::ICONST(lexeme.parse::<i32>().unwrap()));
+                        return Some(self.int_literal(lexeme));
                     }
                 }
                 State::INFLOAT => {
                     if ch.is_numeric() {
-                        let ch = self.input.next().unwrap();
+                        let ch = self.bump().unwrap();
                         lexeme.push(ch);
                         digit_after_decimal_seen = true;
                     } else if digit_after_decimal_seen {
-                        return Some(Token::FCONST(lexeme.parse::<f64>().unwrap()));
+                        return Some(self.float_literal(lexeme));
                     } else {
                         return Some(self.error(lexeme));
                     }
                 }
                 State::INSTRING => {
-                    let ch = self.input.next().unwrap();
+                    let ch = self.bump().unwrap();
                     if ch == '\"' {
                         return Some(Token::SCONST(lexeme));
                     } else if ch == '\n' {
@@ -182,66 +355,34 @@ impl<'a> Iterator for Lexer<'a> {
                 }
                 State::INID => {
                     if ch.is_alphanumeric() || ch == '_' {
-                        let ch = self.input.next().unwrap();
+                        let ch = self.bump().unwrap();
                         lexeme.push(ch);
                     } else {
-                        return Some(match lexeme.as_str() {
-                            "init" => Token::INIT,
-                            "halt" => Token::HALT,
-                            "print" => Token::PRINT,
-                            "if" => Token::IF,
-                            "then" => Token::THEN,
-                            "else" => Token::ELSE,
-                            "endif" => Token::ENDIF,
-                            "while" => Token::WHILE,
-                            "do" => Token::DO,
-                            "endwhile" => Token::ENDWHILE,
-                            "int" => Token::INT,
-                            "float" => Token::FLOAT,
-                            "bool" => Token::BOOL,
-                            "string" => Token::STRING,
-                            "true" => Token::BCONST(true),
-                            "false" => Token::BCONST(false),
-                            _ => Token::IDENT(lexeme),
-                        });
+                        return Some(keyword_or_ident(lexeme));
                     }
                 }
             }
         }
         match state {
             State::START => None,
-            State::INCOMMENT => None,
-            State::INID => Some(match lexeme.as_str() {
-                "init" => Token::INIT,
-                "halt" => Token::HALT,
-                "print" => Token::PRINT,
-                "if" => Token::IF,
-                "then" => Token::THEN,
-                "else" => Token::ELSE,
-                "endif" => Token::ENDIF,
-                "while" => Token::WHILE,
-                "do" => Token::DO,
-                "endwhile" => Token::ENDWHILE,
-                "int" => Token::INT,
-                "float" => Token::FLOAT,
-                "bool" => Token::BOOL,
-                "string" => Token::STRING,
-                "true" => Token::BCONST(true),
-                "false" => Token::BCONST(false),
-                _ => Token::IDENT(lexeme),
-            }),
-            State::ININT => Some(Token::ICONST(lexeme.parse::<i32>().unwrap())),
+            State::INCOMMENT => {
+                self.comments
+                    .push((self.token_line, lexeme.trim_end().to_string()));
+                None
+            }
+            State::INID => Some(keyword_or_ident(lexeme)),
+            State::ININT => Some(self.int_literal(lexeme)),
             State::INFLOAT => {
                 if digit_after_decimal_seen {
-                    Some(Token::FCONST(lexeme.parse::<f64>().unwrap()))
+                    Some(self.float_literal(lexeme))
                 } else {
                     Some(self.error(lexeme))
                 }
             }
-            State::INSTRING => Some(Token::ERROR(String::from(format!(
+            State::INSTRING => Some(Token::ERROR(format!(
                 "Missing closing quotation at line {}.",
                 self.line
-            )))),
+            ))),
         }
     }
 }
@@ -1,11 +1,33 @@
+pub mod builtins;
+pub mod codegen;
+pub mod dataflow;
+pub mod engine;
+pub mod eval;
+pub mod format;
+pub mod globals;
+pub mod incremental;
+pub mod interpreter;
 pub mod lexer;
+pub mod lint;
+pub mod lsp;
+pub mod native;
+pub mod operators;
+pub mod optimizer;
+pub mod output;
 pub mod parser;
+pub mod semantic;
+pub mod syntax;
+pub mod types;
+pub mod value;
+
+pub use engine::Engine;
 
 #[derive(PartialEq, Clone, Debug)]
 pub enum Token {
     INIT,
     HALT,
     PRINT,
+    READ,
     IF,
     THEN,
     ELSE,
@@ -13,12 +35,14 @@ pub enum Token {
     WHILE,
     DO,
     ENDWHILE,
+    CONST,
     INT,
     FLOAT,
     BOOL,
     STRING,
     TRUE,
     FALSE,
+    EOF,
 
     OPENPAREN,
     CLOSEPAREN,
@@ -32,11 +56,25 @@ pub enum Token {
     LTHAN,
     EQUALOP,
     ASSOP,
+    PLUSASSOP,
+    MINUSASSOP,
+    MULTASSOP,
+    DIVASSOP,
+    MODASSOP,
     PLUS,
     MINUS,
     MULT,
     DIV,
     MOD,
+    POW,
+    IDIV,
+    IMOD,
+    BITAND,
+    BITOR,
+    BITXOR,
+    BITNOT,
+    SHL,
+    SHR,
 
     ICONST(i32),
     FCONST(f64),
@@ -46,5 +84,9 @@ pub enum Token {
     ERROR(String),
     DONE,
 
+    // Trivia, only found in the concrete syntax tree.
+    WHITESPACE,
+    COMMENT,
+
     IDENT(String),
 }
@@ -0,0 +1,453 @@
+use crate::{
+    eval::Evaluator,
+    interpreter::{Budget, Limits},
+    lexer::Lexer,
+    operators,
+    parser::{AssignStmt, CtrlStmt, DeclStmt, Expr, IfStmt, LoopStmt, Program, Stmt, StmtList},
+    semantic::ident_name,
+    value::Value,
+    Token,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    pub fn from_name(name: &str) -> Option<LintLevel> {
+        match name {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LintLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LintLevel::Allow => write!(f, "allow"),
+            LintLevel::Warn => write!(f, "warning"),
+            LintLevel::Deny => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Rule {
+    UndeclaredIdentifier,
+    UnusedVariable,
+    DeadCode,
+    SelfAssignment,
+    EmptyLoop,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 5] = [
+        Rule::UndeclaredIdentifier,
+        Rule::UnusedVariable,
+        Rule::DeadCode,
+        Rule::SelfAssignment,
+        Rule::EmptyLoop,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::UndeclaredIdentifier => "undeclared-identifier",
+            Rule::UnusedVariable => "unused-variable",
+            Rule::DeadCode => "dead-code",
+            Rule::SelfAssignment => "self-assignment",
+            Rule::EmptyLoop => "empty-loop",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    fn default_level(&self) -> LintLevel {
+        match self {
+            Rule::UndeclaredIdentifier => LintLevel::Deny,
+            _ => LintLevel::Warn,
+        }
+    }
+}
+
+// Per rule levels. Levels can be set from the command line and then overridden by
+// directives in the file being linted, one or more per comment:
+//
+//     // lint: allow(unused-variable), deny(self-assignment, empty-loop)
+#[derive(Clone, Debug, Default)]
+pub struct LintConfig {
+    levels: HashMap<Rule, LintLevel>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, rule: Rule, level: LintLevel) {
+        self.levels.insert(rule, level);
+    }
+
+    pub fn level(&self, rule: Rule) -> LintLevel {
+        match self.levels.get(&rule) {
+            Some(level) => *level,
+            None => rule.default_level(),
+        }
+    }
+
+    // Applies every `// lint:` directive in the source, errors on unknown levels or rules.
+    // Directives are read from the comments the lexer finds, so a `//` inside a string
+    // doesn't start one.
+    pub fn apply_directives(&mut self, source: &str) -> Result<(), String> {
+        let mut lexer = Lexer::new(source);
+        for _ in lexer.by_ref() {}
+        for (line, comment) in lexer.comments() {
+            let Some(directive) = comment.trim().strip_prefix("lint:") else {
+                continue;
+            };
+            let mut rest = directive.trim();
+            while !rest.is_empty() {
+                let (level_name, after) = rest
+                    .split_once('(')
+                    .ok_or_else(|| format!("Malformed lint directive at line {}.", line))?;
+                let (rule_names, after) = after
+                    .split_once(')')
+                    .ok_or_else(|| format!("Malformed lint directive at line {}.", line))?;
+                let level_name = level_name.trim();
+                let level = LintLevel::from_name(level_name).ok_or_else(|| {
+                    format!("Unknown lint level '{}' at line {}.", level_name, line)
+                })?;
+                for rule_name in rule_names.split(',').map(str::trim) {
+                    let rule = Rule::from_name(rule_name).ok_or_else(|| {
+                        format!("Unknown lint rule '{}' at line {}.", rule_name, line)
+                    })?;
+                    self.set(rule, level);
+                }
+                rest = after.trim().trim_start_matches(',').trim();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub level: LintLevel,
+    pub line: u32,
+    pub msg: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] line {}: {}",
+            self.level,
+            self.rule.name(),
+            self.line,
+            self.msg
+        )
+    }
+}
+
+struct Declaration {
+    line: u32,
+    is_const: bool,
+}
+
+// Levenshtein distance, used to suggest a declared name for a misspelled one.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != *cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// Checks a parsed program without requiring it to pass semantic analysis, so it can
+// report problems in programs the interpreter would reject.
+pub struct Linter<'a> {
+    config: &'a LintConfig,
+    declared: HashMap<String, Declaration>,
+    constants: HashMap<String, Value>,
+    read: HashSet<String>,
+    // Undeclared names already reported on a line, so `count = count + 1` reports once.
+    undeclared: HashSet<(String, u32)>,
+    diagnostics: Vec<Diagnostic>,
+    // The line the top level statement being linted starts on, see Program::stmts.
+    line: u32,
+    // What evaluating constants and conditions may use.
+    budget: Budget,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(config: &'a LintConfig) -> Self {
+        Self {
+            config,
+            declared: HashMap::new(),
+            constants: HashMap::new(),
+            read: HashSet::new(),
+            undeclared: HashSet::new(),
+            diagnostics: vec![],
+            line: 0,
+            budget: Budget::default(),
+        }
+    }
+
+    // Holds the evaluation of constants and conditions to the fuel, string length and time
+    // limits, past which they are treated as not constant.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.budget = Budget::new(limits);
+        self
+    }
+
+    // Returns the diagnostics of every rule that isn't allowed, ordered by line.
+    pub fn lint(mut self, program: &Program) -> Vec<Diagnostic> {
+        self.lint_stmts(program.stmts());
+        let mut unused: Vec<(u32, String, bool)> = self
+            .declared
+            .iter()
+            .filter(|(name, _)| !self.read.contains(*name))
+            .map(|(name, declaration)| (declaration.line, name.clone(), declaration.is_const))
+            .collect();
+        unused.sort();
+        for (line, name, is_const) in unused {
+            let kind = if is_const { "Constant" } else { "Variable" };
+            self.report(
+                Rule::UnusedVariable,
+                line,
+                format!("{} '{}' is never read.", kind, name),
+            );
+        }
+        self.diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+        self.diagnostics
+    }
+
+    fn report(&mut self, rule: Rule, line: u32, msg: String) {
+        let level = self.config.level(rule);
+        if level != LintLevel::Allow {
+            self.diagnostics.push(Diagnostic {
+                rule,
+                level,
+                line,
+                msg,
+            });
+        }
+    }
+
+    fn check_declared(&mut self, name: &str, line: u32) {
+        let line = self.line + line;
+        if self.declared.contains_key(name) || !self.undeclared.insert((name.to_string(), line)) {
+            return;
+        }
+        let suggestion = self
+            .declared
+            .keys()
+            .map(|declared| (edit_distance(name, declared), declared))
+            .filter(|(distance, _)| *distance <= 2.max(name.len() / 3))
+            .min();
+        let msg = match suggestion {
+            Some((_, declared)) => format!(
+                "Use of undeclared identifier '{}', did you mean '{}'?",
+                name, declared
+            ),
+            None => format!("Use of undeclared identifier '{}'.", name),
+        };
+        self.report(Rule::UndeclaredIdentifier, line, msg);
+    }
+
+    fn lint_reads(&mut self, expr: &Expr) {
+        for primary in expr.idents() {
+            let name = ident_name(&primary.constant);
+            self.check_declared(name, primary.line);
+            self.read.insert(name.to_string());
+        }
+    }
+
+    // A condition is constant when it only involves literals and constants.
+    fn constant_condition(&mut self, condition: &Expr) -> Option<bool> {
+        match self.eval_expr(condition) {
+            Ok(Value::Bool(b)) => Some(b),
+            _ => None,
+        }
+    }
+
+    fn lint_stmt_list(&mut self, stmt_list: &StmtList) {
+        let line = self.line;
+        self.lint_stmts(stmt_list.stmts.iter().map(|stmt| (line, stmt)));
+    }
+
+    // Lints statements along with the line each one's lines count from.
+    fn lint_stmts<'s>(&mut self, stmts: impl Iterator<Item = (u32, &'s Stmt)>) {
+        let mut infinite_loop: Option<u32> = None;
+        for (line, stmt) in stmts {
+            self.line = line;
+            if let Some(loop_line) = infinite_loop.take() {
+                self.report(
+                    Rule::DeadCode,
+                    line + stmt.line(),
+                    format!(
+                        "Unreachable statement after the infinite loop at line {}.",
+                        loop_line
+                    ),
+                );
+            }
+            match stmt {
+                Stmt::Decl(decl_stmt) => self.lint_decl_stmt(decl_stmt),
+                Stmt::Ctrl(CtrlStmt::Assign(assign_stmt)) => self.lint_assign_stmt(assign_stmt),
+                Stmt::Ctrl(CtrlStmt::Print(print_stmt)) => {
+                    for expr in &print_stmt.exprs {
+                        self.lint_reads(expr);
+                    }
+                }
+                Stmt::Ctrl(CtrlStmt::Read(read_stmt)) => {
+                    for ident in &read_stmt.idents {
+                        self.check_declared(ident_name(ident), read_stmt.line);
+                    }
+                }
+                Stmt::Ctrl(CtrlStmt::If(if_stmt)) => self.lint_if_stmt(if_stmt),
+                Stmt::Ctrl(CtrlStmt::Loop(loop_stmt)) => {
+                    if self.lint_loop_stmt(loop_stmt) {
+                        infinite_loop = Some(line + loop_stmt.line);
+                    }
+                }
+            }
+        }
+    }
+
+    fn lint_decl_stmt(&mut self, decl_stmt: &DeclStmt) {
+        if let Some(initializer) = &decl_stmt.initializer {
+            self.lint_reads(initializer);
+        }
+        for ident in &decl_stmt.idents {
+            let name = ident_name(ident).to_string();
+            if decl_stmt.is_const {
+                if let Some(Ok(value)) = decl_stmt
+                    .initializer
+                    .as_ref()
+                    .map(|expr| self.eval_expr(expr))
+                {
+                    self.constants.insert(name.clone(), value);
+                }
+            }
+            self.declared.insert(
+                name,
+                Declaration {
+                    line: self.line + decl_stmt.line,
+                    is_const: decl_stmt.is_const,
+                },
+            );
+        }
+    }
+
+    fn lint_assign_stmt(&mut self, assign_stmt: &AssignStmt) {
+        let name = ident_name(&assign_stmt.ident);
+        self.check_declared(name, assign_stmt.line);
+        let self_assignment = assign_stmt.op == Token::ASSOP
+            && assign_stmt
+                .expr
+                .primary()
+                .is_some_and(|primary| primary.constant == assign_stmt.ident);
+        if operators::compound_assignment(&assign_stmt.op).is_some() {
+            self.read.insert(name.to_string());
+        }
+        if self_assignment {
+            self.report(
+                Rule::SelfAssignment,
+                self.line + assign_stmt.line,
+                format!("'{}' is assigned to itself.", name),
+            );
+        } else {
+            self.lint_reads(&assign_stmt.expr);
+        }
+    }
+
+    fn lint_if_stmt(&mut self, if_stmt: &IfStmt) {
+        self.lint_reads(&if_stmt.condition);
+        match (
+            self.constant_condition(&if_stmt.condition),
+            &if_stmt.else_stmts,
+        ) {
+            (Some(false), _) => self.report(
+                Rule::DeadCode,
+                self.line + if_stmt.line,
+                "Condition is always false, the then branch is never executed.".to_string(),
+            ),
+            (Some(true), Some(_)) => self.report(
+                Rule::DeadCode,
+                self.line + if_stmt.line,
+                "Condition is always true, the else branch is never executed.".to_string(),
+            ),
+            _ => (),
+        }
+        self.lint_stmt_list(&if_stmt.then_stmts);
+        if let Some(else_stmts) = &if_stmt.else_stmts {
+            self.lint_stmt_list(else_stmts);
+        }
+    }
+
+    // Returns true when the loop can never exit.
+    fn lint_loop_stmt(&mut self, loop_stmt: &LoopStmt) -> bool {
+        self.lint_reads(&loop_stmt.condition);
+        let condition = self.constant_condition(&loop_stmt.condition);
+        if condition == Some(false) {
+            self.report(
+                Rule::DeadCode,
+                self.line + loop_stmt.line,
+                "Condition is always false, the loop body is never executed.".to_string(),
+            );
+        }
+        let has_effect = loop_stmt
+            .stmts
+            .stmts
+            .iter()
+            .any(|stmt| matches!(stmt, Stmt::Ctrl(_)));
+        if !has_effect {
+            self.report(
+                Rule::EmptyLoop,
+                self.line + loop_stmt.line,
+                "Loop body contains no statements that do anything.".to_string(),
+            );
+        }
+        self.lint_stmt_list(&loop_stmt.stmts);
+        condition == Some(true)
+    }
+}
+
+impl<'a> Evaluator for Linter<'a> {
+    fn step(&mut self) -> Result<(), String> {
+        self.budget.step().map_err(|limit| limit.to_string())
+    }
+
+    fn check_string_length(&mut self, length: usize) -> Result<(), String> {
+        self.budget
+            .check_string_length(length)
+            .map_err(|limit| limit.to_string())
+    }
+
+    fn lookup(&mut self, name: &str) -> Result<Value, String> {
+        match self.constants.get(name) {
+            Some(value) => Ok(value.clone()),
+            None => Err(format!("'{}' is not a constant.", name)),
+        }
+    }
+}
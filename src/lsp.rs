@@ -0,0 +1,624 @@
+use crate::{
+    builtins::{Builtin, BUILTINS},
+    format::{self, Formatter},
+    incremental::{IncrementalParser, TextEdit},
+    interpreter::Limits,
+    lint::{LintConfig, LintLevel, Linter},
+    parser::{CtrlStmt, Parser, Program, Stmt, StmtList},
+    semantic::{ident_name, Analyzer},
+};
+use serde_json::{json, Value as Json};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    time::Duration,
+};
+
+const KEYWORDS: [&str; 21] = [
+    "init", "halt", "print", "read", "eof", "if", "then", "else", "endif", "while", "do",
+    "endwhile", "const", "int", "float", "bool", "string", "true", "false", "div", "mod",
+];
+
+// LSP enum values used in responses.
+const SEVERITY_ERROR: u32 = 1;
+const SEVERITY_WARNING: u32 = 2;
+const COMPLETION_FUNCTION: u32 = 3;
+const COMPLETION_KEYWORD: u32 = 14;
+const COMPLETION_VARIABLE: u32 = 6;
+const COMPLETION_CONSTANT: u32 = 21;
+const SYMBOL_VARIABLE: u32 = 13;
+const SYMBOL_CONSTANT: u32 = 14;
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// What evaluating a document's constants may use, so that no document can hang the server
+// or run it out of memory.
+const LIMITS: Limits = Limits {
+    fuel: Some(100_000),
+    loop_iterations: None,
+    string_length: Some(1 << 20),
+    timeout: Some(Duration::from_secs(1)),
+};
+
+struct Declaration {
+    name: String,
+    line: u32,
+    col: u32,
+    is_const: bool,
+    // The declaration as it would be written, e.g. `const int MAX = 10 * 4`.
+    detail: String,
+}
+
+// An identifier occurrence, declarations included.
+struct Reference {
+    name: String,
+    line: u32,
+    col: u32,
+}
+
+// What the language features need from the last version of a document that parsed.
+#[derive(Default)]
+struct Index {
+    declarations: Vec<Declaration>,
+    references: Vec<Reference>,
+}
+
+impl Index {
+    // Indexes a program parsed from the text, with columns in UTF-16 code units like LSP
+    // positions.
+    fn build(program: &Program, text: &str) -> Self {
+        let mut index = Index::default();
+        for (line, stmt) in program.stmts() {
+            let declarations = index.declarations.len();
+            let references = index.references.len();
+            index.add_stmt(stmt);
+            // Lines in the statement count from its first line.
+            for declaration in &mut index.declarations[declarations..] {
+                declaration.line += line;
+            }
+            for reference in &mut index.references[references..] {
+                reference.line += line;
+            }
+        }
+        let lines: Vec<&str> = text.lines().collect();
+        let utf16_col = |line: u32, col: u32| match lines.get(line.saturating_sub(1) as usize) {
+            Some(text) => text
+                .chars()
+                .take(col as usize)
+                .map(char::len_utf16)
+                .sum::<usize>() as u32,
+            None => col,
+        };
+        for declaration in &mut index.declarations {
+            declaration.col = utf16_col(declaration.line, declaration.col);
+        }
+        for reference in &mut index.references {
+            reference.col = utf16_col(reference.line, reference.col);
+        }
+        index
+    }
+
+    fn add_stmt_list(&mut self, stmt_list: &StmtList) {
+        for stmt in &stmt_list.stmts {
+            self.add_stmt(stmt);
+        }
+    }
+
+    fn add_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Decl(decl_stmt) => {
+                if let Some(initializer) = &decl_stmt.initializer {
+                    self.add_reads(initializer);
+                }
+                for (ident, (line, col)) in decl_stmt.idents.iter().zip(&decl_stmt.ident_positions)
+                {
+                    let name = ident_name(ident).to_string();
+                    let detail = match decl_stmt.is_const {
+                        true => format::format_decl_stmt(decl_stmt),
+                        false => {
+                            format!("{} {}", format::token_text(&decl_stmt.type_specifier), name)
+                        }
+                    };
+                    self.references.push(Reference {
+                        name: name.clone(),
+                        line: *line,
+                        col: *col,
+                    });
+                    self.declarations.push(Declaration {
+                        name,
+                        line: *line,
+                        col: *col,
+                        is_const: decl_stmt.is_const,
+                        detail,
+                    });
+                }
+            }
+            Stmt::Ctrl(CtrlStmt::Assign(assign_stmt)) => {
+                self.references.push(Reference {
+                    name: ident_name(&assign_stmt.ident).to_string(),
+                    line: assign_stmt.line,
+                    col: assign_stmt.col,
+                });
+                self.add_reads(&assign_stmt.expr);
+            }
+            Stmt::Ctrl(CtrlStmt::Print(print_stmt)) => {
+                for expr in &print_stmt.exprs {
+                    self.add_reads(expr);
+                }
+            }
+            Stmt::Ctrl(CtrlStmt::Read(read_stmt)) => {
+                for (ident, (line, col)) in read_stmt.idents.iter().zip(&read_stmt.ident_positions)
+                {
+                    self.references.push(Reference {
+                        name: ident_name(ident).to_string(),
+                        line: *line,
+                        col: *col,
+                    });
+                }
+            }
+            Stmt::Ctrl(CtrlStmt::If(if_stmt)) => {
+                self.add_reads(&if_stmt.condition);
+                self.add_stmt_list(&if_stmt.then_stmts);
+                if let Some(else_stmts) = &if_stmt.else_stmts {
+                    self.add_stmt_list(else_stmts);
+                }
+            }
+            Stmt::Ctrl(CtrlStmt::Loop(loop_stmt)) => {
+                self.add_reads(&loop_stmt.condition);
+                self.add_stmt_list(&loop_stmt.stmts);
+            }
+        }
+    }
+
+    fn add_reads(&mut self, expr: &crate::parser::Expr) {
+        for primary in expr.idents() {
+            self.references.push(Reference {
+                name: ident_name(&primary.constant).to_string(),
+                line: primary.line,
+                col: primary.col,
+            });
+        }
+    }
+
+    // The identifier under an LSP position (0 based line and character).
+    fn reference_at(&self, line: u32, character: u32) -> Option<&Reference> {
+        self.references.iter().find(|reference| {
+            reference.line == line + 1
+                && reference.col <= character
+                && character <= reference.col + utf16_len(&reference.name)
+        })
+    }
+
+    fn declaration(&self, name: &str) -> Option<&Declaration> {
+        self.declarations
+            .iter()
+            .find(|declaration| declaration.name == name)
+    }
+}
+
+struct Document {
+    source: IncrementalParser,
+    index: Option<Index>,
+}
+
+// LSP positions count UTF-16 code units, the lexer counts chars.
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+// Converts an LSP position into a byte offset, clamped to the end of its line, or of the
+// text for a line past its end.
+fn offset(text: &str, position: &Json) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize;
+    let character = position["character"].as_u64()? as usize;
+    let line_start = match line {
+        0 => 0,
+        _ => match text.match_indices('\n').nth(line - 1) {
+            Some((i, _)) => i + 1,
+            None => return Some(text.len()),
+        },
+    };
+    let rest = &text[line_start..];
+    let line_len = rest.find('\n').unwrap_or(rest.len());
+    let mut units = 0;
+    let col = rest[..line_len]
+        .char_indices()
+        .find(|(_, ch)| {
+            units += ch.len_utf16();
+            units > character
+        })
+        .map_or(line_len, |(i, _)| i);
+    Some(line_start + col)
+}
+
+// Converts a 1 based line and 0 based column into an LSP range covering `len` UTF-16
+// code units.
+fn range(line: u32, col: u32, len: u32) -> Json {
+    let line = line.saturating_sub(1);
+    json!({
+        "start": { "line": line, "character": col },
+        "end": { "line": line, "character": col + len },
+    })
+}
+
+fn line_range(text: &str, line: u32) -> Json {
+    let len = text
+        .lines()
+        .nth(line.saturating_sub(1) as usize)
+        .map_or(0, utf16_len);
+    range(line, 0, len)
+}
+
+fn diagnostic(text: &str, line: u32, severity: u32, message: String) -> Json {
+    json!({
+        "range": line_range(text, line),
+        "severity": severity,
+        "source": "oxide",
+        "message": message,
+    })
+}
+
+// A language server speaking LSP over any reader and writer, see run.
+// Documents are synchronized incrementally and kept parsed with IncrementalParser.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Handles one incoming message, returning the messages to send back.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        // A message with an id but no method is a response, and the server sends no
+        // requests to get one.
+        let Some(method) = message["method"].as_str() else {
+            return vec![];
+        };
+        let params = &message["params"];
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => return self.handle_notification(method, params),
+        };
+        if self.shutdown {
+            return vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": INVALID_REQUEST, "message": "Server is shutting down." },
+            })];
+        }
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 2,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": {},
+                    "documentSymbolProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": { "name": "oxide-lsp" },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => Ok(self.hover(params)),
+            "textDocument/definition" => Ok(self.definition(params)),
+            "textDocument/completion" => Ok(self.completion(params)),
+            "textDocument/documentSymbol" => Ok(self.document_symbols(params)),
+            "textDocument/formatting" => Ok(self.formatting(params)),
+            _ => Err(format!("Unhandled method {}.", method)),
+        };
+        vec![match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(message) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": METHOD_NOT_FOUND, "message": message },
+            }),
+        }]
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+        let (source, previous) = match method {
+            "textDocument/didOpen" => match params["textDocument"]["text"].as_str() {
+                Some(text) => (IncrementalParser::new(text.to_string()), None),
+                None => return vec![],
+            },
+            "textDocument/didChange" => {
+                let Some(document) = self.documents.remove(&uri) else {
+                    return vec![];
+                };
+                let mut source = document.source;
+                let changes = params["contentChanges"].as_array().cloned();
+                for change in changes.unwrap_or_default() {
+                    let Some(text) = change["text"].as_str() else {
+                        continue;
+                    };
+                    let range = &change["range"];
+                    if range.is_null() {
+                        source = IncrementalParser::new(text.to_string());
+                        continue;
+                    }
+                    let start = offset(source.text(), &range["start"]);
+                    let end = offset(source.text(), &range["end"]);
+                    if let (Some(start), Some(end)) = (start, end) {
+                        source.edit(TextEdit {
+                            range: start..end.max(start),
+                            text: text.to_string(),
+                        });
+                    }
+                }
+                (source, document.index)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![];
+            }
+            _ => return vec![],
+        };
+        vec![self.update(uri, source, previous)]
+    }
+
+    // Stores a new version of a document and returns its diagnostics notification.
+    fn update(&mut self, uri: String, source: IncrementalParser, previous: Option<Index>) -> Json {
+        let mut diagnostics: Vec<Json> = vec![];
+        let text = source.text();
+        let index = match source.program() {
+            Ok(program) => {
+                if let Err(semantic_errors) = Analyzer::new().with_limits(LIMITS).analyze(program) {
+                    for error in semantic_errors {
+                        diagnostics.push(diagnostic(
+                            text,
+                            error.line,
+                            SEVERITY_ERROR,
+                            error.to_string(),
+                        ));
+                    }
+                }
+                let mut config = LintConfig::new();
+                if let Err(msg) = config.apply_directives(text) {
+                    diagnostics.push(diagnostic(text, 1, SEVERITY_ERROR, msg));
+                }
+                for lint in Linter::new(&config).with_limits(LIMITS).lint(program) {
+                    let severity = match lint.level {
+                        LintLevel::Deny => SEVERITY_ERROR,
+                        _ => SEVERITY_WARNING,
+                    };
+                    let message = format!("{} [{}]", lint.msg, lint.rule.name());
+                    diagnostics.push(diagnostic(text, lint.line, severity, message));
+                }
+                Some(Index::build(program, text))
+            }
+            Err(parse_error) => {
+                diagnostics.push(diagnostic(
+                    text,
+                    parse_error.line,
+                    SEVERITY_ERROR,
+                    parse_error.to_string(),
+                ));
+                None
+            }
+        };
+        // Keep answering queries from the last version that parsed.
+        self.documents.insert(
+            uri.clone(),
+            Document {
+                source,
+                index: index.or(previous),
+            },
+        );
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })
+    }
+
+    fn document(&self, params: &Json) -> Option<&Document> {
+        self.documents.get(params["textDocument"]["uri"].as_str()?)
+    }
+
+    fn index(&self, params: &Json) -> Option<&Index> {
+        self.document(params)?.index.as_ref()
+    }
+
+    fn reference<'a>(&'a self, params: &Json) -> Option<(&'a Index, &'a Reference)> {
+        let index = self.index(params)?;
+        let line = params["position"]["line"].as_u64()? as u32;
+        let character = params["position"]["character"].as_u64()? as u32;
+        Some((index, index.reference_at(line, character)?))
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let declaration = self
+            .reference(params)
+            .and_then(|(index, reference)| index.declaration(&reference.name));
+        match declaration {
+            Some(declaration) => json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": format!("```oxide\n{}\n```", declaration.detail),
+                },
+            }),
+            None => Json::Null,
+        }
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let declaration = self
+            .reference(params)
+            .and_then(|(index, reference)| index.declaration(&reference.name));
+        match declaration {
+            Some(declaration) => json!({
+                "uri": params["textDocument"]["uri"],
+                "range": range(
+                    declaration.line,
+                    declaration.col,
+                    utf16_len(&declaration.name),
+                ),
+            }),
+            None => Json::Null,
+        }
+    }
+
+    fn completion(&self, params: &Json) -> Json {
+        let mut items: Vec<Json> = KEYWORDS
+            .iter()
+            .map(|keyword| json!({ "label": keyword, "kind": COMPLETION_KEYWORD }))
+            .collect();
+        // One item per builtin, listing the signatures of all its overloads.
+        for (i, builtin) in BUILTINS.iter().enumerate() {
+            if BUILTINS[..i].iter().any(|other| other.name == builtin.name) {
+                continue;
+            }
+            let signatures: Vec<String> = BUILTINS
+                .iter()
+                .filter(|overload| overload.name == builtin.name)
+                .map(Builtin::signature)
+                .collect();
+            items.push(json!({
+                "label": builtin.name,
+                "kind": COMPLETION_FUNCTION,
+                "detail": signatures.join("\n"),
+                "documentation": builtin.doc,
+            }));
+        }
+        if let Some(index) = self.index(params) {
+            for declaration in &index.declarations {
+                let kind = match declaration.is_const {
+                    true => COMPLETION_CONSTANT,
+                    false => COMPLETION_VARIABLE,
+                };
+                items.push(json!({
+                    "label": declaration.name,
+                    "kind": kind,
+                    "detail": declaration.detail,
+                }));
+            }
+        }
+        Json::Array(items)
+    }
+
+    fn document_symbols(&self, params: &Json) -> Json {
+        let symbols = match self.index(params) {
+            Some(index) => index
+                .declarations
+                .iter()
+                .map(|declaration| {
+                    let range = range(
+                        declaration.line,
+                        declaration.col,
+                        utf16_len(&declaration.name),
+                    );
+                    let kind = match declaration.is_const {
+                        true => SYMBOL_CONSTANT,
+                        false => SYMBOL_VARIABLE,
+                    };
+                    json!({
+                        "name": declaration.name,
+                        "detail": declaration.detail,
+                        "kind": kind,
+                        "range": range,
+                        "selectionRange": range,
+                    })
+                })
+                .collect(),
+            None => vec![],
+        };
+        Json::Array(symbols)
+    }
+
+    // One edit replacing the whole document, or null when it doesn't parse.
+    fn formatting(&self, params: &Json) -> Json {
+        let document = match self.document(params) {
+            Some(document) => document,
+            None => return Json::Null,
+        };
+        let text = document.source.text();
+        let mut parser = Parser::new(text);
+        let program = match parser.parse_program() {
+            Ok(program) => program,
+            Err(_) => return Json::Null,
+        };
+        let formatted = Formatter::new(parser.comments()).format_program(&program);
+        let lines = text.lines().count() as u32;
+        json!([{
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": lines + 1, "character": 0 },
+            },
+            "newText": formatted,
+        }])
+    }
+}
+
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Json>> {
+    let mut content_length: Option<usize> = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse().ok();
+        }
+    }
+    let length = content_length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length."))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message<W: Write>(writer: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+// Serves LSP messages framed with Content-Length headers until the exit notification
+// or the end of input.
+pub fn run<R: BufRead, W: Write>(mut reader: R, mut writer: W) -> io::Result<()> {
+    let mut server = Server::new();
+    loop {
+        let message = match read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            // A malformed frame or body is answered with a parse error, the messages after
+            // it are still served.
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                let error = json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": PARSE_ERROR, "message": e.to_string() },
+                });
+                write_message(&mut writer, &error)?;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if message["method"] == "exit" {
+            break;
+        }
+        for response in server.handle(&message) {
+            write_message(&mut writer, &response)?;
+        }
+    }
+    Ok(())
+}
@@ -1,16 +1,164 @@
-use std::{env, fs};
+use std::{env, fs, process};
 
-use oxide::{lexer::Lexer, parser::Parser, Token};
+use oxide::{
+    builtins::{Category, BUILTINS},
+    codegen,
+    interpreter::Interpreter,
+    lint::{LintConfig, LintLevel, Linter, Rule},
+    optimizer::Optimizer,
+    parser::{Parser, Program},
+    semantic::Analyzer,
+};
 
-fn main() {
-    let mut args = env::args();
-    args.next();
-    let file_path = args.next().expect("oxide: Missing filename.");
-    let file = fs::read_to_string(file_path).expect("oxide: Couldn't open file.");
-    let mut parser = Parser::new(&file);
-    let program = match parser.parse_program() {
+fn parse_file(file: &str) -> Program {
+    let mut parser = Parser::new(file);
+    match parser.parse_program() {
         Ok(prog) => prog,
         Err(parse_error) => panic!("{:#?}", parse_error),
+    }
+}
+
+// oxide lint [--allow RULE] [--warn RULE] [--deny RULE] <file>
+fn lint(args: Vec<String>) {
+    let mut config = LintConfig::new();
+    let mut file_path: Option<String> = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match LintLevel::from_name(arg.trim_start_matches("--")) {
+            Some(level) if arg.starts_with("--") => {
+                let rule_name = args.next().expect("oxide: Missing lint rule.");
+                let rule = Rule::from_name(&rule_name)
+                    .unwrap_or_else(|| panic!("oxide: Unknown lint rule '{}'.", rule_name));
+                config.set(rule, level);
+            }
+            _ => file_path = Some(arg),
+        }
+    }
+    let file_path = file_path.expect("oxide: Missing filename.");
+    let file = fs::read_to_string(file_path).expect("oxide: Couldn't open file.");
+    if let Err(msg) = config.apply_directives(&file) {
+        panic!("oxide: {}", msg);
+    }
+    let program = parse_file(&file);
+    let diagnostics = Linter::new(&config).lint(&program);
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    if diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.level == LintLevel::Deny)
+    {
+        process::exit(1);
+    }
+}
+
+// oxide build --emit asm|c|wat [-o <output>] <file>
+fn build(args: Vec<String>) {
+    let mut emit: Option<String> = None;
+    let mut output_path: Option<String> = None;
+    let mut file_path: Option<String> = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--emit" => emit = Some(args.next().expect("oxide: Missing backend after --emit.")),
+            "-o" => output_path = Some(args.next().expect("oxide: Missing output path.")),
+            _ => file_path = Some(arg),
+        }
+    }
+    let file_path = file_path.expect("oxide: Missing filename.");
+    let file = fs::read_to_string(file_path).expect("oxide: Couldn't open file.");
+    let program = parse_file(&file);
+    let symbols = match Analyzer::new().analyze(&program) {
+        Ok(symbols) => symbols,
+        Err(semantic_errors) => panic!("{:#?}", semantic_errors),
+    };
+    let program = Optimizer::new(&symbols).optimize_program(program);
+    let code = match emit.as_deref() {
+        Some("asm") => codegen::asm::Generator::new(&symbols).emit_program(&program),
+        Some("c") => codegen::c::Generator::new(&symbols).emit_program(&program),
+        Some("wat") => codegen::wasm::Generator::new(&symbols).emit_program(&program),
+        Some(backend) => panic!("oxide: Unknown backend '{}'.", backend),
+        None => panic!("oxide: Missing --emit."),
+    };
+    let code = match code {
+        Ok(code) => code,
+        Err(codegen_error) => panic!("{:#?}", codegen_error),
+    };
+    match output_path {
+        Some(path) => fs::write(path, code).expect("oxide: Couldn't write output."),
+        None => print!("{}", code),
+    }
+}
+
+// oxide --list-builtins
+fn list_builtins() {
+    let width = BUILTINS
+        .iter()
+        .map(|builtin| builtin.signature().len())
+        .max()
+        .unwrap_or(0);
+    for category in Category::ALL {
+        println!("{}:", category.name());
+        for builtin in BUILTINS
+            .iter()
+            .filter(|builtin| builtin.category == category)
+        {
+            println!("  {:width$}  {}", builtin.signature(), builtin.doc);
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("lint") {
+        args.remove(0);
+        return lint(args);
+    }
+    if args.first().map(String::as_str) == Some("build") {
+        args.remove(0);
+        return build(args);
+    }
+    if args.first().map(String::as_str) == Some("--list-builtins") {
+        return list_builtins();
+    }
+    let mut file_path: Option<String> = None;
+    let mut dump_cst = false;
+    let mut dump_ast = false;
+    let mut dump_optimized = false;
+    for arg in args {
+        match arg.as_str() {
+            "--dump-cst" => dump_cst = true,
+            "--dump-ast" => dump_ast = true,
+            "--dump-optimized" => dump_optimized = true,
+            _ => file_path = Some(arg),
+        }
+    }
+    let file_path = file_path.expect("oxide: Missing filename.");
+    let file = fs::read_to_string(file_path).expect("oxide: Couldn't open file.");
+    if dump_cst {
+        match Parser::new(&file).parse_syntax() {
+            Ok(root) => print!("{:?}", root),
+            Err(parse_error) => panic!("{:#?}", parse_error),
+        }
+        return;
+    }
+    let program = parse_file(&file);
+    let symbols = match Analyzer::new().analyze(&program) {
+        Ok(symbols) => symbols,
+        Err(semantic_errors) => panic!("{:#?}", semantic_errors),
     };
-    println!("Parsed program: {:#?}", program);
+    if dump_ast {
+        println!("Parsed program: {:#?}", program);
+        println!("Symbol table: {:#?}", symbols);
+        return;
+    }
+    let program = Optimizer::new(&symbols).optimize_program(program);
+    if dump_optimized {
+        println!("Optimized program: {:#?}", program);
+        return;
+    }
+    let mut interpreter = Interpreter::new(&symbols);
+    if let Err(runtime_error) = interpreter.run(&program) {
+        panic!("{:#?}", runtime_error);
+    }
 }
@@ -0,0 +1,117 @@
+use crate::{builtins, format::token_text, interpreter::RuntimeError, lexer, value::Value, Token};
+use std::{collections::HashMap, fmt, sync::Arc};
+
+// Functions an embedding host registers for its scripts to call, like builtins but backed
+// by Rust closures. Each has one typed signature, which the analyzer checks calls against
+// like a builtin's, so a script can't pass a closure arguments of the wrong type. Natives
+// are handed to Analyzer::with_natives and travel with the symbol table from there. The
+// closures are Send and Sync, so an Engine holding them can be shared between threads.
+pub type NativeFn = dyn Fn(&[Value]) -> Result<Value, RuntimeError> + Send + Sync;
+
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: String,
+    pub params: Vec<Token>,
+    pub returns: Token,
+    function: Arc<NativeFn>,
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params: Vec<String> = self.params.iter().map(token_text).collect();
+        write!(
+            f,
+            "{}({}) -> {}",
+            self.name,
+            params.join(", "),
+            token_text(&self.returns)
+        )
+    }
+}
+
+impl NativeFunction {
+    // Checks the types of a call's arguments, None being a type that isn't known before
+    // running.
+    pub fn check_args(&self, arg_types: &[Option<Token>]) -> Result<(), String> {
+        builtins::select(&self.name, &[&self.params], arg_types).map(|_| ())
+    }
+
+    // Calls the closure with arguments widened to its parameter types, and checks it
+    // returned the type it was registered with.
+    pub fn call(&self, args: &[Value]) -> Result<Value, String> {
+        let arg_types: Vec<Option<Token>> =
+            args.iter().map(|arg| Some(arg.type_specifier())).collect();
+        self.check_args(&arg_types)?;
+        let mut args = args.to_vec();
+        builtins::widen(&mut args, &self.params);
+        let value = (self.function)(&args).map_err(|error| error.to_string())?;
+        if !value.has_type(&self.returns) {
+            return Err(format!(
+                "Returned {}, declared to return {}.",
+                value.type_name(),
+                token_text(&self.returns)
+            ));
+        }
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Natives {
+    functions: HashMap<String, NativeFunction>,
+}
+
+impl Natives {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Registers `function` as `name(params) -> returns`. The name must be an identifier
+    // that no builtin or other native function has, and the types must be type
+    // specifiers (INT, FLOAT, BOOL or STRING).
+    pub fn register(
+        &mut self,
+        name: &str,
+        params: &[Token],
+        returns: Token,
+        function: impl Fn(&[Value]) -> Result<Value, RuntimeError> + Send + Sync + 'static,
+    ) -> Result<(), String> {
+        if !lexer::is_identifier(name) {
+            return Err(format!("'{}' is not an identifier.", name));
+        }
+        if builtins::BUILTINS
+            .iter()
+            .any(|builtin| builtin.name == name)
+            || self.functions.contains_key(name)
+        {
+            return Err(format!("A function named '{}' already exists.", name));
+        }
+        let is_type = |token: &Token| {
+            matches!(
+                token,
+                Token::INT | Token::FLOAT | Token::BOOL | Token::STRING
+            )
+        };
+        if let Some(token) = params
+            .iter()
+            .chain([&returns])
+            .find(|token| !is_type(token))
+        {
+            return Err(format!("{:?} is not a type specifier.", token));
+        }
+        self.functions.insert(
+            name.to_string(),
+            NativeFunction {
+                name: name.to_string(),
+                params: params.to_vec(),
+                returns,
+                function: Arc::new(function),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NativeFunction> {
+        self.functions.get(name)
+    }
+}
@@ -0,0 +1,110 @@
+use crate::Token;
+
+// Expression operators as data. The parser reads precedence and associativity from
+// OPERATORS, so adding an operator means adding its token to the lexer, an entry here
+// and its semantics in value.rs.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+    // Can't be chained, `a < b < c` is an error rather than `(a < b) < c`.
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fixity {
+    Prefix,
+    Infix(Assoc),
+    Postfix,
+}
+
+#[derive(Debug)]
+pub struct Operator {
+    pub token: Token,
+    pub fixity: Fixity,
+    // Higher binds tighter.
+    pub precedence: u8,
+}
+
+const fn operator(token: Token, fixity: Fixity, precedence: u8) -> Operator {
+    Operator {
+        token,
+        fixity,
+        precedence,
+    }
+}
+
+pub static OPERATORS: &[Operator] = &[
+    operator(Token::OROP, Fixity::Infix(Assoc::Left), 1),
+    operator(Token::ANDOP, Fixity::Infix(Assoc::Left), 2),
+    operator(Token::EQUALOP, Fixity::Infix(Assoc::None), 3),
+    operator(Token::LTHAN, Fixity::Infix(Assoc::None), 4),
+    operator(Token::GTHAN, Fixity::Infix(Assoc::None), 4),
+    // Bitwise operators bind tighter than comparisons, `a & 1 == 0` is `(a & 1) == 0`.
+    operator(Token::BITOR, Fixity::Infix(Assoc::Left), 5),
+    operator(Token::BITXOR, Fixity::Infix(Assoc::Left), 6),
+    operator(Token::BITAND, Fixity::Infix(Assoc::Left), 7),
+    operator(Token::SHL, Fixity::Infix(Assoc::Left), 8),
+    operator(Token::SHR, Fixity::Infix(Assoc::Left), 8),
+    operator(Token::PLUS, Fixity::Infix(Assoc::Left), 9),
+    operator(Token::MINUS, Fixity::Infix(Assoc::Left), 9),
+    operator(Token::MULT, Fixity::Infix(Assoc::Left), 10),
+    operator(Token::DIV, Fixity::Infix(Assoc::Left), 10),
+    operator(Token::MOD, Fixity::Infix(Assoc::Left), 10),
+    operator(Token::IDIV, Fixity::Infix(Assoc::Left), 10),
+    operator(Token::IMOD, Fixity::Infix(Assoc::Left), 10),
+    operator(Token::NOT, Fixity::Prefix, 11),
+    operator(Token::MINUS, Fixity::Prefix, 11),
+    operator(Token::PLUS, Fixity::Prefix, 11),
+    operator(Token::BITNOT, Fixity::Prefix, 11),
+    // Binds tighter than the prefix operators, `-2 ** 2` is `-(2 ** 2)`. Its right
+    // operand can still start with one, `2 ** -1` is `2 ** (-1)`.
+    operator(Token::POW, Fixity::Infix(Assoc::Right), 12),
+];
+
+// Compound assignment tokens and the binary operator each one applies, `x += e` assigns
+// `x + e` to x.
+pub static COMPOUND_ASSIGNMENTS: &[(Token, Token)] = &[
+    (Token::PLUSASSOP, Token::PLUS),
+    (Token::MINUSASSOP, Token::MINUS),
+    (Token::MULTASSOP, Token::MULT),
+    (Token::DIVASSOP, Token::DIV),
+    (Token::MODASSOP, Token::MOD),
+];
+
+pub fn compound_assignment(token: &Token) -> Option<&'static Token> {
+    COMPOUND_ASSIGNMENTS
+        .iter()
+        .find(|(assignment, _)| assignment == token)
+        .map(|(_, op)| op)
+}
+
+// The operators that only take ints.
+pub fn is_int_only(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::BITAND | Token::BITOR | Token::BITXOR | Token::BITNOT | Token::SHL | Token::SHR
+    )
+}
+
+fn find(token: &Token, matches: impl Fn(Fixity) -> bool) -> Option<&'static Operator> {
+    OPERATORS
+        .iter()
+        .find(|operator| &operator.token == token && matches(operator.fixity))
+}
+
+// A token can be both a prefix and an infix operator, like MINUS. Which one it is
+// depends on whether it comes before an operand or after one, so `a - -b` is a
+// subtraction of a negation.
+pub fn prefix(token: &Token) -> Option<&'static Operator> {
+    find(token, |fixity| fixity == Fixity::Prefix)
+}
+
+pub fn infix(token: &Token) -> Option<&'static Operator> {
+    find(token, |fixity| matches!(fixity, Fixity::Infix(_)))
+}
+
+pub fn postfix(token: &Token) -> Option<&'static Operator> {
+    find(token, |fixity| fixity == Fixity::Postfix)
+}
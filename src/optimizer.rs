@@ -0,0 +1,391 @@
+use crate::{
+    builtins,
+    interpreter::{Budget, Limits},
+    parser::{
+        AssignStmt, BinaryExpr, CallExpr, CastExpr, CtrlStmt, DeclStmt, Expr, IfStmt, LoopStmt,
+        ParenExpr, PostfixExpr, PrefixExpr, PrimaryExpr, PrintStmt, Program, Stmt, StmtList,
+    },
+    semantic::{ident_name, SymbolTable},
+    value::{self, Value},
+    Token,
+};
+
+impl Expr {
+    // The primary expression this is, if it has no operators.
+    pub(crate) fn primary(&self) -> Option<&PrimaryExpr> {
+        match self {
+            Expr::Primary(primary) => Some(primary),
+            _ => None,
+        }
+    }
+
+    fn constant(&self) -> Option<Value> {
+        self.primary()
+            .and_then(|primary| Value::from_literal(&primary.constant))
+    }
+
+    fn from_value(value: Value, (line, col): (u32, u32)) -> Self {
+        Expr::Primary(PrimaryExpr {
+            line,
+            col,
+            constant: value.to_literal(),
+        })
+    }
+
+    // The line and column of the leftmost token, kept on folded results.
+    pub(crate) fn position(&self) -> (u32, u32) {
+        match self {
+            Expr::Primary(PrimaryExpr { line, col, .. })
+            | Expr::Paren(ParenExpr { line, col, .. })
+            | Expr::Prefix(PrefixExpr { line, col, .. })
+            | Expr::Cast(CastExpr { line, col, .. })
+            | Expr::Call(CallExpr { line, col, .. }) => (*line, *col),
+            Expr::Postfix(postfix) => postfix.expr.position(),
+            Expr::Binary(binary) => binary.lhs.position(),
+        }
+    }
+}
+
+// Whether an && (decisive false) or || (decisive true) chain is known to end with the
+// given result once evaluation reaches `expr`: a decisive literal, or a chain of the
+// same operator whose last operand is one. Operands after it are never evaluated.
+fn is_decided(expr: &Expr, op: &Token, decisive: bool) -> bool {
+    match expr {
+        Expr::Binary(binary) if &binary.op == op => is_decided(&binary.rhs, op, decisive),
+        expr => expr.constant() == Some(Value::Bool(decisive)),
+    }
+}
+
+// Whether a literal is the identity element for the given operator and operand type,
+// e.g. 0 for PLUS on ints or 1.0 for MULT on floats.
+fn is_identity(value: &Value, op: &Token, operand_type: &Token) -> bool {
+    let identity = match op {
+        Token::PLUS | Token::MINUS => 0,
+        Token::MULT | Token::DIV | Token::POW => 1,
+        // x div 1.0 rounds x down.
+        Token::IDIV if operand_type == &Token::INT => 1,
+        // The bitwise operators only take ints, x | 0.0 is an error rather than x.
+        Token::BITOR | Token::BITXOR | Token::SHL | Token::SHR if operand_type == &Token::INT => 0,
+        Token::BITAND if operand_type == &Token::INT => -1,
+        _ => return false,
+    };
+    match (value, operand_type) {
+        // "" is the identity of string concatenation.
+        (Value::Str(s), Token::STRING) => op == &Token::PLUS && s.is_empty(),
+        (Value::Int(i), Token::INT) => *i == identity,
+        // x + 0.0 is 0.0 rather than x for x = -0.0, and so is x - -0.0, so it takes a zero
+        // of the other sign to leave every float as it is.
+        (Value::Float(f), Token::FLOAT) => match op {
+            Token::PLUS => *f == 0.0 && f.is_sign_negative(),
+            Token::MINUS => *f == 0.0 && f.is_sign_positive(),
+            _ => *f == identity as f64,
+        },
+        _ => false,
+    }
+}
+
+// Rewrites a program after semantic analysis: folds constant subtrees, drops identity
+// operations, short-circuits constant logical chains and removes statically dead branches.
+// Any fold that would fail (overflow, division by zero) is left for the interpreter to report,
+// and so is any that would go past the limits the program runs with.
+pub struct Optimizer<'a> {
+    symbols: &'a SymbolTable,
+    budget: Budget,
+}
+
+impl<'a> Optimizer<'a> {
+    pub fn new(symbols: &'a SymbolTable) -> Self {
+        Self {
+            symbols,
+            budget: Budget::default(),
+        }
+    }
+
+    // Only folds what the program could compute within these limits, see fold.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.budget = Budget::new(limits);
+        self
+    }
+
+    // The value of a fold, None if it fails, if the optimizer ran out of fuel or time, or
+    // if it's a string longer than the limit. A call whose result would be too long isn't
+    // made at all, see builtins::result_length.
+    fn fold(&mut self, result: impl FnOnce() -> Result<Value, String>) -> Option<Value> {
+        self.budget.step().ok()?;
+        match result().ok()? {
+            Value::Str(s) if self.budget.check_string_length(s.chars().count()).is_err() => None,
+            value => Some(value),
+        }
+    }
+
+    // Statements spliced out of a top level branch keep the branch's span, which their
+    // lines count from.
+    pub fn optimize_program(&mut self, program: Program) -> Program {
+        let (mut stmts, mut spans) = (vec![], vec![]);
+        for (index, stmt) in program.stmt_list.stmts.into_iter().enumerate() {
+            self.optimize_stmt(stmt, &mut stmts);
+            spans.resize(stmts.len(), program.spans.get(index));
+        }
+        Program::new(stmts, spans)
+    }
+
+    fn optimize_stmt_list(&mut self, stmt_list: StmtList) -> StmtList {
+        let mut stmts: Vec<Stmt> = vec![];
+        for stmt in stmt_list.stmts {
+            self.optimize_stmt(stmt, &mut stmts);
+        }
+        StmtList { stmts }
+    }
+
+    fn optimize_stmt(&mut self, stmt: Stmt, stmts: &mut Vec<Stmt>) {
+        match stmt {
+            Stmt::Decl(decl_stmt) => stmts.push(Stmt::Decl(self.optimize_decl_stmt(decl_stmt))),
+            Stmt::Ctrl(ctrl_stmt) => self.optimize_ctrl_stmt(ctrl_stmt, stmts),
+        }
+    }
+
+    fn optimize_decl_stmt(&self, decl_stmt: DeclStmt) -> DeclStmt {
+        // Constant initializers were already folded while building the symbol table.
+        let initializer = match decl_stmt.idents.first() {
+            Some(ident) if decl_stmt.is_const => match self.symbols.constant(ident_name(ident)) {
+                Some(value) => Some(Box::new(Expr::from_value(
+                    value.clone(),
+                    decl_stmt.ident_positions[0],
+                ))),
+                None => decl_stmt.initializer,
+            },
+            _ => decl_stmt.initializer,
+        };
+        DeclStmt {
+            initializer,
+            ..decl_stmt
+        }
+    }
+
+    // Pushes the optimized statement onto stmts. Branches with a constant condition are
+    // spliced into the enclosing list since declarations are not scoped to a block.
+    fn optimize_ctrl_stmt(&mut self, ctrl_stmt: CtrlStmt, stmts: &mut Vec<Stmt>) {
+        let ctrl_stmt = match ctrl_stmt {
+            CtrlStmt::Assign(AssignStmt {
+                line,
+                col,
+                ident,
+                op,
+                expr,
+            }) => CtrlStmt::Assign(AssignStmt {
+                line,
+                col,
+                ident,
+                op,
+                expr: Box::new(self.optimize_expr(*expr)),
+            }),
+            CtrlStmt::Print(PrintStmt { line, exprs }) => CtrlStmt::Print(PrintStmt {
+                line,
+                exprs: exprs
+                    .into_iter()
+                    .map(|expr| self.optimize_expr(expr))
+                    .collect(),
+            }),
+            CtrlStmt::Read(read_stmt) => CtrlStmt::Read(read_stmt),
+            CtrlStmt::If(if_stmt) => {
+                let condition = self.optimize_expr(*if_stmt.condition);
+                let taken = match condition.constant() {
+                    Some(Value::Bool(true)) => Some(if_stmt.then_stmts),
+                    Some(Value::Bool(false)) => if_stmt.else_stmts,
+                    _ => {
+                        stmts.push(Stmt::Ctrl(CtrlStmt::If(IfStmt {
+                            line: if_stmt.line,
+                            condition: Box::new(condition),
+                            then_stmts: self.optimize_stmt_list(if_stmt.then_stmts),
+                            else_stmts: if_stmt
+                                .else_stmts
+                                .map(|else_stmts| self.optimize_stmt_list(else_stmts)),
+                        })));
+                        return;
+                    }
+                };
+                if let Some(taken) = taken {
+                    stmts.extend(self.optimize_stmt_list(taken).stmts);
+                }
+                return;
+            }
+            CtrlStmt::Loop(loop_stmt) => {
+                let condition = self.optimize_expr(*loop_stmt.condition);
+                if let Some(Value::Bool(false)) = condition.constant() {
+                    return;
+                }
+                CtrlStmt::Loop(LoopStmt {
+                    line: loop_stmt.line,
+                    condition: Box::new(condition),
+                    stmts: self.optimize_stmt_list(loop_stmt.stmts),
+                })
+            }
+        };
+        stmts.push(Stmt::Ctrl(ctrl_stmt));
+    }
+
+    fn optimize_expr(&mut self, expr: Expr) -> Expr {
+        match expr {
+            Expr::Primary(primary) => self.optimize_primary_expr(primary),
+            // Parentheses are dropped once their contents fold to a single primary.
+            Expr::Paren(paren) => match self.optimize_expr(*paren.expr) {
+                expr @ Expr::Primary(_) => expr,
+                expr => Expr::Paren(ParenExpr {
+                    expr: Box::new(expr),
+                    ..paren
+                }),
+            },
+            Expr::Prefix(prefix) => {
+                let operand = self.optimize_expr(*prefix.expr);
+                if let Some(value) = operand.constant() {
+                    if let Some(value) = self.fold(|| value::unary_op(&prefix.op, value)) {
+                        return Expr::from_value(value, (prefix.line, prefix.col));
+                    }
+                }
+                // +x and !!x are x, as long as x has a type the operators accept.
+                if prefix.op == Token::PLUS
+                    && matches!(
+                        operand.static_type(self.symbols),
+                        Some(Token::INT | Token::FLOAT)
+                    )
+                {
+                    return operand;
+                }
+                match operand {
+                    Expr::Prefix(inner)
+                        if prefix.op == Token::NOT
+                            && inner.op == Token::NOT
+                            && inner.expr.static_type(self.symbols) == Some(Token::BOOL) =>
+                    {
+                        *inner.expr
+                    }
+                    operand => Expr::Prefix(PrefixExpr {
+                        expr: Box::new(operand),
+                        ..prefix
+                    }),
+                }
+            }
+            Expr::Postfix(postfix) => {
+                let operand = self.optimize_expr(*postfix.expr);
+                if let Some(value) = operand.constant() {
+                    if let Some(value) = self.fold(|| value::unary_op(&postfix.op, value)) {
+                        return Expr::from_value(value, operand.position());
+                    }
+                }
+                Expr::Postfix(PostfixExpr {
+                    op: postfix.op,
+                    expr: Box::new(operand),
+                })
+            }
+            Expr::Binary(binary) => self.optimize_binary_expr(binary),
+            Expr::Cast(cast) => {
+                let operand = self.optimize_expr(*cast.expr);
+                if let Some(value) = operand.constant() {
+                    if let Some(value) = self.fold(|| value::cast(&cast.type_specifier, value)) {
+                        return Expr::from_value(value, (cast.line, cast.col));
+                    }
+                }
+                Expr::Cast(CastExpr {
+                    expr: Box::new(operand),
+                    ..cast
+                })
+            }
+            // A call with constant arguments is folded, unless it does I/O.
+            Expr::Call(call) => {
+                let args: Vec<Expr> = call
+                    .args
+                    .into_iter()
+                    .map(|arg| self.optimize_expr(arg))
+                    .collect();
+                let values: Option<Vec<Value>> = args.iter().map(Expr::constant).collect();
+                let name = ident_name(&call.name);
+                if let Some(values) = values {
+                    let fits = builtins::result_length(name, &values)
+                        .is_none_or(|length| self.budget.check_string_length(length).is_ok());
+                    if fits {
+                        if let Some(value) = self.fold(|| builtins::call(name, values, None)) {
+                            return Expr::from_value(value, (call.line, call.col));
+                        }
+                    }
+                }
+                Expr::Call(CallExpr { args, ..call })
+            }
+        }
+    }
+
+    fn optimize_binary_expr(&mut self, expr: BinaryExpr) -> Expr {
+        let lhs = self.optimize_expr(*expr.lhs);
+        let rhs = self.optimize_expr(*expr.rhs);
+        match expr.op {
+            Token::ANDOP => return self.optimize_logical_expr(expr.op, lhs, rhs, false),
+            Token::OROP => return self.optimize_logical_expr(expr.op, lhs, rhs, true),
+            _ => {}
+        }
+        if let (Some(a), Some(b)) = (lhs.constant(), rhs.constant()) {
+            if let Some(value) = self.fold(|| value::binary_op(&expr.op, a, b)) {
+                return Expr::from_value(value, lhs.position());
+            }
+        }
+        // Identities only hold when both operands are known to have the same type.
+        let operand_type = lhs
+            .static_type(self.symbols)
+            .filter(|lhs_type| rhs.static_type(self.symbols).as_ref() == Some(lhs_type));
+        if let Some(operand_type) = &operand_type {
+            if let Some(value) = rhs.constant() {
+                if is_identity(&value, &expr.op, operand_type) {
+                    return lhs;
+                }
+            }
+            // 0 + x and 1 * x, the left operand can only be dropped for commutative
+            // operators.
+            if let (
+                Some(value),
+                Token::PLUS | Token::MULT | Token::BITAND | Token::BITOR | Token::BITXOR,
+            ) = (lhs.constant(), &expr.op)
+            {
+                if is_identity(&value, &expr.op, operand_type) {
+                    return rhs;
+                }
+            }
+        }
+        Expr::Binary(BinaryExpr {
+            op: expr.op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        })
+    }
+
+    // Drops an operand that cannot change the result (true for &&, false for ||) and
+    // cuts off the right operand once the left one decides the result, since evaluation
+    // would never reach it.
+    fn optimize_logical_expr(&self, op: Token, lhs: Expr, rhs: Expr, decisive: bool) -> Expr {
+        if is_decided(&lhs, &op, decisive) {
+            return lhs;
+        }
+        // Without knowing the other operand is a bool, dropping a literal could hide a
+        // type error, so neutral operands are only removed when both are bools.
+        let is_bool = |expr: &Expr| expr.static_type(self.symbols) == Some(Token::BOOL);
+        let neutral = Some(Value::Bool(!decisive));
+        if is_bool(&lhs) && is_bool(&rhs) {
+            if lhs.constant() == neutral {
+                return rhs;
+            }
+            if rhs.constant() == neutral {
+                return lhs;
+            }
+        }
+        Expr::Binary(BinaryExpr {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        })
+    }
+
+    // Identifiers naming constants are replaced by their folded value.
+    fn optimize_primary_expr(&self, expr: PrimaryExpr) -> Expr {
+        match self.symbols.constant(ident_name(&expr.constant)) {
+            Some(value) => Expr::from_value(value.clone(), (expr.line, expr.col)),
+            None => Expr::Primary(expr),
+        }
+    }
+}
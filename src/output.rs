@@ -0,0 +1,122 @@
+use crate::value::Value;
+use std::io::{self, Write};
+
+// Where print statements and the write builtin send their text. The interpreter hands
+// over the text of a whole print statement at once, line break included when the print
+// format has one, and whatever write was given as is.
+pub trait Output {
+    fn write(&mut self, text: &str) -> Result<(), String>;
+}
+
+impl<T: Output + ?Sized> Output for &mut T {
+    fn write(&mut self, text: &str) -> Result<(), String> {
+        (**self).write(text)
+    }
+}
+
+// The default, output goes to stdout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stdout;
+
+impl Output for Stdout {
+    fn write(&mut self, text: &str) -> Result<(), String> {
+        Writer(io::stdout().lock()).write(text)
+    }
+}
+
+// Keeps output as a list of lines without their line breaks. Text after the last line
+// break is kept as an unfinished last line that the next output continues.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Capture {
+    lines: Vec<String>,
+    unfinished: bool,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn into_lines(self) -> Vec<String> {
+        self.lines
+    }
+}
+
+impl Output for Capture {
+    fn write(&mut self, text: &str) -> Result<(), String> {
+        for chunk in text.split_inclusive('\n') {
+            let line = chunk.strip_suffix('\n');
+            let content = line.unwrap_or(chunk);
+            match self.lines.last_mut() {
+                Some(last) if self.unfinished => last.push_str(content),
+                _ => self.lines.push(content.to_string()),
+            }
+            self.unfinished = line.is_none();
+        }
+        Ok(())
+    }
+}
+
+// Writes output to a file or anything else that implements io::Write.
+#[derive(Debug)]
+pub struct Writer<W: Write>(pub W);
+
+impl<W: Write> Output for Writer<W> {
+    fn write(&mut self, text: &str) -> Result<(), String> {
+        self.0
+            .write_all(text.as_bytes())
+            .map_err(|error| format!("Couldn't write output: {}.", error))
+    }
+}
+
+// Hands output to a closure as it is produced.
+pub struct Callback<F: FnMut(&str)>(pub F);
+
+impl<F: FnMut(&str)> Output for Callback<F> {
+    fn write(&mut self, text: &str) -> Result<(), String> {
+        (self.0)(text);
+        Ok(())
+    }
+}
+
+// How a print statement turns its values into text. By default the values are printed
+// next to each other, floats as short as they can be written without losing precision,
+// and every print ends its line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintFormat {
+    pub separator: String,
+    // Digits after the decimal point, None for the shortest exact form.
+    pub float_precision: Option<usize>,
+    pub trailing_newline: bool,
+}
+
+impl Default for PrintFormat {
+    fn default() -> Self {
+        Self {
+            separator: String::new(),
+            float_precision: None,
+            trailing_newline: true,
+        }
+    }
+}
+
+impl PrintFormat {
+    pub fn format(&self, values: &[Value]) -> String {
+        let texts: Vec<String> = values
+            .iter()
+            .map(|value| match (value, self.float_precision) {
+                (Value::Float(x), Some(precision)) => format!("{:.*}", precision, x),
+                _ => value.to_string(),
+            })
+            .collect();
+        let mut text = texts.join(&self.separator);
+        if self.trailing_newline {
+            text.push('\n');
+        }
+        text
+    }
+}
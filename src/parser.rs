@@ -1,8 +1,17 @@
-use crate::{lexer::Lexer, Token};
-use std::{error::Error, fmt, ops::Add};
+use crate::{
+    lexer::{self, Lexer},
+    operators::{self, Assoc, Fixity},
+    syntax::{
+        Checkpoint, GreenNodeBuilder, LineIndex, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken,
+    },
+    Token,
+};
+use std::{error::Error, fmt, ops::Range};
 
 #[derive(Debug)]
 pub struct ParseError {
+    // The line of the token the parser stopped at.
+    pub line: u32,
     msg: String,
 }
 
@@ -19,420 +28,1148 @@ impl fmt::Display for ParseError {
     }
 }
 
+// Where a piece of the source sits: its byte range and the lines it starts and ends on,
+// with the column just past its last character.
+#[derive(Debug, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub end_col: u32,
+}
+
+// The span of every top level statement, semicolon included. The lines in a statement's
+// tree count from the line it starts on, so moving a statement only changes its span.
+//
+// Spans from `split` on are stored as their distance back from `end` and `end_line`,
+// like a gap buffer: the incremental parser moves every statement below an edit by
+// changing those two, and only converts the spans between the last edit and this one.
+pub struct Spans {
+    spans: Vec<Span>,
+    split: usize,
+    end: usize,
+    end_line: u32,
+}
+
+impl Spans {
+    fn new(spans: Vec<Span>) -> Self {
+        Self {
+            split: spans.len(),
+            spans,
+            end: 0,
+            end_line: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Span {
+        match index < self.split {
+            true => self.spans[index],
+            false => self.flip(self.spans[index]),
+        }
+    }
+
+    // Converts a span between the two ways they're stored.
+    fn flip(&self, span: Span) -> Span {
+        Span {
+            start: self.end - span.start,
+            end: self.end - span.end,
+            start_line: self.end_line - span.start_line,
+            end_line: self.end_line - span.end_line,
+            end_col: span.end_col,
+        }
+    }
+
+    // The first index whose span doesn't satisfy `pred`, which holds for a prefix of them.
+    pub(crate) fn partition_point(&self, pred: impl Fn(&Span) -> bool) -> usize {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            match pred(&self.get(mid)) {
+                true => low = mid + 1,
+                false => high = mid,
+            }
+        }
+        low
+    }
+
+    // Moves the spans from `index` on by `delta` bytes and `line_delta` lines.
+    pub(crate) fn shift(&mut self, index: usize, delta: isize, line_delta: i32) {
+        if self.split == self.len() && index < self.split {
+            let last = self.spans[self.split - 1];
+            self.end = last.end;
+            self.end_line = last.end_line;
+        }
+        while self.split > index {
+            self.split -= 1;
+            self.spans[self.split] = self.flip(self.spans[self.split]);
+        }
+        while self.split < index {
+            self.spans[self.split] = self.flip(self.spans[self.split]);
+            self.split += 1;
+        }
+        self.end = self.end.wrapping_add_signed(delta);
+        self.end_line = self.end_line.wrapping_add_signed(line_delta);
+    }
+
+    // Replaces the spans in `range`, which must end where the spans were last shifted.
+    pub(crate) fn splice(&mut self, range: Range<usize>, spans: Vec<Span>) {
+        debug_assert_eq!(range.end, self.split);
+        self.split = range.start + spans.len();
+        self.spans.splice(range, spans);
+    }
+}
+
+impl fmt::Debug for Spans {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries((0..self.len()).map(|index| self.get(index)))
+            .finish()
+    }
+}
+
+// A token with the byte range it was read from.
+struct Lexeme {
+    token: Token,
+    start: usize,
+    end: usize,
+    line: u32,
+    col: u32,
+}
+
+fn same_kind(token: &Token, target: &Token) -> bool {
+    std::mem::discriminant(token) == std::mem::discriminant(target)
+}
+
+// Parses into the concrete syntax tree (see syntax.rs) and lowers that into the AST.
+// The input is lexed up front; the trivia between two tokens is added to the tree right
+// before the later token, or before a node starts so nodes don't begin with trivia.
 pub struct Parser<'a> {
+    input: &'a str,
     lexer: Lexer<'a>,
+    lexemes: Vec<Lexeme>,
+    // Index of the next lexeme, and how much of the input is in the tree so far.
+    pos: usize,
+    emitted: usize,
+    builder: GreenNodeBuilder,
+    first_line: u32,
+    first_col: u32,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
+        Self::new_at(input, 1, 0)
+    }
+
+    // Parses a slice of a larger source starting at the given line and column, see
+    // Lexer::new_at.
+    pub fn new_at(input: &'a str, line: u32, col: u32) -> Self {
+        let mut lexer = Lexer::new_at(input, line, col);
+        let mut lexemes: Vec<Lexeme> = vec![];
+        while let Some(token) = lexer.next() {
+            lexemes.push(Lexeme {
+                token,
+                start: lexer.token_offset(),
+                end: lexer.position().2,
+                line: lexer.token_line(),
+                col: lexer.token_col(),
+            });
+        }
         Self {
-            lexer: Lexer::new(input),
+            input,
+            lexer,
+            lexemes,
+            pos: 0,
+            emitted: 0,
+            builder: GreenNodeBuilder::new(),
+            first_line: line,
+            first_col: col,
         }
     }
 
-    fn next_token(&mut self) -> Result<Token, ParseError> {
-        match self.lexer.next() {
-            Some(t) => Ok(t),
-            None => Err(ParseError {
-                msg: "Unexpected end of input.".to_string(),
-            }),
+    // Line comments in the input, see Lexer::comments.
+    pub fn comments(&self) -> &[(u32, String)] {
+        self.lexer.comments()
+    }
+
+    // Line, column and byte offset of the end of the input.
+    pub fn position(&self) -> (u32, u32, usize) {
+        self.lexer.position()
+    }
+
+    fn line_index(&self) -> LineIndex<'a> {
+        LineIndex::new(self.input, self.first_line, self.first_col)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.lexemes.get(self.pos).map(|lexeme| &lexeme.token)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.lexemes.get(self.pos + n).map(|lexeme| &lexeme.token)
+    }
+
+    fn at(&self, target: &Token) -> bool {
+        self.peek().is_some_and(|token| same_kind(token, target))
+    }
+
+    // The line of the next token, or of the last one at the end of the input.
+    fn error(&self, msg: String) -> ParseError {
+        let line = self
+            .lexemes
+            .get(self.pos)
+            .or(self.lexemes.last())
+            .map_or(self.first_line, |lexeme| lexeme.line);
+        ParseError { line, msg }
+    }
+
+    fn end_of_input(&self) -> ParseError {
+        self.error("Unexpected end of input.".to_string())
+    }
+
+    // Adds the trivia before the next token to the node being built.
+    fn trivia(&mut self) {
+        let end = self
+            .lexemes
+            .get(self.pos)
+            .map_or(self.input.len(), |lexeme| lexeme.start);
+        for (token, text) in lexer::trivia(&self.input[self.emitted..end]) {
+            self.builder.token(token, text);
         }
+        self.emitted = end;
     }
 
-    fn cmp_next_token(&mut self, target: Token) -> Result<Token, ParseError> {
-        let token = self.next_token()?;
-        if std::mem::discriminant(&token) == std::mem::discriminant(&target) {
-            return Ok(token);
+    // Adds the next token to the node being built.
+    fn bump(&mut self) {
+        self.trivia();
+        let lexeme = &self.lexemes[self.pos];
+        self.builder
+            .token(lexeme.token.clone(), &self.input[lexeme.start..lexeme.end]);
+        self.emitted = lexeme.end;
+        self.pos += 1;
+    }
+
+    fn eat(&mut self, target: &Token) -> bool {
+        let found = self.at(target);
+        if found {
+            self.bump();
         }
-        if let Token::ERROR(msg) = token {
-            Err(ParseError { msg })
-        } else {
-            self.lexer.push_back(token);
-            Err(ParseError {
-                msg: format!("Expected token {:?}.", target),
-            })
+        found
+    }
+
+    fn start_node(&mut self, kind: SyntaxKind) {
+        self.trivia();
+        self.builder.start_node(kind);
+    }
+
+    fn checkpoint(&mut self) -> Checkpoint {
+        self.trivia();
+        self.builder.checkpoint()
+    }
+
+    // The tree built so far, with the rest of the input's trivia in the root.
+    fn finish(&mut self) -> SyntaxNode {
+        self.trivia();
+        self.builder.finish_node();
+        SyntaxNode::new_root(std::mem::take(&mut self.builder).finish())
+    }
+
+    fn cmp_next_token(&mut self, target: Token) -> Result<(), ParseError> {
+        match self.peek() {
+            None => return Err(self.end_of_input()),
+            Some(token) if same_kind(token, &target) => {}
+            Some(Token::ERROR(msg)) => return Err(self.error(msg.clone())),
+            // Every ( reads its own ) with close_paren, so one found anywhere else is
+            // unmatched.
+            Some(Token::CLOSEPAREN) => return Err(self.error("Unmatched ')'.".to_string())),
+            Some(_) => return Err(self.error(format!("Expected token {:?}.", target))),
         }
+        self.bump();
+        Ok(())
     }
 
     pub fn cmp_next_token_many(
         &mut self,
-        targets: Vec<Token>,
+        targets: &[Token],
         stmt_type: &str,
-    ) -> Result<Token, ParseError> {
-        let token = self.next_token()?;
-        for target in targets {
-            if std::mem::discriminant(&token) == std::mem::discriminant(&target) {
-                return Ok(token);
+    ) -> Result<(), ParseError> {
+        match self.peek() {
+            None => return Err(self.end_of_input()),
+            Some(token) if targets.iter().any(|target| same_kind(token, target)) => {}
+            Some(Token::ERROR(msg)) => return Err(self.error(msg.clone())),
+            Some(_) => return Err(self.error(format!("Expected {}.", stmt_type))),
+        }
+        self.bump();
+        Ok(())
+    }
+
+    // Reads a (, returning its line and column for close_paren.
+    fn open_paren(&mut self) -> Result<(u32, u32), ParseError> {
+        let position = self
+            .lexemes
+            .get(self.pos)
+            .map(|lexeme| (lexeme.line, lexeme.col));
+        self.cmp_next_token(Token::OPENPAREN)?;
+        Ok(position.unwrap_or_default())
+    }
+
+    // Reads the ) matching the ( at `open`. A missing one is reported at the (, since
+    // that is where the unbalanced group starts rather than where it was noticed.
+    fn close_paren(&mut self, (line, col): (u32, u32)) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(Token::CLOSEPAREN) => {
+                self.bump();
+                Ok(())
             }
+            Some(Token::ERROR(msg)) => Err(self.error(msg.clone())),
+            _ => Err(ParseError {
+                line,
+                msg: format!(
+                    "Expected ')' to close the '(' at line {}, column {}.",
+                    line,
+                    col + 1
+                ),
+            }),
         }
-        self.lexer.push_back(token);
-        Err(ParseError {
-            msg: format!("Expected {}.", stmt_type),
-        })
     }
 
     // Program ::= INIT <StmtList> HALT
     pub fn parse_program(&mut self) -> Result<Program, ParseError> {
+        let root = self.parse_syntax()?;
+        Ok(lower_program(&root, &self.line_index()))
+    }
+
+    // The concrete syntax tree of a program. Anything after halt isn't part of the
+    // program but is kept in the tree.
+    pub fn parse_syntax(&mut self) -> Result<SyntaxNode, ParseError> {
+        self.builder.start_node(SyntaxKind::Program);
         self.cmp_next_token(Token::INIT)?;
-        let stmt_list = self.parse_stmt_list()?;
+        self.parse_stmt_list()?;
         self.cmp_next_token(Token::HALT)?;
-        Ok(Program { stmt_list })
+        while self.peek().is_some() {
+            self.bump();
+        }
+        Ok(self.finish())
+    }
+
+    // Same as parse_program, also returning the span of the init keyword.
+    pub fn parse_program_with_init(&mut self) -> Result<(Program, Span), ParseError> {
+        let root = self.parse_syntax()?;
+        let index = self.line_index();
+        let init = root
+            .tokens()
+            .next()
+            .expect("program starts with init")
+            .text_range();
+        let (start_line, _) = index.line_col(init.start);
+        let (end_line, end_col) = index.line_col(init.end);
+        let init = Span {
+            start: init.start,
+            end: init.end,
+            start_line,
+            end_line,
+            end_col,
+        };
+        Ok((lower_program(&root, &index), init))
+    }
+
+    // Parses statements up to the end of the input, for re-parsing the part of a program
+    // an edit touched. Spans are relative to the input.
+    pub fn parse_stmts_with_spans(&mut self) -> Result<(Vec<Stmt>, Vec<Span>), ParseError> {
+        self.builder.start_node(SyntaxKind::StmtList);
+        while self.peek().is_some() {
+            self.parse_stmt()?;
+            self.cmp_next_token(Token::SEMICOL)?;
+        }
+        let stmt_list = self.finish();
+        Ok(lower_top_level(&stmt_list, &self.line_index()))
     }
 
-    // Maybe check for stmtlist ending items here like endwhile endif and halt
     // StmtList ::= <Stmt> SEMICOL { <Stmt> SEMICOL }
-    fn parse_stmt_list(&mut self) -> Result<StmtList, ParseError> {
-        let mut stmts: Vec<Stmt> = vec![];
-        let stmt = self.parse_stmt()?;
+    fn parse_stmt_list(&mut self) -> Result<(), ParseError> {
+        self.start_node(SyntaxKind::StmtList);
+        self.parse_stmt()?;
         self.cmp_next_token(Token::SEMICOL)?;
-        stmts.push(stmt);
-
-        loop {
-            match self.cmp_next_token_many(
-                vec![Token::HALT, Token::ENDIF, Token::ENDWHILE],
-                "ending delimiter",
-            ) {
-                Ok(t) => {
-                    self.lexer.push_back(t);
-                    break;
-                }
-                Err(_) => {
-                    let next_stmt = self.parse_stmt()?;
-                    self.cmp_next_token(Token::SEMICOL)?;
-                    stmts.push(next_stmt);
-                }
-            }
+        while !matches!(
+            self.peek(),
+            Some(Token::HALT | Token::ELSE | Token::ENDIF | Token::ENDWHILE)
+        ) {
+            self.parse_stmt()?;
+            self.cmp_next_token(Token::SEMICOL)?;
         }
-
-        Ok(StmtList { stmts })
+        self.builder.finish_node();
+        Ok(())
     }
 
     // Stmt ::= <DeclStmt> | <CtrlStmt>
-    fn parse_stmt(&mut self) -> Result<Stmt, ParseError> {
-        let token = self.next_token()?;
-        let stmt = match token {
-            Token::INT | Token::FLOAT | Token::BOOL | Token::STRING => {
-                self.lexer.push_back(token);
-                let decl_stmt = self.parse_decl_stmt()?;
-                Stmt::Decl(decl_stmt)
+    fn parse_stmt(&mut self) -> Result<(), ParseError> {
+        match self.peek() {
+            None => Err(self.end_of_input()),
+            Some(Token::CONST | Token::INT | Token::FLOAT | Token::BOOL | Token::STRING) => {
+                self.parse_decl_stmt()
             }
-            _ => {
-                self.lexer.push_back(token);
-                let ctrl_stmt = self.parse_ctrl_stmt()?;
-                Stmt::Ctrl(ctrl_stmt)
-            }
-        };
-        Ok(stmt)
+            Some(_) => self.parse_ctrl_stmt(),
+        }
     }
 
     // DeclStmt ::= (INT | FLOAT | BOOL | STRING) IDENT { COMMA IDENT }
-    fn parse_decl_stmt(&mut self) -> Result<DeclStmt, ParseError> {
-        let mut idents: Vec<Token> = vec![];
-        let type_specifier = self.cmp_next_token_many(
-            vec![Token::INT, Token::FLOAT, Token::BOOL, Token::STRING],
+    //            | CONST (INT | FLOAT | BOOL | STRING) IDENT ASSOP <Expr>
+    fn parse_decl_stmt(&mut self) -> Result<(), ParseError> {
+        self.start_node(SyntaxKind::DeclStmt);
+        let is_const = self.eat(&Token::CONST);
+        self.cmp_next_token_many(
+            &[Token::INT, Token::FLOAT, Token::BOOL, Token::STRING],
             "type specifier",
         )?;
-        let token = self.cmp_next_token(Token::IDENT("".to_string()))?;
-        idents.push(token);
-        while let Ok(_) = self.cmp_next_token(Token::COMMA) {
-            let token = self.cmp_next_token(Token::IDENT("".to_string()))?;
-            idents.push(token);
-        }
-        Ok(DeclStmt {
-            type_specifier,
-            idents,
-        })
+        self.cmp_next_token(Token::IDENT(String::new()))?;
+        if is_const {
+            // Constants are declared one at a time and must be initialized.
+            self.cmp_next_token(Token::ASSOP)?;
+            self.parse_expr()?;
+        } else {
+            while self.eat(&Token::COMMA) {
+                self.cmp_next_token(Token::IDENT(String::new()))?;
+            }
+        }
+        self.builder.finish_node();
+        Ok(())
     }
 
-    // CtrlStmt ::= <AssignStmt> | <PrintStmt> | <IfStmt> | <LoopStmt>
-    fn parse_ctrl_stmt(&mut self) -> Result<CtrlStmt, ParseError> {
-        let token = self.next_token()?;
-        let stmt = match token {
-            Token::IDENT(s) => {
-                self.lexer.push_back(Token::IDENT(s));
-                let assign_stmt = self.parse_assign_stmt()?;
-                CtrlStmt::Assign(assign_stmt)
-            }
-            Token::PRINT => {
-                self.lexer.push_back(token);
-                let print_stmt = self.parse_print_stmt()?;
-                CtrlStmt::Print(print_stmt)
-            }
-            Token::IF => {
-                self.lexer.push_back(token);
-                let if_stmt = self.parse_if_stmt()?;
-                CtrlStmt::If(if_stmt)
-            }
-            Token::WHILE => {
-                self.lexer.push_back(token);
-                let loop_stmt = self.parse_loop_stmt()?;
-                CtrlStmt::Loop(loop_stmt)
-            }
-            _ => {
-                self.lexer.push_back(token);
-                return Err(ParseError {
-                    msg: "Expected control statement".to_string(),
-                });
-            }
-        };
-        Ok(stmt)
+    // CtrlStmt ::= <AssignStmt> | <PrintStmt> | <ReadStmt> | <IfStmt> | <LoopStmt>
+    fn parse_ctrl_stmt(&mut self) -> Result<(), ParseError> {
+        match self.peek() {
+            None => Err(self.end_of_input()),
+            Some(Token::IDENT(_)) => self.parse_assign_stmt(),
+            Some(Token::PRINT) => self.parse_print_stmt(),
+            Some(Token::READ) => self.parse_read_stmt(),
+            Some(Token::IF) => self.parse_if_stmt(),
+            Some(Token::WHILE) => self.parse_loop_stmt(),
+            Some(_) => Err(self.error("Expected control statement".to_string())),
+        }
     }
 
-    // AssignStmt ::= IDENT ASSOP <OrExpr>
-    fn parse_assign_stmt(&mut self) -> Result<AssignStmt, ParseError> {
-        let ident = self.cmp_next_token(Token::IDENT("".to_string()))?;
-        self.cmp_next_token(Token::ASSOP)?;
-        let expr = self.parse_or_expr()?;
-        Ok(AssignStmt { ident, expr })
+    // AssignStmt ::= IDENT (ASSOP | PLUSASSOP | MINUSASSOP | MULTASSOP | DIVASSOP | MODASSOP) <Expr>
+    fn parse_assign_stmt(&mut self) -> Result<(), ParseError> {
+        self.start_node(SyntaxKind::AssignStmt);
+        self.cmp_next_token(Token::IDENT(String::new()))?;
+        match self.peek().and_then(operators::compound_assignment) {
+            Some(_) => self.bump(),
+            None => self.cmp_next_token(Token::ASSOP)?,
+        }
+        self.parse_expr()?;
+        self.builder.finish_node();
+        Ok(())
     }
 
-    // PrintStmt ::= PRINT OPENPAREN <OrExpr> { COMMA <OrExpr> } CLOSEPAREN
-    fn parse_print_stmt(&mut self) -> Result<PrintStmt, ParseError> {
-        let mut exprs: Vec<OrExpr> = vec![];
+    // PrintStmt ::= PRINT OPENPAREN <Expr> { COMMA <Expr> } CLOSEPAREN
+    fn parse_print_stmt(&mut self) -> Result<(), ParseError> {
+        self.start_node(SyntaxKind::PrintStmt);
         self.cmp_next_token(Token::PRINT)?;
-        self.cmp_next_token(Token::OPENPAREN)?;
-        let expr = self.parse_or_expr()?;
-        exprs.push(expr);
-        while let Ok(_) = self.cmp_next_token(Token::COMMA) {
-            let expr = self.parse_or_expr()?;
-            exprs.push(expr);
+        let open = self.open_paren()?;
+        self.parse_expr()?;
+        while self.eat(&Token::COMMA) {
+            self.parse_expr()?;
         }
-        self.cmp_next_token(Token::CLOSEPAREN)?;
-        Ok(PrintStmt { exprs })
+        self.close_paren(open)?;
+        self.builder.finish_node();
+        Ok(())
     }
 
-    // Contains odd err handling.
-    // IfStmt ::= IF OPENPAREN <OrExpr> CLOSEPAREN THEN <StmtList> [ ELSE <StmtList> ] ENDIF
-    fn parse_if_stmt(&mut self) -> Result<IfStmt, ParseError> {
+    // ReadStmt ::= READ OPENPAREN IDENT { COMMA IDENT } CLOSEPAREN
+    fn parse_read_stmt(&mut self) -> Result<(), ParseError> {
+        self.start_node(SyntaxKind::ReadStmt);
+        self.cmp_next_token(Token::READ)?;
+        let open = self.open_paren()?;
+        self.cmp_next_token(Token::IDENT(String::new()))?;
+        while self.eat(&Token::COMMA) {
+            self.cmp_next_token(Token::IDENT(String::new()))?;
+        }
+        self.close_paren(open)?;
+        self.builder.finish_node();
+        Ok(())
+    }
+
+    // IfStmt ::= IF OPENPAREN <Expr> CLOSEPAREN THEN <StmtList> [ ELSE <StmtList> ] ENDIF
+    fn parse_if_stmt(&mut self) -> Result<(), ParseError> {
+        self.start_node(SyntaxKind::IfStmt);
         self.cmp_next_token(Token::IF)?;
-        self.cmp_next_token(Token::OPENPAREN)?;
-        let condition = self.parse_or_expr()?;
-        self.cmp_next_token(Token::CLOSEPAREN)?;
+        let open = self.open_paren()?;
+        self.parse_expr()?;
+        self.close_paren(open)?;
         self.cmp_next_token(Token::THEN)?;
-        let then_stmts = self.parse_stmt_list()?;
-        let token = self.next_token()?;
-        let mut else_present = false;
-        let else_stmts = match token {
-            Token::ELSE => {
-                else_present = true;
-                Some(self.parse_stmt_list()?)
+        self.parse_stmt_list()?;
+        match self.peek() {
+            None => return Err(self.end_of_input()),
+            Some(Token::ELSE) => {
+                self.bump();
+                self.parse_stmt_list()?;
+                self.cmp_next_token(Token::ENDIF)?;
             }
-            Token::ENDIF => None,
-            _ => {
-                return Err(ParseError {
-                    msg: "Expected else or endif.".to_string(),
-                })
+            Some(Token::ENDIF) => {
+                self.bump();
             }
-        };
-        if else_present {
-            self.cmp_next_token(Token::ENDIF)?;
+            Some(_) => return Err(self.error("Expected else or endif.".to_string())),
         }
-        Ok(IfStmt {
-            condition,
-            then_stmts,
-            else_stmts,
-        })
+        self.builder.finish_node();
+        Ok(())
     }
 
-    // LoopStmt ::= WHILE OPENPAREN <OrExpr> CLOSEPAREN DO <StmtList> ENDWHILE
-    fn parse_loop_stmt(&mut self) -> Result<LoopStmt, ParseError> {
+    // LoopStmt ::= WHILE OPENPAREN <Expr> CLOSEPAREN DO <StmtList> ENDWHILE
+    fn parse_loop_stmt(&mut self) -> Result<(), ParseError> {
+        self.start_node(SyntaxKind::LoopStmt);
         self.cmp_next_token(Token::WHILE)?;
-        self.cmp_next_token(Token::OPENPAREN)?;
-        let condition = self.parse_or_expr()?;
-        self.cmp_next_token(Token::CLOSEPAREN)?;
+        let open = self.open_paren()?;
+        self.parse_expr()?;
+        self.close_paren(open)?;
         self.cmp_next_token(Token::DO)?;
-        let stmts = self.parse_stmt_list()?;
+        self.parse_stmt_list()?;
         self.cmp_next_token(Token::ENDWHILE)?;
-        Ok(LoopStmt { condition, stmts })
+        self.builder.finish_node();
+        Ok(())
     }
 
-    // OrExpr ::= <AndExpr> { OROP <AndExpr> }
-    fn parse_or_expr(&mut self) -> Result<OrExpr, ParseError> {
-        let mut rhs: Vec<AndExpr> = vec![];
-        let lhs = self.parse_and_expr()?;
-        while let Ok(_) = self.cmp_next_token(Token::OROP) {
-            let expr = self.parse_and_expr()?;
-            rhs.push(expr)
-        }
-        Ok(OrExpr { lhs, rhs })
+    // Expr ::= <PrefixOp> <Expr> | <Expr> <InfixOp> <Expr> | <Expr> <PostfixOp> | <PrimaryExpr>
+    fn parse_expr(&mut self) -> Result<(), ParseError> {
+        self.parse_expr_at(0)
     }
 
-    // AndExpr ::= <EqualExpr> { ANDOP <EqualExpr> }
-    fn parse_and_expr(&mut self) -> Result<AndExpr, ParseError> {
-        let mut rhs: Vec<EqualExpr> = vec![];
-        let lhs = self.parse_equal_expr()?;
-        while let Ok(_) = self.cmp_next_token(Token::ANDOP) {
-            let expr = self.parse_equal_expr()?;
-            rhs.push(expr);
+    // Pratt parsing over operators::OPERATORS. Parses an operand followed by every
+    // operator binding at least as tight as `min_precedence`; the right operand of an
+    // infix operator only takes operators binding tighter than it, or as tight for right
+    // associative ones. Operator nodes are started at a checkpoint taken before their
+    // left operand, since the operator is only seen after that operand was parsed.
+    fn parse_expr_at(&mut self, min_precedence: u8) -> Result<(), ParseError> {
+        let checkpoint = self.checkpoint();
+        match self.peek().and_then(operators::prefix) {
+            Some(operator) => {
+                self.start_node(SyntaxKind::PrefixExpr);
+                self.bump();
+                self.parse_expr_at(operator.precedence)?;
+                self.builder.finish_node();
+            }
+            None => self.parse_primary_expr()?,
         }
-        Ok(AndExpr { lhs, rhs })
-    }
-
-    // EqualExpr ::= <RelExpr> [ EQUALOP <RelExpr> ]
-    fn parse_equal_expr(&mut self) -> Result<EqualExpr, ParseError> {
-        let lhs = self.parse_rel_expr()?;
-        let mut rhs: Option<RelExpr> = None;
-        if let Ok(_) = self.cmp_next_token(Token::EQUALOP) {
-            rhs = Some(self.parse_rel_expr()?);
+        while let Some(token) = self.peek() {
+            if let Some(operator) = operators::postfix(token) {
+                if operator.precedence < min_precedence {
+                    break;
+                }
+                self.builder
+                    .start_node_at(checkpoint, SyntaxKind::PostfixExpr);
+                self.bump();
+                self.builder.finish_node();
+                continue;
+            }
+            let Some(operator) = operators::infix(token) else {
+                break;
+            };
+            if operator.precedence < min_precedence {
+                break;
+            }
+            self.builder
+                .start_node_at(checkpoint, SyntaxKind::BinaryExpr);
+            self.bump();
+            let rhs_precedence = match operator.fixity {
+                Fixity::Infix(Assoc::Right) => operator.precedence,
+                _ => operator.precedence + 1,
+            };
+            self.parse_expr_at(rhs_precedence)?;
+            self.builder.finish_node();
+            if operator.fixity == Fixity::Infix(Assoc::None) {
+                if let Some(next) = self.peek().and_then(operators::infix) {
+                    if next.precedence == operator.precedence {
+                        return Err(self.error(format!(
+                            "{:?} can't be chained, use parentheses.",
+                            next.token
+                        )));
+                    }
+                }
+            }
         }
-        Ok(EqualExpr { lhs, rhs })
-    }
-
-    // More odd error handling
-    // RelExpr ::= <AddExpr> [ (LTHAN | GTHAN) <AddExpr> ]
-    fn parse_rel_expr(&mut self) -> Result<RelExpr, ParseError> {
-        let lhs = self.parse_add_expr()?;
-        let op = self
-            .cmp_next_token_many(vec![Token::LTHAN, Token::GTHAN], "relational operator")
-            .ok();
-        let rhs = match op {
-            Some(_) => Some(self.parse_add_expr()?),
-            None => None,
-        };
-        Ok(RelExpr { lhs, op, rhs })
+        Ok(())
     }
 
-    // AddExpr ::= <MultExpr> { (PLUS | MINUS) <MultExpr> }
-    fn parse_add_expr(&mut self) -> Result<AddExpr, ParseError> {
-        let lhs = self.parse_mult_expr()?;
-        let mut ops: Vec<Token> = vec![];
-        let mut rhs: Vec<MultExpr> = vec![];
-        while let Ok(op) =
-            self.cmp_next_token_many(vec![Token::PLUS, Token::MINUS], "addition operator")
+    // PrimaryExpr ::= IDENT | ICONST | FCONST | BCONST | SCONST | EOF | OPENPAREN <Expr> CLOSEPAREN
+    //               | (INT | FLOAT | BOOL | STRING) OPENPAREN <Expr> CLOSEPAREN
+    //               | IDENT OPENPAREN [ <Expr> { COMMA <Expr> } ] CLOSEPAREN
+    fn parse_primary_expr(&mut self) -> Result<(), ParseError> {
+        if matches!(self.peek(), Some(Token::IDENT(_)))
+            && self.peek_nth(1) == Some(&Token::OPENPAREN)
         {
-            ops.push(op);
-            let expr = self.parse_mult_expr()?;
-            rhs.push(expr);
+            self.start_node(SyntaxKind::CallExpr);
+            self.bump();
+            let open = self.open_paren()?;
+            if !self.at(&Token::CLOSEPAREN) {
+                self.parse_expr()?;
+                while self.eat(&Token::COMMA) {
+                    self.parse_expr()?;
+                }
+            }
+            self.close_paren(open)?;
+            self.builder.finish_node();
+            return Ok(());
         }
-        Ok(AddExpr { lhs, ops, rhs })
-    }
-
-    // MultExpr ::= <UnaryExpr> { (MULT | DIV | MOD) <UnaryExpr> }
-    fn parse_mult_expr(&mut self) -> Result<MultExpr, ParseError> {
-        let lhs = self.parse_unary_expr()?;
-        let mut ops: Vec<Token> = vec![];
-        let mut rhs: Vec<UnaryExpr> = vec![];
-        while let Ok(op) = self.cmp_next_token_many(
-            vec![Token::MULT, Token::DIV, Token::MOD],
-            "multiplication operator",
+        if matches!(
+            self.peek(),
+            Some(Token::INT | Token::FLOAT | Token::BOOL | Token::STRING)
         ) {
-            ops.push(op);
-            let expr = self.parse_unary_expr()?;
-            rhs.push(expr);
+            self.start_node(SyntaxKind::CastExpr);
+            self.bump();
+            let open = self.open_paren()?;
+            self.parse_expr()?;
+            self.close_paren(open)?;
+            self.builder.finish_node();
+            return Ok(());
         }
-        Ok(MultExpr { lhs, ops, rhs })
-    }
-
-    // UnaryExpr ::= [ (NOT | MINUS) ] <PrimaryExpr>
-    fn parse_unary_expr(&mut self) -> Result<UnaryExpr, ParseError> {
-        let op = self
-            .cmp_next_token_many(vec![Token::NOT, Token::MINUS], "unary operator")
-            .ok();
-        let expr = self.parse_primary_expr()?;
-        Ok(UnaryExpr { op, expr })
-    }
-
-    // The ( <OrExpr> ) is required to use parenthesis to control order of operations.
-    // PrimaryExpr ::= IDENT | ICONST | FCONST | BCONST | SCONST
-    fn parse_primary_expr(&mut self) -> Result<PrimaryExpr, ParseError> {
-        let constant = self.cmp_next_token_many(
-            vec![
-                Token::IDENT("".to_string()),
+        if self.at(&Token::OPENPAREN) {
+            self.start_node(SyntaxKind::ParenExpr);
+            let open = self.open_paren()?;
+            self.parse_expr()?;
+            self.close_paren(open)?;
+            self.builder.finish_node();
+            return Ok(());
+        }
+        self.start_node(SyntaxKind::PrimaryExpr);
+        self.cmp_next_token_many(
+            &[
+                Token::IDENT(String::new()),
                 Token::ICONST(0),
                 Token::FCONST(0.0),
                 Token::BCONST(false),
-                Token::SCONST("".to_string()),
+                Token::SCONST(String::new()),
+                Token::EOF,
             ],
             "literal",
         )?;
-        Ok(PrimaryExpr { constant })
+        self.builder.finish_node();
+        Ok(())
+    }
+}
+
+fn stmt_spans(stmt_list: &SyntaxNode, index: &LineIndex) -> Vec<Span> {
+    let mut spans: Vec<Span> = vec![];
+    let mut start: Option<usize> = None;
+    for child in stmt_list.children_with_tokens() {
+        match child {
+            SyntaxElement::Node(stmt) => start = Some(stmt.text_range().start),
+            SyntaxElement::Token(token) if token.token() == &Token::SEMICOL => {
+                let start = start.take().expect("statement before semicolon");
+                let end = token.text_range().end;
+                let (start_line, _) = index.line_col(start);
+                let (end_line, end_col) = index.line_col(end);
+                spans.push(Span {
+                    start,
+                    end,
+                    start_line,
+                    end_line,
+                    end_col,
+                });
+            }
+            SyntaxElement::Token(_) => {}
+        }
+    }
+    spans
+}
+
+// AST lowering. The tree passed in comes from a successful parse, so the children the
+// grammar requires are always there.
+
+pub fn lower_program(root: &SyntaxNode, index: &LineIndex) -> Program {
+    let stmt_list = root
+        .children()
+        .next()
+        .expect("program has a statement list");
+    let (stmts, spans) = lower_top_level(&stmt_list, index);
+    Program {
+        stmt_list: StmtList { stmts },
+        spans: Spans::new(spans),
+    }
+}
+
+// Lowers top level statements, with the lines in each counting from its first line.
+fn lower_top_level(stmt_list: &SyntaxNode, index: &LineIndex) -> (Vec<Stmt>, Vec<Span>) {
+    let spans = stmt_spans(stmt_list, index);
+    let stmts = stmt_list
+        .children()
+        .zip(&spans)
+        .map(|(node, span)| {
+            let mut stmt = lower_stmt(&node, index);
+            stmt.shift_lines(-(span.start_line as i32));
+            stmt
+        })
+        .collect();
+    (stmts, spans)
+}
+
+fn lower_stmt_list(node: &SyntaxNode, index: &LineIndex) -> StmtList {
+    StmtList {
+        stmts: node
+            .children()
+            .map(|stmt| lower_stmt(&stmt, index))
+            .collect(),
+    }
+}
+
+fn first_token(node: &SyntaxNode) -> SyntaxToken {
+    node.tokens().next().expect("node has a token")
+}
+
+fn lower_stmt(node: &SyntaxNode, index: &LineIndex) -> Stmt {
+    let (line, col) = index.line_col(node.text_range().start);
+    let mut children = node.children();
+    let expr = |children: &mut dyn Iterator<Item = SyntaxNode>| {
+        Box::new(lower_expr(&children.next().expect("expression"), index))
+    };
+    match node.kind() {
+        SyntaxKind::DeclStmt => {
+            let mut decl_stmt = DeclStmt {
+                line,
+                is_const: false,
+                type_specifier: Token::INT,
+                idents: vec![],
+                ident_positions: vec![],
+                initializer: None,
+            };
+            for token in node.tokens() {
+                match token.token() {
+                    Token::CONST => decl_stmt.is_const = true,
+                    Token::INT | Token::FLOAT | Token::BOOL | Token::STRING => {
+                        decl_stmt.type_specifier = token.token().clone()
+                    }
+                    Token::IDENT(_) => {
+                        decl_stmt.idents.push(token.token().clone());
+                        decl_stmt
+                            .ident_positions
+                            .push(index.line_col(token.text_range().start));
+                    }
+                    _ => {}
+                }
+            }
+            if decl_stmt.is_const {
+                decl_stmt.initializer = Some(expr(&mut children));
+            }
+            Stmt::Decl(decl_stmt)
+        }
+        SyntaxKind::AssignStmt => Stmt::Ctrl(CtrlStmt::Assign(AssignStmt {
+            line,
+            col,
+            ident: first_token(node).token().clone(),
+            op: node
+                .tokens()
+                .nth(1)
+                .expect("assignment operator")
+                .token()
+                .clone(),
+            expr: expr(&mut children),
+        })),
+        SyntaxKind::PrintStmt => Stmt::Ctrl(CtrlStmt::Print(PrintStmt {
+            line,
+            exprs: node
+                .children()
+                .map(|expr| lower_expr(&expr, index))
+                .collect(),
+        })),
+        SyntaxKind::ReadStmt => {
+            let mut read_stmt = ReadStmt {
+                line,
+                idents: vec![],
+                ident_positions: vec![],
+            };
+            for token in node.tokens() {
+                if let Token::IDENT(_) = token.token() {
+                    read_stmt.idents.push(token.token().clone());
+                    read_stmt
+                        .ident_positions
+                        .push(index.line_col(token.text_range().start));
+                }
+            }
+            Stmt::Ctrl(CtrlStmt::Read(read_stmt))
+        }
+        SyntaxKind::IfStmt => {
+            let condition = expr(&mut children);
+            let then_stmts = lower_stmt_list(&children.next().expect("then branch"), index);
+            let else_stmts = children.next().map(|stmts| lower_stmt_list(&stmts, index));
+            Stmt::Ctrl(CtrlStmt::If(IfStmt {
+                line,
+                condition,
+                then_stmts,
+                else_stmts,
+            }))
+        }
+        SyntaxKind::LoopStmt => {
+            let condition = expr(&mut children);
+            let stmts = lower_stmt_list(&children.next().expect("loop body"), index);
+            Stmt::Ctrl(CtrlStmt::Loop(LoopStmt {
+                line,
+                condition,
+                stmts,
+            }))
+        }
+        kind => unreachable!("{:?} is not a statement", kind),
+    }
+}
+
+fn lower_expr(node: &SyntaxNode, index: &LineIndex) -> Expr {
+    let op = || first_token(node).token().clone();
+    let operand = |i: usize| {
+        let operand = node.children().nth(i).expect("operand");
+        Box::new(lower_expr(&operand, index))
+    };
+    match node.kind() {
+        SyntaxKind::BinaryExpr => Expr::Binary(BinaryExpr {
+            op: op(),
+            lhs: operand(0),
+            rhs: operand(1),
+        }),
+        SyntaxKind::PrefixExpr => {
+            let (line, col) = index.line_col(node.text_range().start);
+            Expr::Prefix(PrefixExpr {
+                line,
+                col,
+                op: op(),
+                expr: operand(0),
+            })
+        }
+        SyntaxKind::PostfixExpr => Expr::Postfix(PostfixExpr {
+            op: op(),
+            expr: operand(0),
+        }),
+        SyntaxKind::ParenExpr => {
+            let (line, col) = index.line_col(node.text_range().start);
+            Expr::Paren(ParenExpr {
+                line,
+                col,
+                expr: operand(0),
+            })
+        }
+        SyntaxKind::CastExpr => {
+            let (line, col) = index.line_col(node.text_range().start);
+            Expr::Cast(CastExpr {
+                line,
+                col,
+                type_specifier: op(),
+                expr: operand(0),
+            })
+        }
+        SyntaxKind::CallExpr => {
+            let (line, col) = index.line_col(node.text_range().start);
+            Expr::Call(CallExpr {
+                line,
+                col,
+                name: op(),
+                args: node.children().map(|arg| lower_expr(&arg, index)).collect(),
+            })
+        }
+        SyntaxKind::PrimaryExpr => {
+            let token = first_token(node);
+            let (line, col) = index.line_col(token.text_range().start);
+            Expr::Primary(PrimaryExpr {
+                line,
+                col,
+                constant: token.token().clone(),
+            })
+        }
+        kind => unreachable!("{:?} is not an expression", kind),
     }
 }
 
 #[derive(Debug)]
 pub struct Program {
-    stmt_list: StmtList,
+    // Lines in the tree of a top level statement count from 0 at the line it starts on,
+    // see stmts.
+    pub stmt_list: StmtList,
+    pub spans: Spans,
+}
+impl Program {
+    pub(crate) fn new(stmts: Vec<Stmt>, spans: Vec<Span>) -> Self {
+        Self {
+            stmt_list: StmtList { stmts },
+            spans: Spans::new(spans),
+        }
+    }
+
+    // Every top level statement with the line it starts on, which the lines in its tree
+    // are relative to.
+    pub fn stmts(&self) -> impl Iterator<Item = (u32, &Stmt)> {
+        self.stmt_list
+            .stmts
+            .iter()
+            .enumerate()
+            .map(|(index, stmt)| (self.spans.get(index).start_line, stmt))
+    }
 }
 #[derive(Debug)]
 pub struct StmtList {
-    stmts: Vec<Stmt>,
+    pub stmts: Vec<Stmt>,
 }
 #[derive(Debug)]
 pub enum Stmt {
     Decl(DeclStmt),
     Ctrl(CtrlStmt),
 }
+impl Stmt {
+    pub fn line(&self) -> u32 {
+        match self {
+            Stmt::Decl(decl_stmt) => decl_stmt.line,
+            Stmt::Ctrl(ctrl_stmt) => ctrl_stmt.line(),
+        }
+    }
+}
 #[derive(Debug)]
 pub struct DeclStmt {
-    type_specifier: Token,
-    idents: Vec<Token>,
+    pub line: u32,
+    pub is_const: bool,
+    pub type_specifier: Token,
+    pub idents: Vec<Token>,
+    // Line and column of each ident.
+    pub ident_positions: Vec<(u32, u32)>,
+    pub initializer: Option<Box<Expr>>,
 }
 #[derive(Debug)]
 pub enum CtrlStmt {
     Assign(AssignStmt),
     Print(PrintStmt),
+    Read(ReadStmt),
     If(IfStmt),
     Loop(LoopStmt),
 }
+impl CtrlStmt {
+    pub fn line(&self) -> u32 {
+        match self {
+            CtrlStmt::Assign(assign_stmt) => assign_stmt.line,
+            CtrlStmt::Print(print_stmt) => print_stmt.line,
+            CtrlStmt::Read(read_stmt) => read_stmt.line,
+            CtrlStmt::If(if_stmt) => if_stmt.line,
+            CtrlStmt::Loop(loop_stmt) => loop_stmt.line,
+        }
+    }
+}
 #[derive(Debug)]
 pub struct AssignStmt {
-    ident: Token,
-    expr: OrExpr,
+    pub line: u32,
+    pub col: u32,
+    pub ident: Token,
+    // ASSOP, or a compound assignment like PLUSASSOP, see operators::compound_assignment.
+    pub op: Token,
+    pub expr: Box<Expr>,
 }
 #[derive(Debug)]
 pub struct PrintStmt {
-    exprs: Vec<OrExpr>,
+    pub line: u32,
+    pub exprs: Vec<Expr>,
+}
+#[derive(Debug)]
+pub struct ReadStmt {
+    pub line: u32,
+    pub idents: Vec<Token>,
+    // Line and column of each ident.
+    pub ident_positions: Vec<(u32, u32)>,
 }
 #[derive(Debug)]
 pub struct IfStmt {
-    condition: OrExpr,
-    then_stmts: StmtList,
-    else_stmts: Option<StmtList>,
+    pub line: u32,
+    pub condition: Box<Expr>,
+    pub then_stmts: StmtList,
+    pub else_stmts: Option<StmtList>,
 }
 #[derive(Debug)]
 pub struct LoopStmt {
-    condition: OrExpr,
-    stmts: StmtList,
+    pub line: u32,
+    pub condition: Box<Expr>,
+    pub stmts: StmtList,
 }
+// An expression is a tree of operators from operators::OPERATORS over primary
+// expressions. Parentheses are kept so the source can be printed back.
 #[derive(Debug)]
-pub struct OrExpr {
-    lhs: AndExpr,
-    rhs: Vec<AndExpr>,
+pub enum Expr {
+    Primary(PrimaryExpr),
+    Paren(ParenExpr),
+    Prefix(PrefixExpr),
+    Postfix(PostfixExpr),
+    Binary(BinaryExpr),
+    Cast(CastExpr),
+    Call(CallExpr),
 }
 #[derive(Debug)]
-pub struct AndExpr {
-    lhs: EqualExpr,
-    rhs: Vec<EqualExpr>,
+pub struct PrimaryExpr {
+    pub line: u32,
+    pub col: u32,
+    // A literal or identifier.
+    pub constant: Token,
 }
 #[derive(Debug)]
-pub struct EqualExpr {
-    lhs: RelExpr,
-    rhs: Option<RelExpr>,
+pub struct ParenExpr {
+    // Line and column of the (.
+    pub line: u32,
+    pub col: u32,
+    pub expr: Box<Expr>,
 }
 #[derive(Debug)]
-pub struct RelExpr {
-    lhs: AddExpr,
-    op: Option<Token>,
-    rhs: Option<AddExpr>,
+pub struct PrefixExpr {
+    // Line and column of the operator.
+    pub line: u32,
+    pub col: u32,
+    pub op: Token,
+    pub expr: Box<Expr>,
 }
 #[derive(Debug)]
-pub struct AddExpr {
-    lhs: MultExpr,
-    ops: Vec<Token>,
-    rhs: Vec<MultExpr>,
+pub struct PostfixExpr {
+    pub op: Token,
+    pub expr: Box<Expr>,
 }
 #[derive(Debug)]
-pub struct MultExpr {
-    lhs: UnaryExpr,
-    ops: Vec<Token>,
-    rhs: Vec<UnaryExpr>,
+pub struct CastExpr {
+    // Line and column of the type specifier.
+    pub line: u32,
+    pub col: u32,
+    pub type_specifier: Token,
+    pub expr: Box<Expr>,
 }
 #[derive(Debug)]
-pub struct UnaryExpr {
-    op: Option<Token>,
-    expr: PrimaryExpr,
+pub struct CallExpr {
+    // Line and column of the function name.
+    pub line: u32,
+    pub col: u32,
+    // An IDENT naming a builtin, see builtins.rs.
+    pub name: Token,
+    pub args: Vec<Expr>,
 }
 #[derive(Debug)]
-pub struct PrimaryExpr {
-    constant: Token,
+pub struct BinaryExpr {
+    pub op: Token,
+    pub lhs: Box<Expr>,
+    pub rhs: Box<Expr>,
+}
+
+// Identifier collection, used by the passes that track which variables an expression reads.
+// The primary expressions holding identifiers are pushed in evaluation order.
+
+impl Expr {
+    pub fn idents(&self) -> Vec<&PrimaryExpr> {
+        let mut idents: Vec<&PrimaryExpr> = vec![];
+        self.collect_idents(&mut idents);
+        idents
+    }
+
+    fn collect_idents<'a>(&'a self, idents: &mut Vec<&'a PrimaryExpr>) {
+        match self {
+            Expr::Primary(primary) => {
+                if let Token::IDENT(_) = primary.constant {
+                    idents.push(primary);
+                }
+            }
+            Expr::Paren(ParenExpr { expr, .. })
+            | Expr::Prefix(PrefixExpr { expr, .. })
+            | Expr::Postfix(PostfixExpr { expr, .. })
+            | Expr::Cast(CastExpr { expr, .. }) => expr.collect_idents(idents),
+            Expr::Binary(binary) => {
+                binary.lhs.collect_idents(idents);
+                binary.rhs.collect_idents(idents);
+            }
+            Expr::Call(call) => {
+                for arg in &call.args {
+                    arg.collect_idents(idents);
+                }
+            }
+        }
+    }
+}
+
+// Line shifting, used to make the lines in a top level statement relative to its first
+// line.
+
+fn shift(line: &mut u32, delta: i32) {
+    *line = line.wrapping_add_signed(delta);
+}
+
+impl Stmt {
+    fn shift_lines(&mut self, delta: i32) {
+        match self {
+            Stmt::Decl(decl_stmt) => {
+                shift(&mut decl_stmt.line, delta);
+                for (line, _) in &mut decl_stmt.ident_positions {
+                    shift(line, delta);
+                }
+                if let Some(initializer) = &mut decl_stmt.initializer {
+                    initializer.shift_lines(delta);
+                }
+            }
+            Stmt::Ctrl(CtrlStmt::Assign(assign_stmt)) => {
+                shift(&mut assign_stmt.line, delta);
+                assign_stmt.expr.shift_lines(delta);
+            }
+            Stmt::Ctrl(CtrlStmt::Print(print_stmt)) => {
+                shift(&mut print_stmt.line, delta);
+                for expr in &mut print_stmt.exprs {
+                    expr.shift_lines(delta);
+                }
+            }
+            Stmt::Ctrl(CtrlStmt::Read(read_stmt)) => {
+                shift(&mut read_stmt.line, delta);
+                for (line, _) in &mut read_stmt.ident_positions {
+                    shift(line, delta);
+                }
+            }
+            Stmt::Ctrl(CtrlStmt::If(if_stmt)) => {
+                shift(&mut if_stmt.line, delta);
+                if_stmt.condition.shift_lines(delta);
+                if_stmt.then_stmts.shift_lines(delta);
+                if let Some(else_stmts) = &mut if_stmt.else_stmts {
+                    else_stmts.shift_lines(delta);
+                }
+            }
+            Stmt::Ctrl(CtrlStmt::Loop(loop_stmt)) => {
+                shift(&mut loop_stmt.line, delta);
+                loop_stmt.condition.shift_lines(delta);
+                loop_stmt.stmts.shift_lines(delta);
+            }
+        }
+    }
+}
+
+impl StmtList {
+    fn shift_lines(&mut self, delta: i32) {
+        for stmt in &mut self.stmts {
+            stmt.shift_lines(delta);
+        }
+    }
+}
+
+impl Expr {
+    fn shift_lines(&mut self, delta: i32) {
+        match self {
+            Expr::Primary(primary) => shift(&mut primary.line, delta),
+            Expr::Paren(paren) => {
+                shift(&mut paren.line, delta);
+                paren.expr.shift_lines(delta);
+            }
+            Expr::Prefix(prefix) => {
+                shift(&mut prefix.line, delta);
+                prefix.expr.shift_lines(delta);
+            }
+            Expr::Postfix(postfix) => postfix.expr.shift_lines(delta),
+            Expr::Cast(cast) => {
+                shift(&mut cast.line, delta);
+                cast.expr.shift_lines(delta);
+            }
+            Expr::Binary(binary) => {
+                binary.lhs.shift_lines(delta);
+                binary.rhs.shift_lines(delta);
+            }
+            Expr::Call(call) => {
+                shift(&mut call.line, delta);
+                for arg in &mut call.args {
+                    arg.shift_lines(delta);
+                }
+            }
+        }
+    }
 }
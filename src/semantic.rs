@@ -0,0 +1,355 @@
+use crate::{
+    dataflow::DefiniteAssignment,
+    eval::Evaluator,
+    format::token_text,
+    globals::Globals,
+    interpreter::{Budget, Limit, Limits},
+    native::Natives,
+    operators,
+    parser::{AssignStmt, CallExpr, CtrlStmt, DeclStmt, Expr, Program, Stmt, StmtList},
+    types,
+    value::Value,
+    Token,
+};
+use std::{collections::HashMap, error::Error, fmt};
+
+#[derive(Debug)]
+pub struct SemanticError {
+    pub line: u32,
+    msg: String,
+    // The limit a constant initializer ran into, see Analyzer::with_limits.
+    pub limit: Option<Limit>,
+}
+
+impl Error for SemanticError {}
+
+impl SemanticError {
+    pub fn new(line: u32, msg: String) -> Self {
+        SemanticError {
+            line,
+            msg,
+            limit: None,
+        }
+    }
+}
+
+impl fmt::Display for SemanticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+#[derive(Debug)]
+pub struct Symbol {
+    pub type_specifier: Token,
+    pub is_const: bool,
+    // The folded initializer of a constant, None for variables and for constants whose
+    // initializer couldn't be folded.
+    pub constant: Option<Value>,
+}
+
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<String, Symbol>,
+    natives: Natives,
+    globals: Globals,
+}
+
+impl SymbolTable {
+    // The functions registered by the host, see Analyzer::with_natives.
+    pub fn natives(&self) -> &Natives {
+        &self.natives
+    }
+
+    // The variables declared by the host, see Analyzer::with_globals.
+    pub fn globals(&self) -> &Globals {
+        &self.globals
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.get(name)
+    }
+
+    pub fn is_const(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|symbol| symbol.is_const)
+    }
+
+    pub fn constant(&self, name: &str) -> Option<&Value> {
+        self.get(name).and_then(|symbol| symbol.constant.as_ref())
+    }
+}
+
+// Returns the name held by an IDENT token.
+pub fn ident_name(token: &Token) -> &str {
+    match token {
+        Token::IDENT(name) => name,
+        _ => "",
+    }
+}
+
+// Walks the parse tree building the symbol table. Constant initializers are
+// evaluated here so the interpreter only ever sees their folded values.
+#[derive(Default)]
+pub struct Analyzer {
+    symbols: SymbolTable,
+    errors: Vec<SemanticError>,
+    // The line the top level statement being analyzed starts on, see Program::stmts.
+    line: u32,
+    // What evaluating constant initializers may use, and a limit it ran into.
+    budget: Budget,
+    exceeded: Option<Limit>,
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Lets the program call the native functions registered by an embedding host.
+    pub fn with_natives(mut self, natives: &Natives) -> Self {
+        self.symbols.natives = natives.clone();
+        self
+    }
+
+    // Holds the evaluation of constant initializers to the limits the program runs with.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.budget = Budget::new(limits);
+        self
+    }
+
+    // Declares the host's variables for the program, assigned from the start.
+    pub fn with_globals(mut self, globals: &Globals) -> Self {
+        for (name, value) in globals.iter() {
+            self.symbols.symbols.insert(
+                name.clone(),
+                Symbol {
+                    type_specifier: value.type_specifier(),
+                    is_const: false,
+                    constant: None,
+                },
+            );
+        }
+        self.symbols.globals = globals.clone();
+        self
+    }
+
+    // Collects every semantic error in the program rather than stopping at the first.
+    pub fn analyze(mut self, program: &Program) -> Result<SymbolTable, Vec<SemanticError>> {
+        for (line, stmt) in program.stmts() {
+            self.line = line;
+            self.analyze_stmt(stmt);
+        }
+        let unassigned = DefiniteAssignment::new(&self.symbols).check(program);
+        self.errors.extend(unassigned);
+        if self.errors.is_empty() {
+            Ok(self.symbols)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn error(&mut self, line: u32, msg: String) {
+        let line = self.line + line;
+        self.errors.push(SemanticError::new(line, msg));
+    }
+
+    fn analyze_stmt_list(&mut self, stmt_list: &StmtList) {
+        for stmt in &stmt_list.stmts {
+            self.analyze_stmt(stmt);
+        }
+    }
+
+    fn analyze_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Decl(decl_stmt) => self.analyze_decl_stmt(decl_stmt),
+            Stmt::Ctrl(ctrl_stmt) => self.analyze_ctrl_stmt(ctrl_stmt),
+        }
+    }
+
+    fn analyze_decl_stmt(&mut self, decl_stmt: &DeclStmt) {
+        for (ident, (line, _)) in decl_stmt.idents.iter().zip(&decl_stmt.ident_positions) {
+            let name = ident_name(ident);
+            if self.symbols.globals.get(name).is_some() {
+                self.error(
+                    *line,
+                    format!("'{}' is already declared by the host.", name),
+                );
+                continue;
+            }
+            if self.symbols.get(name).is_some() {
+                self.error(*line, format!("Redeclaration of '{}'.", name));
+                continue;
+            }
+            let constant = match &decl_stmt.initializer {
+                Some(expr) if decl_stmt.is_const => match self.eval_expr(expr) {
+                    Ok(value) if value.has_type(&decl_stmt.type_specifier) => Some(value),
+                    Ok(value) => {
+                        self.error(
+                            *line,
+                            format!(
+                                "Constant '{}' declared as {:?} but initialized with {}.",
+                                name,
+                                decl_stmt.type_specifier,
+                                value.type_name()
+                            ),
+                        );
+                        None
+                    }
+                    Err(msg) => {
+                        self.error(
+                            *line,
+                            format!("Invalid initializer for constant '{}': {}", name, msg),
+                        );
+                        if let Some(error) = self.errors.last_mut() {
+                            error.limit = self.exceeded.take();
+                        }
+                        None
+                    }
+                },
+                _ => None,
+            };
+            self.symbols.symbols.insert(
+                name.to_string(),
+                Symbol {
+                    type_specifier: decl_stmt.type_specifier.clone(),
+                    is_const: decl_stmt.is_const,
+                    constant,
+                },
+            );
+        }
+    }
+
+    fn analyze_ctrl_stmt(&mut self, ctrl_stmt: &CtrlStmt) {
+        match ctrl_stmt {
+            CtrlStmt::Assign(assign_stmt) => self.analyze_assign_stmt(assign_stmt),
+            CtrlStmt::Print(print_stmt) => {
+                for expr in &print_stmt.exprs {
+                    self.check_expr(expr);
+                }
+            }
+            CtrlStmt::Read(read_stmt) => {
+                for ident in &read_stmt.idents {
+                    let name = ident_name(ident);
+                    if self.symbols.is_const(name) {
+                        self.error(
+                            read_stmt.line,
+                            format!("Cannot read into constant '{}'.", name),
+                        );
+                    }
+                }
+            }
+            CtrlStmt::If(if_stmt) => {
+                self.check_condition(&if_stmt.condition);
+                self.analyze_stmt_list(&if_stmt.then_stmts);
+                if let Some(else_stmts) = &if_stmt.else_stmts {
+                    self.analyze_stmt_list(else_stmts);
+                }
+            }
+            CtrlStmt::Loop(loop_stmt) => {
+                self.check_condition(&loop_stmt.condition);
+                self.analyze_stmt_list(&loop_stmt.stmts);
+            }
+        }
+    }
+
+    fn analyze_assign_stmt(&mut self, assign_stmt: &AssignStmt) {
+        let name = ident_name(&assign_stmt.ident);
+        if self.symbols.is_const(name) {
+            self.error(
+                assign_stmt.line,
+                format!("Cannot assign to constant '{}'.", name),
+            );
+        }
+        let rhs_type = self.check_expr(&assign_stmt.expr);
+        let Some(declared) = self
+            .symbols
+            .get(name)
+            .map(|symbol| symbol.type_specifier.clone())
+        else {
+            return;
+        };
+        // `x += e` is checked like `x = x + e`.
+        let value_type = match (operators::compound_assignment(&assign_stmt.op), rhs_type) {
+            (Some(op), Some(rhs_type)) => {
+                let (lhs_type, rhs_type) = types::promote(op, &declared, &rhs_type);
+                match types::binary_type(op, &lhs_type, &rhs_type) {
+                    Ok(value_type) => Some(value_type),
+                    Err(msg) => {
+                        self.error(assign_stmt.line, msg);
+                        None
+                    }
+                }
+            }
+            (_, rhs_type) => rhs_type,
+        };
+        if let Some(value_type) = value_type {
+            if value_type != declared {
+                self.error(
+                    assign_stmt.line,
+                    format!(
+                        "Cannot assign {} to '{}' declared as {:?}.",
+                        token_text(&value_type),
+                        name,
+                        declared
+                    ),
+                );
+            }
+        }
+    }
+
+    // Checks the types in an expression, see types.rs, returning its type when known.
+    // Constant initializers aren't checked since evaluating them already reports any
+    // illegal operand.
+    fn check_expr(&mut self, expr: &Expr) -> Option<Token> {
+        let (errors, base) = (&mut self.errors, self.line);
+        expr.check_type(&self.symbols, &mut |line, msg| {
+            errors.push(SemanticError::new(base + line, msg))
+        })
+    }
+
+    fn check_condition(&mut self, condition: &Expr) {
+        match self.check_expr(condition) {
+            None | Some(Token::BOOL) => {}
+            Some(type_specifier) => self.error(
+                condition.position().0,
+                format!(
+                    "Condition must be bool, found {}.",
+                    token_text(&type_specifier)
+                ),
+            ),
+        }
+    }
+}
+
+// Constant expression evaluation. Only literals and previously declared constants
+// may appear, the error strings are wrapped by analyze_decl_stmt.
+impl Evaluator for Analyzer {
+    fn step(&mut self) -> Result<(), String> {
+        self.budget.step().map_err(|limit| {
+            self.exceeded = Some(limit);
+            limit.to_string()
+        })
+    }
+
+    fn check_string_length(&mut self, length: usize) -> Result<(), String> {
+        self.budget.check_string_length(length).map_err(|limit| {
+            self.exceeded = Some(limit);
+            limit.to_string()
+        })
+    }
+
+    fn call_native(&mut self, call: &CallExpr, _: &[Value]) -> Option<Result<Value, String>> {
+        let name = ident_name(&call.name);
+        self.symbols
+            .natives
+            .get(name)
+            .map(|_| Err(format!("'{}' is not a constant.", name)))
+    }
+
+    fn lookup(&mut self, name: &str) -> Result<Value, String> {
+        match self.symbols.constant(name) {
+            Some(value) => Ok(value.clone()),
+            None => Err(format!("'{}' is not a constant.", name)),
+        }
+    }
+}
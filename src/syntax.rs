@@ -0,0 +1,333 @@
+use std::{fmt, ops::Range, rc::Rc};
+
+use crate::Token;
+
+// The concrete syntax tree. It keeps every character of the source, trivia included, so
+// printing the root gives back the exact input. The AST is lowered from it, see
+// parser::lower_program.
+//
+// The tree comes in two layers. Green nodes are immutable, know only their kind, length
+// and children, and can be shared between trees. Red nodes wrap a green node with its
+// parent and absolute offset and are created on the fly while walking down the tree.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Program,
+    StmtList,
+    DeclStmt,
+    AssignStmt,
+    PrintStmt,
+    ReadStmt,
+    IfStmt,
+    LoopStmt,
+    // One node per operator, holding the operator token and its operands.
+    BinaryExpr,
+    PrefixExpr,
+    PostfixExpr,
+    ParenExpr,
+    CastExpr,
+    CallExpr,
+    PrimaryExpr,
+}
+
+#[derive(Debug)]
+pub struct GreenToken {
+    pub token: Token,
+    pub text: String,
+}
+
+impl GreenToken {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.token, Token::WHITESPACE | Token::COMMENT)
+    }
+}
+
+#[derive(Debug)]
+pub struct GreenNode {
+    pub kind: SyntaxKind,
+    // Length of the node's text in bytes.
+    pub len: usize,
+    pub children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+// Marks a position in the builder that a node can later be started at, for nodes like a
+// binary expression whose kind is only known after their first child was parsed.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint(usize);
+
+// Builds a green tree bottom up, from start_node/token/finish_node calls made in source
+// order.
+#[derive(Default)]
+pub struct GreenNodeBuilder {
+    parents: Vec<(SyntaxKind, usize)>,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNodeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.children.len())
+    }
+
+    // Starts a node that also holds everything added since the checkpoint.
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+        self.parents.push((kind, checkpoint.0));
+    }
+
+    pub fn token(&mut self, token: Token, text: &str) {
+        self.children.push(GreenElement::Token(Rc::new(GreenToken {
+            token,
+            text: text.to_string(),
+        })));
+    }
+
+    pub fn finish_node(&mut self) {
+        let (kind, first) = self.parents.pop().expect("finish_node without start_node");
+        let children = self.children.split_off(first);
+        let len = children.iter().map(GreenElement::len).sum();
+        self.children.push(GreenElement::Node(Rc::new(GreenNode {
+            kind,
+            len,
+            children,
+        })));
+    }
+
+    // The root, once every started node has been finished.
+    pub fn finish(mut self) -> Rc<GreenNode> {
+        match self.children.pop() {
+            Some(GreenElement::Node(root)) if self.parents.is_empty() => root,
+            _ => panic!("finish called with unfinished nodes"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    offset: usize,
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    offset: usize,
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            parent: None,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.len
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    pub fn children_with_tokens(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        let mut offset = self.0.offset;
+        self.0.green.children.iter().map(move |child| {
+            let start = offset;
+            offset += child.len();
+            match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    parent: Some(self.clone()),
+                    offset: start,
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    parent: self.clone(),
+                    offset: start,
+                }),
+            }
+        })
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children_with_tokens().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    // The node's own tokens, trivia left out.
+    pub fn tokens(&self) -> impl Iterator<Item = SyntaxToken> + '_ {
+        self.children_with_tokens().filter_map(|child| match child {
+            SyntaxElement::Token(token) if !token.is_trivia() => Some(token),
+            _ => None,
+        })
+    }
+
+    // The innermost token covering `offset`, trivia included.
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) if node.text_range().contains(&offset) => {
+                    return node.token_at_offset(offset)
+                }
+                SyntaxElement::Token(token) if token.text_range().contains(&offset) => {
+                    return Some(token)
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn write_tree(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        let range = self.text_range();
+        writeln!(
+            f,
+            "{}{:?}@{}..{}",
+            "  ".repeat(depth),
+            self.kind(),
+            range.start,
+            range.end
+        )?;
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => node.write_tree(f, depth + 1)?,
+                SyntaxElement::Token(token) => {
+                    writeln!(f, "{}{:?}", "  ".repeat(depth + 1), token)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Prints the node as an indented tree, one node or token per line.
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_tree(f, 0)
+    }
+}
+
+// Prints the source text the node was parsed from.
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn write_green(node: &GreenNode, f: &mut fmt::Formatter) -> fmt::Result {
+            for child in &node.children {
+                match child {
+                    GreenElement::Node(node) => write_green(node, f)?,
+                    GreenElement::Token(token) => f.write_str(&token.text)?,
+                }
+            }
+            Ok(())
+        }
+        write_green(&self.0.green, f)
+    }
+}
+
+impl SyntaxToken {
+    pub fn token(&self) -> &Token {
+        &self.green.token
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        self.green.is_trivia()
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let range = self.text_range();
+        write!(
+            f,
+            "{:?}@{}..{} {:?}",
+            self.green.token, range.start, range.end, self.green.text
+        )
+    }
+}
+
+// Maps byte offsets in a source to the 1 based lines and 0 based char columns the rest of
+// the crate reports positions in.
+pub struct LineIndex<'a> {
+    // Byte offset of the start of every line.
+    line_starts: Vec<usize>,
+    text: &'a str,
+    first_line: u32,
+    first_col: u32,
+}
+
+impl<'a> LineIndex<'a> {
+    // Indexes text that starts at the given line and column of its source.
+    pub fn new(text: &'a str, first_line: u32, first_col: u32) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        Self {
+            line_starts,
+            text,
+            first_line,
+            first_col,
+        }
+    }
+
+    pub fn line_col(&self, offset: usize) -> (u32, u32) {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let col = self.text[self.line_starts[line]..offset].chars().count() as u32;
+        match line {
+            0 => (self.first_line, self.first_col + col),
+            _ => (self.first_line + line as u32, col),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    builtins,
+    format::token_text,
+    operators,
+    parser::{CastExpr, Expr, ParenExpr, PostfixExpr, PrefixExpr},
+    semantic::{ident_name, SymbolTable},
+    Token,
+};
+
+// Static typing. The analyzer checks every expression with these rules before the
+// program runs, and the optimizer and the backends use them to know the type of an
+// operand. They mirror value.rs, so an operation is rejected here exactly when the
+// interpreter would reject it at run time. Types are type specifier tokens (INT, FLOAT,
+// BOOL or STRING).
+
+// The types of a binary operation's operands after an int mixed with a float is widened,
+// see value::binary_op.
+pub(crate) fn promote(op: &Token, lhs: &Token, rhs: &Token) -> (Token, Token) {
+    if operators::is_int_only(op) {
+        return (lhs.clone(), rhs.clone());
+    }
+    match (lhs, rhs) {
+        (Token::INT, Token::FLOAT) => (Token::FLOAT, Token::FLOAT),
+        (Token::FLOAT, Token::INT) if op != &Token::POW => (Token::FLOAT, Token::FLOAT),
+        _ => (lhs.clone(), rhs.clone()),
+    }
+}
+
+// The type of a binary operation on operands of the given promoted types, or the error
+// value::binary_op would report.
+pub(crate) fn binary_type(op: &Token, lhs: &Token, rhs: &Token) -> Result<Token, String> {
+    let result = match (op, lhs, rhs) {
+        (Token::ANDOP | Token::OROP, Token::BOOL, Token::BOOL) => Token::BOOL,
+        (Token::ANDOP | Token::OROP, Token::BOOL, operand)
+        | (Token::ANDOP | Token::OROP, operand, _) => {
+            return Err(format!(
+                "Illegal operand for {:?}: {}.",
+                op,
+                token_text(operand)
+            ))
+        }
+        (Token::EQUALOP, _, _) if lhs == rhs => Token::BOOL,
+        (Token::LTHAN | Token::GTHAN, Token::INT | Token::FLOAT | Token::STRING, _)
+            if lhs == rhs =>
+        {
+            Token::BOOL
+        }
+        (Token::PLUS, Token::STRING, Token::STRING) => Token::STRING,
+        (
+            Token::PLUS
+            | Token::MINUS
+            | Token::MULT
+            | Token::DIV
+            | Token::MOD
+            | Token::IDIV
+            | Token::IMOD
+            | Token::POW,
+            Token::INT | Token::FLOAT,
+            _,
+        ) if lhs == rhs => lhs.clone(),
+        (Token::POW, Token::FLOAT, Token::INT) => Token::FLOAT,
+        (_, Token::INT, Token::INT) if operators::is_int_only(op) => Token::INT,
+        _ => {
+            return Err(format!(
+                "Illegal operands for {:?}: {} and {}.",
+                op,
+                token_text(lhs),
+                token_text(rhs)
+            ))
+        }
+    };
+    Ok(result)
+}
+
+// The type of a prefix operation, see value::unary_op.
+pub(crate) fn unary_type(op: &Token, operand: &Token) -> Result<Token, String> {
+    match (op, operand) {
+        (Token::NOT, Token::BOOL)
+        | (Token::PLUS | Token::MINUS, Token::INT | Token::FLOAT)
+        | (Token::BITNOT, Token::INT) => Ok(operand.clone()),
+        _ => Err(format!(
+            "Illegal operand for {:?}: {}.",
+            op,
+            token_text(operand)
+        )),
+    }
+}
+
+impl Expr {
+    // The type this evaluates to, None if it reads an undeclared identifier or has an
+    // operation with illegal operands.
+    pub(crate) fn static_type(&self, symbols: &SymbolTable) -> Option<Token> {
+        self.check_type(symbols, &mut |_, _| {})
+    }
+
+    // Like static_type, reporting each operation with illegal operands and each call that
+    // doesn't fit the function's signature with its line. An operand whose type isn't
+    // known has already been reported, or is an undeclared identifier left to the
+    // linter, so the operations around it aren't.
+    pub(crate) fn check_type(
+        &self,
+        symbols: &SymbolTable,
+        error: &mut dyn FnMut(u32, String),
+    ) -> Option<Token> {
+        let line = self.position().0;
+        match self {
+            Expr::Primary(primary) => match &primary.constant {
+                Token::ICONST(_) => Some(Token::INT),
+                Token::FCONST(_) => Some(Token::FLOAT),
+                Token::BCONST(_) => Some(Token::BOOL),
+                Token::SCONST(_) => Some(Token::STRING),
+                Token::EOF => Some(Token::BOOL),
+                ident => symbols
+                    .get(ident_name(ident))
+                    .map(|symbol| symbol.type_specifier.clone()),
+            },
+            Expr::Paren(ParenExpr { expr, .. }) => expr.check_type(symbols, error),
+            Expr::Prefix(PrefixExpr { op, expr, .. }) | Expr::Postfix(PostfixExpr { op, expr }) => {
+                let operand = expr.check_type(symbols, error)?;
+                unary_type(op, &operand)
+                    .map_err(|msg| error(line, msg))
+                    .ok()
+            }
+            // Any type converts to any other, a value that doesn't is a runtime error.
+            Expr::Cast(CastExpr {
+                type_specifier,
+                expr,
+                ..
+            }) => {
+                expr.check_type(symbols, error);
+                Some(type_specifier.clone())
+            }
+            Expr::Call(call) => {
+                let arg_types: Vec<Option<Token>> = call
+                    .args
+                    .iter()
+                    .map(|arg| arg.check_type(symbols, error))
+                    .collect();
+                let name = ident_name(&call.name);
+                let returns = match symbols.natives().get(name) {
+                    Some(native) => native
+                        .check_args(&arg_types)
+                        .map(|_| Some(native.returns.clone())),
+                    None => builtins::resolve(name, &arg_types)
+                        .map(|builtin| builtin.map(|builtin| builtin.returns.clone())),
+                };
+                returns.map_err(|msg| error(call.line, msg)).ok()?
+            }
+            Expr::Binary(binary) => {
+                let lhs = binary.lhs.check_type(symbols, error);
+                let rhs = binary.rhs.check_type(symbols, error);
+                let result = match &binary.op {
+                    // Each operand is checked on its own, like the interpreter does when it
+                    // gets to it.
+                    Token::ANDOP | Token::OROP => {
+                        let lhs = lhs.map(|lhs| binary_type(&binary.op, &lhs, &Token::BOOL));
+                        let rhs = rhs.map(|rhs| binary_type(&binary.op, &Token::BOOL, &rhs));
+                        match (lhs, rhs) {
+                            (Some(Err(msg)), _) | (_, Some(Err(msg))) => Err(msg),
+                            (Some(Ok(_)), Some(Ok(_))) => Ok(Token::BOOL),
+                            _ => return None,
+                        }
+                    }
+                    op => {
+                        let (lhs, rhs) = promote(op, &lhs?, &rhs?);
+                        binary_type(op, &lhs, &rhs)
+                    }
+                };
+                result.map_err(|msg| error(line, msg)).ok()
+            }
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::fmt;
+
+use crate::{format::token_text, types, Token};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum Value {
+    Int(i32),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl Value {
+    // Builds a value from a literal token, None for anything that isn't a constant.
+    pub fn from_literal(token: &Token) -> Option<Value> {
+        match token {
+            Token::ICONST(i) => Some(Value::Int(*i)),
+            Token::FCONST(f) => Some(Value::Float(*f)),
+            Token::BCONST(b) => Some(Value::Bool(*b)),
+            Token::SCONST(s) => Some(Value::Str(s.clone())),
+            _ => None,
+        }
+    }
+
+    // The inverse of from_literal, used when a folded value is put back into the tree.
+    pub fn to_literal(&self) -> Token {
+        match self {
+            Value::Int(i) => Token::ICONST(*i),
+            Value::Float(f) => Token::FCONST(*f),
+            Value::Bool(b) => Token::BCONST(*b),
+            Value::Str(s) => Token::SCONST(s.clone()),
+        }
+    }
+
+    // Checks the value against a type specifier token (INT, FLOAT, BOOL or STRING).
+    pub fn has_type(&self, type_specifier: &Token) -> bool {
+        matches!(
+            (self, type_specifier),
+            (Value::Int(_), Token::INT)
+                | (Value::Float(_), Token::FLOAT)
+                | (Value::Bool(_), Token::BOOL)
+                | (Value::Str(_), Token::STRING)
+        )
+    }
+
+    // The type specifier token of the value's type, the inverse of has_type.
+    pub fn type_specifier(&self) -> Token {
+        match self {
+            Value::Int(_) => Token::INT,
+            Value::Float(_) => Token::FLOAT,
+            Value::Bool(_) => Token::BOOL,
+            Value::Str(_) => Token::STRING,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "string",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Str(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+// Applies a binary operator token to two values. Operands must have the same type once an
+// int mixed with a float has been promoted to float. The error string is wrapped by the
+// caller into a semantic or runtime error.
+pub fn binary_op(op: &Token, lhs: Value, rhs: Value) -> Result<Value, String> {
+    let (lhs, rhs) = promote(op, lhs, rhs);
+    let illegal = |lhs: &Value, rhs: &Value| {
+        Err(format!(
+            "Illegal operands for {:?}: {} and {}.",
+            op,
+            lhs.type_name(),
+            rhs.type_name()
+        ))
+    };
+    let overflow = || Err(format!("Integer overflow in {:?}.", op));
+    match (op, &lhs, &rhs) {
+        (Token::PLUS, Value::Int(a), Value::Int(b)) => a
+            .checked_add(*b)
+            .map_or_else(overflow, |v| Ok(Value::Int(v))),
+        (Token::MINUS, Value::Int(a), Value::Int(b)) => a
+            .checked_sub(*b)
+            .map_or_else(overflow, |v| Ok(Value::Int(v))),
+        (Token::MULT, Value::Int(a), Value::Int(b)) => a
+            .checked_mul(*b)
+            .map_or_else(overflow, |v| Ok(Value::Int(v))),
+        (Token::DIV | Token::MOD | Token::IDIV | Token::IMOD, Value::Int(_), Value::Int(0)) => {
+            Err("Division by zero.".to_string())
+        }
+        (Token::DIV, Value::Int(a), Value::Int(b)) => a
+            .checked_div(*b)
+            .map_or_else(overflow, |v| Ok(Value::Int(v))),
+        (Token::MOD, Value::Int(a), Value::Int(b)) => a
+            .checked_rem(*b)
+            .map_or_else(overflow, |v| Ok(Value::Int(v))),
+        // Rounds toward negative infinity where DIV truncates, -7 div 2 is -4.
+        (Token::IDIV, Value::Int(a), Value::Int(b)) => match a.checked_div(*b) {
+            Some(q) if a % b != 0 && (*a < 0) != (*b < 0) => Ok(Value::Int(q - 1)),
+            Some(q) => Ok(Value::Int(q)),
+            None => overflow(),
+        },
+        // Takes the sign of the divisor where MOD takes the dividend's, -7 mod 2 is 1, so
+        // that a div b * b + a mod b is a.
+        (Token::IMOD, Value::Int(a), Value::Int(b)) => match a.checked_rem(*b) {
+            Some(r) if r != 0 && (r < 0) != (*b < 0) => Ok(Value::Int(r + b)),
+            Some(r) => Ok(Value::Int(r)),
+            None => overflow(),
+        },
+        // An int result can't hold a fraction, so a negative exponent needs a float base.
+        (Token::POW, Value::Int(_), Value::Int(b)) if *b < 0 => {
+            Err("Negative exponent in integer POW, use a float base.".to_string())
+        }
+        (Token::POW, Value::Int(a), Value::Int(b)) => a
+            .checked_pow(*b as u32)
+            .map_or_else(overflow, |v| Ok(Value::Int(v))),
+        (Token::BITAND, Value::Int(a), Value::Int(b)) => Ok(Value::Int(a & b)),
+        (Token::BITOR, Value::Int(a), Value::Int(b)) => Ok(Value::Int(a | b)),
+        (Token::BITXOR, Value::Int(a), Value::Int(b)) => Ok(Value::Int(a ^ b)),
+        // Bits shifted out are dropped and >> keeps the sign. Shifting by a negative amount
+        // or by the width of an int or more is an error.
+        (Token::SHL | Token::SHR, Value::Int(a), Value::Int(b)) => {
+            let shifted = match op {
+                Token::SHL => u32::try_from(*b).ok().and_then(|b| a.checked_shl(b)),
+                _ => u32::try_from(*b).ok().and_then(|b| a.checked_shr(b)),
+            };
+            shifted
+                .map(Value::Int)
+                .ok_or_else(|| format!("Shift amount {} out of range in {:?}.", b, op))
+        }
+
+        (Token::PLUS, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
+        (Token::MINUS, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a - b)),
+        (Token::MULT, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a * b)),
+        (Token::DIV, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a / b)),
+        (Token::MOD, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a % b)),
+        (Token::IDIV, Value::Float(a), Value::Float(b)) => Ok(Value::Float((a / b).floor())),
+        (Token::IMOD, Value::Float(a), Value::Float(b)) => Ok(Value::Float(match a % b {
+            r if r != 0.0 && (r < 0.0) != (*b < 0.0) => r + b,
+            r => r,
+        })),
+        (Token::POW, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a.powf(*b))),
+        (Token::POW, Value::Float(a), Value::Int(b)) => Ok(Value::Float(a.powi(*b))),
+
+        (Token::PLUS, Value::Str(a), Value::Str(b)) => Ok(Value::Str(format!("{}{}", a, b))),
+
+        (Token::LTHAN, Value::Int(a), Value::Int(b)) => Ok(Value::Bool(a < b)),
+        (Token::GTHAN, Value::Int(a), Value::Int(b)) => Ok(Value::Bool(a > b)),
+        (Token::LTHAN, Value::Float(a), Value::Float(b)) => Ok(Value::Bool(a < b)),
+        (Token::GTHAN, Value::Float(a), Value::Float(b)) => Ok(Value::Bool(a > b)),
+        // Strings compare lexicographically by character.
+        (Token::LTHAN, Value::Str(a), Value::Str(b)) => Ok(Value::Bool(a < b)),
+        (Token::GTHAN, Value::Str(a), Value::Str(b)) => Ok(Value::Bool(a > b)),
+
+        (Token::EQUALOP, _, _) if std::mem::discriminant(&lhs) == std::mem::discriminant(&rhs) => {
+            Ok(Value::Bool(lhs == rhs))
+        }
+
+        _ => illegal(&lhs, &rhs),
+    }
+}
+
+// Widens the int operand of an int and float pair to float, so `1 + 0.5` is `1.5` and
+// `1 == 1.0` holds. The int only operators don't promote, and neither does the int
+// exponent of a float power, which stays exact.
+fn promote(op: &Token, lhs: Value, rhs: Value) -> (Value, Value) {
+    let (lhs_type, rhs_type) = types::promote(op, &lhs.type_specifier(), &rhs.type_specifier());
+    let widen = |value: Value, to: &Token| match value {
+        Value::Int(i) if to == &Token::FLOAT => Value::Float(i as f64),
+        value => value,
+    };
+    (widen(lhs, &lhs_type), widen(rhs, &rhs_type))
+}
+
+// Converts a value for a cast like int(x), the target given as a type specifier token.
+// Numbers convert between each other and to and from bools (nonzero is true), a float
+// converts to int by dropping its fraction. Strings convert to whatever they spell out,
+// `int("12")` is 12 while `int("abc")` is an error, and anything converts to a string
+// the way print shows it.
+pub fn cast(type_specifier: &Token, value: Value) -> Result<Value, String> {
+    let error = |value: &Value| {
+        let shown = match value {
+            Value::Str(s) => format!("\"{}\"", s),
+            value => value.to_string(),
+        };
+        Err(format!(
+            "Cannot convert {} {} to {}.",
+            value.type_name(),
+            shown,
+            token_text(type_specifier)
+        ))
+    };
+    match (type_specifier, &value) {
+        (Token::INT, Value::Int(_))
+        | (Token::FLOAT, Value::Float(_))
+        | (Token::BOOL, Value::Bool(_))
+        | (Token::STRING, Value::Str(_)) => Ok(value),
+        (Token::INT, Value::Float(f)) => {
+            let truncated = f.trunc();
+            if truncated >= i32::MIN as f64 && truncated <= i32::MAX as f64 {
+                Ok(Value::Int(truncated as i32))
+            } else {
+                error(&value)
+            }
+        }
+        (Token::INT, Value::Bool(b)) => Ok(Value::Int(*b as i32)),
+        (Token::INT, Value::Str(s)) => s.parse().map(Value::Int).or_else(|_| error(&value)),
+        (Token::FLOAT, Value::Int(i)) => Ok(Value::Float(*i as f64)),
+        (Token::FLOAT, Value::Bool(b)) => Ok(Value::Float(*b as i32 as f64)),
+        (Token::FLOAT, Value::Str(s)) => s.parse().map(Value::Float).or_else(|_| error(&value)),
+        (Token::BOOL, Value::Int(i)) => Ok(Value::Bool(*i != 0)),
+        (Token::BOOL, Value::Float(f)) => Ok(Value::Bool(*f != 0.0)),
+        (Token::BOOL, Value::Str(s)) => match s.as_str() {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => error(&value),
+        },
+        (Token::STRING, value) => Ok(Value::Str(value.to_string())),
+        _ => error(&value),
+    }
+}
+
+// Unwraps an operand of a logical operator (ANDOP or OROP), which must be a bool.
+// The operators themselves are evaluated in eval.rs since they short-circuit.
+pub fn as_bool(op: &Token, operand: &Value) -> Result<bool, String> {
+    match operand {
+        Value::Bool(b) => Ok(*b),
+        _ => Err(format!(
+            "Illegal operand for {:?}: {}.",
+            op,
+            operand.type_name()
+        )),
+    }
+}
+
+// Applies a prefix operator token (NOT, MINUS, PLUS or BITNOT) to a value.
+pub fn unary_op(op: &Token, operand: Value) -> Result<Value, String> {
+    match (op, &operand) {
+        (Token::NOT, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        (Token::PLUS, Value::Int(_) | Value::Float(_)) => Ok(operand),
+        (Token::BITNOT, Value::Int(i)) => Ok(Value::Int(!i)),
+        (Token::MINUS, Value::Int(i)) => i
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or_else(|| "Integer overflow in MINUS.".to_string()),
+        (Token::MINUS, Value::Float(f)) => Ok(Value::Float(-f)),
+        _ => Err(format!(
+            "Illegal operand for {:?}: {}.",
+            op,
+            operand.type_name()
+        )),
+    }
+}
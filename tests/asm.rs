@@ -0,0 +1,17 @@
+// Compiles the example programs in src/test to x86-64 assembly, assembles and links them
+// with `cc` and runs them, comparing their stdout, stderr and exit status with the
+// interpreter. Skipped without a `cc`. Run with `cargo test --test asm`.
+
+mod cc;
+
+use oxide::codegen::asm::Generator;
+
+fn main() {
+    cc::check(cc::Backend {
+        name: "asm",
+        extension: "s",
+        flags: &[],
+        libs: &[],
+        emit: |symbols, program| Generator::new(symbols).emit_program(program),
+    });
+}
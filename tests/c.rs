@@ -0,0 +1,17 @@
+// Compiles the example programs in src/test to C, builds them with
+// `cc -std=c99 -O2 -lm` and runs them, comparing their stdout, stderr and exit status
+// with the interpreter. Skipped without a `cc`. Run with `cargo test --test c`.
+
+mod cc;
+
+use oxide::codegen::c::Generator;
+
+fn main() {
+    cc::check(cc::Backend {
+        name: "c",
+        extension: "c",
+        flags: &["-std=c99", "-O2"],
+        libs: &["-lm"],
+        emit: |symbols, program| Generator::new(symbols).emit_program(program),
+    });
+}
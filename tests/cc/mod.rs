@@ -0,0 +1,158 @@
+// Builds the example programs in src/test with a backend whose output the C compiler
+// takes, runs them and compares their stdout, stderr and exit status with the
+// interpreter's. Programs the backend doesn't compile, like those with floats for the
+// assembly backend, are skipped, and so is everything when there is no `cc`.
+
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use oxide::{
+    codegen::CodegenError,
+    interpreter::Interpreter,
+    optimizer::Optimizer,
+    output::Writer,
+    parser::{Parser, Program},
+    semantic::{Analyzer, SymbolTable},
+};
+
+// Every program reads this from stdin.
+const INPUT: &str = "1 2 3\n";
+
+pub struct Backend {
+    pub name: &'static str,
+    // Of the file the backend's output is written to.
+    pub extension: &'static str,
+    // Passed to cc before and after the file.
+    pub flags: &'static [&'static str],
+    pub libs: &'static [&'static str],
+    pub emit: fn(&SymbolTable, &Program) -> Result<String, CodegenError>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Run {
+    stdout: String,
+    stderr: String,
+    status: i32,
+}
+
+// What a compiled program does, reported the way the runtime reports it: the output up
+// to a runtime error, the error on stderr and exit status 1.
+fn interpret(symbols: &SymbolTable, program: &Program) -> Run {
+    let mut stdout = vec![];
+    let result = Interpreter::new(symbols)
+        .with_input(INPUT.as_bytes())
+        .with_output(Writer(&mut stdout))
+        .run(program);
+    let stdout = String::from_utf8(stdout).expect("output is UTF-8");
+    match result {
+        Ok(()) => Run {
+            stdout,
+            stderr: String::new(),
+            status: 0,
+        },
+        Err(error) => Run {
+            stdout,
+            stderr: format!("Runtime error: {}\n", error),
+            status: 1,
+        },
+    }
+}
+
+fn build_and_run(backend: &Backend, dir: &Path, name: &str, code: &str) -> Result<Run, String> {
+    let source = dir.join(format!("{}.{}", name, backend.extension));
+    let executable = dir.join(name);
+    fs::write(&source, code).map_err(|error| error.to_string())?;
+    let built = Command::new("cc")
+        .args(backend.flags)
+        .arg("-o")
+        .arg(&executable)
+        .arg(&source)
+        .args(backend.libs)
+        .output()
+        .map_err(|error| error.to_string())?;
+    if !built.status.success() {
+        return Err(format!(
+            "cc failed: {}",
+            String::from_utf8_lossy(&built.stderr)
+        ));
+    }
+    let mut child = Command::new(&executable)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| error.to_string())?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    match stdin.write_all(INPUT.as_bytes()) {
+        // The program finished without reading all of it.
+        Err(error) if error.kind() == ErrorKind::BrokenPipe => {}
+        result => result.map_err(|error| error.to_string())?,
+    }
+    drop(stdin);
+    let output = child
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+    Ok(Run {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        status: output.status.code().ok_or("killed by a signal")?,
+    })
+}
+
+pub fn check(backend: Backend) {
+    if Command::new("cc").arg("--version").output().is_err() {
+        println!("skipped, no cc to build {} with", backend.name);
+        return;
+    }
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(backend.name);
+    fs::create_dir_all(&dir).expect("the build directory can be created");
+    let mut paths: Vec<_> = fs::read_dir("src/test")
+        .expect("src/test exists")
+        .map(|entry| entry.expect("src/test is readable").path())
+        .filter(|path| path.extension().is_none())
+        .collect();
+    paths.sort();
+    let mut compiled = 0;
+    let mut failures = vec![];
+    for path in paths {
+        let name = path.display().to_string();
+        let source = fs::read_to_string(&path).expect("test programs are text");
+        let Ok(program) = Parser::new(&source).parse_program() else {
+            continue;
+        };
+        let Ok(symbols) = Analyzer::new().analyze(&program) else {
+            continue;
+        };
+        let program = Optimizer::new(&symbols).optimize_program(program);
+        let code = match (backend.emit)(&symbols, &program) {
+            Ok(code) => code,
+            Err(error) => {
+                println!("{}: skipped, {}", name, error);
+                continue;
+            }
+        };
+        compiled += 1;
+        let expected = interpret(&symbols, &program);
+        let file_name = path.file_name().expect("a file").to_string_lossy();
+        match build_and_run(&backend, &dir, &file_name, &code) {
+            Ok(actual) if actual == expected && actual.status == 0 => println!("{}: ok", name),
+            Ok(actual) if actual == expected => {
+                println!("{}: ok, {}", name, actual.stderr.trim_end())
+            }
+            Ok(actual) => failures.push(format!(
+                "{}: expected {:?}, got {:?}",
+                name, expected, actual
+            )),
+            Err(error) => failures.push(format!("{}: {}", name, error)),
+        }
+    }
+    for failure in &failures {
+        println!("{}", failure);
+    }
+    assert!(compiled > 0, "No program in src/test was compiled.");
+    assert!(failures.is_empty(), "{} programs failed.", failures.len());
+}
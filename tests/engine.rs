@@ -0,0 +1,405 @@
+// Runs scripts through the Engine the way an embedding host would. Run with
+// `cargo test --test engine`.
+
+use std::{
+    collections::HashMap,
+    io,
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use oxide::{
+    engine::EngineError,
+    interpreter::{Interpreter, Limit, Limits, RuntimeError},
+    output::{Callback, Capture, Output, PrintFormat, Writer},
+    parser::Parser,
+    semantic::Analyzer,
+    value::Value,
+    Engine, Token,
+};
+
+#[test]
+fn runs_while_the_host_holds_stdin() {
+    let stdin = io::stdin();
+    let _lock = stdin.lock();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let source = "init int n; read(n); print(n * 2); halt";
+        let outcome = Engine::new().with_input("21").run_source(source);
+        sender
+            .send(outcome.map(|outcome| outcome.output).ok())
+            .unwrap();
+    });
+    let output = receiver
+        .recv_timeout(Duration::from_secs(10))
+        .expect("the engine doesn't wait for stdin");
+    assert_eq!(output, Some(vec!["42".to_string()]));
+}
+
+#[test]
+fn shares_an_engine_between_threads() {
+    let table: HashMap<String, i32> = HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)]);
+    let mut engine = Engine::new();
+    engine
+        .register(
+            "lookup",
+            &[Token::STRING],
+            Token::INT,
+            move |args| match &args[0] {
+                Value::Str(key) => table
+                    .get(key)
+                    .map(|value| Value::Int(*value))
+                    .ok_or_else(|| RuntimeError::new(format!("No entry for {}.", key))),
+                _ => unreachable!(),
+            },
+        )
+        .unwrap();
+    let engine = Arc::new(engine);
+    let threads: Vec<_> = ["a", "b"]
+        .into_iter()
+        .map(|key| {
+            let engine = Arc::clone(&engine);
+            thread::spawn(move || {
+                let source = format!("init print(lookup(\"{}\") * 10); halt", key);
+                engine.run_source(&source).unwrap().output
+            })
+        })
+        .collect();
+    let outputs: Vec<Vec<String>> = threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .collect();
+    assert_eq!(outputs, [["10"], ["20"]]);
+}
+
+// Runs a loop that never ends under the given limits, returning the limit it stopped at
+// and the line.
+fn run_forever(limits: Limits) -> (Limit, u32) {
+    let source = "init
+        int n;
+        n = 0;
+        while (true) do
+            n = n + 1;
+        endwhile;
+        halt";
+    match Engine::new().with_limits(limits).run_source(source) {
+        Err(EngineError::Runtime(RuntimeError::LimitExceeded { limit, line })) => (limit, line),
+        result => panic!("expected a limit, got {:?}", result),
+    }
+}
+
+#[test]
+fn stops_an_endless_loop_out_of_fuel() {
+    let limits = Limits {
+        fuel: Some(1000),
+        ..Limits::default()
+    };
+    assert_eq!(run_forever(limits), (Limit::Fuel(1000), 5));
+}
+
+#[test]
+fn stops_an_endless_loop_after_its_iterations() {
+    let limits = Limits {
+        loop_iterations: Some(100),
+        ..Limits::default()
+    };
+    assert_eq!(run_forever(limits), (Limit::LoopIterations(100), 4));
+}
+
+#[test]
+fn stops_an_endless_loop_on_timeout() {
+    let limits = Limits {
+        timeout: Some(Duration::from_millis(50)),
+        ..Limits::default()
+    };
+    let started = Instant::now();
+    let (limit, _) = run_forever(limits);
+    assert_eq!(limit, Limit::Timeout(Duration::from_millis(50)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn limits_strings_inside_an_expression() {
+    let engine = Engine::new().with_limits(Limits {
+        string_length: Some(100),
+        fuel: Some(1000),
+        ..Limits::default()
+    });
+    let t = "a".repeat(100);
+    let source = format!(
+        "init string t; int n; t = \"{}\"; n = len(replace(replace(t, \"a\", t), \"a\", t)); halt",
+        t
+    );
+    match engine.run_source(&source) {
+        Err(EngineError::Runtime(RuntimeError::LimitExceeded { limit, line })) => {
+            assert_eq!(limit, Limit::StringLength(100));
+            assert_eq!(line, 1);
+        }
+        result => panic!("expected the string length limit, got {:?}", result),
+    }
+    let source = format!("init string t; int n; t = \"{}\"; n = len(t + t); halt", t);
+    assert!(matches!(
+        engine.run_source(&source),
+        Err(EngineError::Runtime(RuntimeError::LimitExceeded {
+            limit: Limit::StringLength(100),
+            ..
+        }))
+    ));
+    let source = format!("init int n; n = len(\"{}\"); print(n); halt", t);
+    assert_eq!(engine.run_source(&source).unwrap().output, ["100"]);
+}
+
+#[test]
+fn limits_constant_initializers() {
+    let engine = Engine::new().with_limits(Limits {
+        string_length: Some(100),
+        fuel: Some(1000),
+        timeout: Some(Duration::from_millis(100)),
+        ..Limits::default()
+    });
+    // Each constant is its predecessor squared, the last one would have 10^16 characters.
+    let source = "init
+        const string a = \"aaaaaaaaaa\";
+        const string b = replace(a, \"a\", a);
+        const string c = replace(b, \"a\", b);
+        const string d = replace(c, \"a\", c);
+        const string e = replace(d, \"a\", d);
+        halt";
+    match engine.run_source(source) {
+        Err(EngineError::Semantic(errors)) => {
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|error| (error.line, error.limit, error.to_string()))
+                .collect();
+            assert_eq!(
+                errors[0],
+                (
+                    4,
+                    Some(Limit::StringLength(100)),
+                    "Invalid initializer for constant 'c': String longer than 100 characters."
+                        .to_string()
+                )
+            );
+        }
+        result => panic!("expected the string length limit, got {:?}", result),
+    }
+    let terms = vec!["1"; 20].join(" + ");
+    let engine = Engine::new().with_limits(Limits {
+        fuel: Some(10),
+        ..Limits::default()
+    });
+    let source = format!("init const int n = {}; halt", terms);
+    match engine.run_source(&source) {
+        Err(EngineError::Semantic(errors)) => assert_eq!(errors[0].limit, Some(Limit::Fuel(10))),
+        result => panic!("expected the fuel limit, got {:?}", result),
+    }
+    // Folds that would go past the limit are left for the interpreter to run into.
+    let engine = Engine::new().with_limits(Limits {
+        string_length: Some(100),
+        ..Limits::default()
+    });
+    let source = "init
+        const string b = replace(\"aaaaaaaaaa\", \"a\", \"aaaaaaaaaa\");
+        print(len(replace(b, \"a\", b)));
+        halt";
+    assert!(matches!(
+        engine.run_source(source),
+        Err(EngineError::Runtime(RuntimeError::LimitExceeded {
+            limit: Limit::StringLength(100),
+            line: 3
+        }))
+    ));
+}
+
+// Prints a line, writes part of the next one and prints the rest of it.
+const PRINTS: &str = "init int n; print(1, \"a\"); n = write(\"b\"); print(2.5); halt";
+
+fn print_to(output: impl Output, print_format: PrintFormat) {
+    let program = Parser::new(PRINTS).parse_program().unwrap();
+    let symbols = Analyzer::new().analyze(&program).unwrap();
+    Interpreter::new(&symbols)
+        .with_input("".as_bytes())
+        .with_output(output)
+        .with_print_format(print_format)
+        .run(&program)
+        .unwrap();
+}
+
+#[test]
+fn sends_output_to_each_sink() {
+    let mut capture = Capture::new();
+    print_to(&mut capture, PrintFormat::default());
+    assert_eq!(capture.lines(), ["1a", "b2.5"]);
+
+    let mut bytes = vec![];
+    print_to(Writer(&mut bytes), PrintFormat::default());
+    assert_eq!(bytes, b"1a\nb2.5\n");
+
+    let mut texts = vec![];
+    print_to(
+        Callback(|text: &str| texts.push(text.to_string())),
+        PrintFormat::default(),
+    );
+    assert_eq!(texts, ["1a\n", "b", "2.5\n"]);
+}
+
+#[test]
+fn formats_prints() {
+    let printed = |print_format: PrintFormat| {
+        let mut bytes = vec![];
+        print_to(Writer(&mut bytes), print_format);
+        String::from_utf8(bytes).unwrap()
+    };
+    let separator = PrintFormat {
+        separator: ", ".to_string(),
+        ..PrintFormat::default()
+    };
+    assert_eq!(printed(separator), "1, a\nb2.5\n");
+    let float_precision = PrintFormat {
+        float_precision: Some(3),
+        ..PrintFormat::default()
+    };
+    assert_eq!(printed(float_precision), "1a\nb2.500\n");
+    let no_newline = PrintFormat {
+        trailing_newline: false,
+        ..PrintFormat::default()
+    };
+    assert_eq!(printed(no_newline), "1ab2.5");
+
+    let engine = Engine::new().with_print_format(PrintFormat {
+        separator: " ".to_string(),
+        float_precision: Some(1),
+        trailing_newline: true,
+    });
+    let outcome = engine.run_source(PRINTS).unwrap();
+    assert_eq!(outcome.output, ["1 a", "b2.5"]);
+}
+
+#[test]
+fn reads_and_assigns_host_globals() {
+    let mut engine = Engine::new();
+    engine.set("threshold", Value::Float(0.5)).unwrap();
+    engine.set("name", Value::Str("x".to_string())).unwrap();
+    let source = "init
+        bool flagged;
+        flagged = threshold > 0.25;
+        threshold = threshold * 2.0;
+        print(name, flagged);
+        halt";
+    let outcome = engine.run_source(source).unwrap();
+    assert_eq!(outcome.output, ["xtrue"]);
+    assert_eq!(
+        outcome.variables,
+        HashMap::from([
+            ("threshold".to_string(), Value::Float(1.0)),
+            ("name".to_string(), Value::Str("x".to_string())),
+            ("flagged".to_string(), Value::Bool(true)),
+        ])
+    );
+    // Globals are typed by their value.
+    match engine.run_source("init threshold = \"high\"; halt") {
+        Err(EngineError::Semantic(errors)) => assert_eq!(
+            errors[0].to_string(),
+            "Cannot assign string to 'threshold' declared as FLOAT."
+        ),
+        result => panic!("expected a type error, got {:?}", result),
+    }
+    assert_eq!(
+        engine.set("not a name", Value::Int(1)),
+        Err("'not a name' is not an identifier.".to_string())
+    );
+}
+
+#[test]
+fn rejects_redeclaring_a_host_global() {
+    let mut engine = Engine::new();
+    engine.set("limit", Value::Int(10)).unwrap();
+    match engine.run_source("init\n    int limit;\n    limit = 1;\nhalt") {
+        Err(EngineError::Semantic(errors)) => {
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|error| (error.line, error.to_string()))
+                .collect();
+            assert_eq!(
+                errors,
+                [(2, "'limit' is already declared by the host.".to_string())]
+            );
+        }
+        result => panic!("expected a semantic error, got {:?}", result),
+    }
+}
+
+// Divides its first argument by its second, failing on zero, to check calls to natives.
+fn engine_with_ratio() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .register(
+            "ratio",
+            &[Token::FLOAT, Token::FLOAT],
+            Token::FLOAT,
+            |args| match (&args[0], &args[1]) {
+                (_, Value::Float(b)) if *b == 0.0 => {
+                    Err(RuntimeError::new("Zero ratio.".to_string()))
+                }
+                (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a / b)),
+                _ => unreachable!(),
+            },
+        )
+        .unwrap();
+    engine
+}
+
+fn semantic_errors(engine: &Engine, source: &str) -> Vec<(u32, String)> {
+    match engine.run_source(source) {
+        Err(EngineError::Semantic(errors)) => errors
+            .iter()
+            .map(|error| (error.line, error.to_string()))
+            .collect(),
+        result => panic!("expected semantic errors, got {:?}", result),
+    }
+}
+
+#[test]
+fn checks_native_calls_before_running() {
+    let engine = engine_with_ratio();
+    let outcome = engine
+        .run_source("init\n    print(ratio(3, 2.0));\nhalt")
+        .unwrap();
+    assert_eq!(outcome.output, ["1.5"]);
+    // Nothing runs when a call doesn't match the signature.
+    let arity = "init\n    print(\"first\");\n    print(ratio(1.0));\nhalt";
+    assert_eq!(
+        semantic_errors(&engine, arity),
+        [(3, "'ratio' takes 2 arguments, got 1.".to_string())]
+    );
+    let types = "init\n    print(\"first\");\n    print(ratio(1.0, \"2\"));\nhalt";
+    assert_eq!(
+        semantic_errors(&engine, types),
+        [(
+            3,
+            "Illegal argument 2 for 'ratio': string, expected float.".to_string()
+        )]
+    );
+}
+
+#[test]
+fn places_runtime_errors() {
+    let engine = engine_with_ratio();
+    let source = "init\n    float r;\n    r = 1.0;\n    r = 2.0 +   ratio(r, 0.0);\nhalt";
+    match engine.run_source(source) {
+        Err(EngineError::Runtime(error)) => {
+            assert_eq!(
+                error.to_string(),
+                "Line 4, column 17: in 'ratio': Zero ratio."
+            )
+        }
+        result => panic!("expected a runtime error, got {:?}", result),
+    }
+    match engine.run_source("init\n    int n;\n    n = 0;\n    print(1 / n);\nhalt") {
+        Err(EngineError::Runtime(error)) => {
+            assert_eq!(error.to_string(), "Line 4: Division by zero.")
+        }
+        result => panic!("expected a runtime error, got {:?}", result),
+    }
+}
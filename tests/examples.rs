@@ -0,0 +1,113 @@
+// Checks the example programs in src/test against the expectations next to them. For
+// src/test/N, src/test/N.expected holds the errors the parser or the analyzer reports,
+// the linter's diagnostics under the program's directives, and what the interpreter
+// prints running the program with INPUT on stdin, followed by the runtime error it stops
+// at. Run with `cargo test --test examples`, with UPDATE_EXPECTED=1 to write the
+// expectations from what the programs do now.
+
+use std::{env, fs};
+
+use oxide::{
+    interpreter::{Interpreter, Limits},
+    lint::{LintConfig, Linter},
+    optimizer::Optimizer,
+    output::Writer,
+    parser::Parser,
+    semantic::Analyzer,
+};
+
+const INPUT: &str = "1 2 3\n";
+
+// Each section starts with a `-- name` line, those with nothing in them are left out.
+fn section(out: &mut String, name: &str, text: &str) {
+    if !text.is_empty() {
+        out.push_str(&format!("-- {}\n{}", name, text));
+    }
+}
+
+fn lines(messages: impl Iterator<Item = String>) -> String {
+    messages.map(|message| message + "\n").collect()
+}
+
+fn check(source: &str) -> String {
+    let mut out = String::new();
+    let program = match Parser::new(source).parse_program() {
+        Ok(program) => program,
+        Err(error) => {
+            let error = format!("line {}: {}", error.line, error);
+            section(&mut out, "errors", &lines([error].into_iter()));
+            return out;
+        }
+    };
+    let mut config = LintConfig::new();
+    config
+        .apply_directives(source)
+        .expect("lint directives are well formed");
+    let diagnostics = Linter::new(&config).lint(&program);
+    let lint = lines(diagnostics.iter().map(ToString::to_string));
+    let symbols = match Analyzer::new().analyze(&program) {
+        Ok(symbols) => symbols,
+        Err(errors) => {
+            let errors = errors
+                .iter()
+                .map(|error| format!("line {}: {}", error.line, error));
+            section(&mut out, "errors", &lines(errors));
+            section(&mut out, "lint", &lint);
+            return out;
+        }
+    };
+    section(&mut out, "lint", &lint);
+    let program = Optimizer::new(&symbols).optimize_program(program);
+    let mut stdout = vec![];
+    let result = Interpreter::new(&symbols)
+        .with_input(INPUT.as_bytes())
+        .with_output(Writer(&mut stdout))
+        .with_limits(Limits {
+            fuel: Some(1_000_000),
+            ..Limits::default()
+        })
+        .run(&program);
+    let mut output = String::from_utf8(stdout).expect("output is UTF-8");
+    if let Err(error) = result {
+        output.push_str(&format!("Runtime error: {}\n", error));
+    }
+    section(&mut out, "output", &output);
+    out
+}
+
+fn main() {
+    let update = env::var_os("UPDATE_EXPECTED").is_some();
+    let mut paths: Vec<_> = fs::read_dir("src/test")
+        .expect("src/test exists")
+        .map(|entry| entry.expect("src/test is readable").path())
+        .filter(|path| path.extension().is_none())
+        .collect();
+    paths.sort();
+    let mut failures = vec![];
+    for path in paths {
+        let name = path.display().to_string();
+        let source = fs::read_to_string(&path).expect("test programs are text");
+        let actual = check(&source);
+        let expected_path = path.with_extension("expected");
+        if update {
+            fs::write(&expected_path, &actual).expect("expectations can be written");
+            println!("{}: updated", name);
+            continue;
+        }
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => println!("{}: ok", name),
+            Ok(expected) => {
+                failures.push(format!("{}: expected\n{}got\n{}", name, expected, actual))
+            }
+            Err(_) => failures.push(format!(
+                "{}: no {}, run with UPDATE_EXPECTED=1 to write it",
+                name,
+                expected_path.display()
+            )),
+        }
+    }
+    for failure in &failures {
+        println!("{}", failure);
+    }
+    assert!(failures.is_empty(), "{} programs failed.", failures.len());
+}
@@ -0,0 +1,128 @@
+// Applies random edits to the example programs and checks that IncrementalParser always
+// ends up where parsing the edited text from scratch does. Run with
+// `cargo test --test incremental`.
+
+use std::{fs, path::Path};
+
+use oxide::{
+    incremental::{IncrementalParser, TextEdit},
+    parser::{ParseError, Parser, Program},
+};
+
+// Text the edits insert, chosen to join, split and break statements.
+const FRAGMENTS: &[&str] = &[
+    "",
+    " ",
+    "\n",
+    ";",
+    "x",
+    "1 + ",
+    "\"",
+    "é",
+    "// note\n",
+    "print(1);\n",
+    "int y;\n",
+    "while (true) do\n",
+    "endwhile;\n",
+    "if (x > 1) then\n",
+    "endif;\n",
+    "init",
+    "halt",
+];
+
+const EDITS_PER_PROGRAM: usize = 100;
+
+// A xorshift generator, so that a failure can be reproduced from its seed.
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+
+    // A byte offset into the text on a char boundary.
+    fn offset(&mut self, text: &str) -> usize {
+        let mut offset = self.below(text.len() + 1);
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+}
+
+// The program, or the parse error, as a string to compare.
+fn parsed(result: Result<&Program, &ParseError>) -> String {
+    match result {
+        Ok(program) => format!("{:?}", program),
+        Err(error) => format!("error: {}", error),
+    }
+}
+
+// Applies the edit to both the text and the parser and compares the parser's program
+// with a full parse of the text. Returns whether the text parses.
+fn check(
+    parser: &mut IncrementalParser,
+    text: &mut String,
+    edit: TextEdit,
+    path: &Path,
+    n: usize,
+) -> bool {
+    text.replace_range(edit.range.clone(), &edit.text);
+    parser.edit(edit.clone());
+    assert_eq!(parser.text(), text);
+    let full = Parser::new(text).parse_program();
+    assert_eq!(
+        parsed(parser.program()),
+        parsed(full.as_ref()),
+        "{}: edit {} ({:?}) of\n{}",
+        path.display(),
+        n,
+        edit,
+        text
+    );
+    full.is_ok()
+}
+
+#[test]
+fn random_edits_match_a_full_parse() {
+    let mut paths: Vec<_> = fs::read_dir("src/test")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_none())
+        .collect();
+    paths.sort();
+    for (seed, path) in paths.iter().enumerate() {
+        let mut rng = Rng(seed as u64 + 1);
+        let mut text = fs::read_to_string(path).unwrap();
+        let mut parser = IncrementalParser::new(text.clone());
+        for n in 0..EDITS_PER_PROGRAM {
+            let start = rng.offset(&text);
+            // Mostly small edits, now and then a whole region.
+            let end = match rng.below(4) {
+                0 => rng.offset(&text).max(start),
+                _ => (start..=(start + 3).min(text.len()))
+                    .rev()
+                    .find(|end| text.is_char_boundary(*end))
+                    .unwrap(),
+            };
+            let inserted = FRAGMENTS[rng.below(FRAGMENTS.len())];
+            let removed = text[start..end].to_string();
+            let edit = TextEdit {
+                range: start..end,
+                text: inserted.to_string(),
+            };
+            let parses = check(&mut parser, &mut text, edit, path, n);
+            // Undoing an edit that broke the program gets the next ones a tree to reuse.
+            if !parses {
+                let undo = TextEdit {
+                    range: start..start + inserted.len(),
+                    text: removed,
+                };
+                check(&mut parser, &mut text, undo, path, n);
+            }
+        }
+    }
+}
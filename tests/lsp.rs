@@ -0,0 +1,300 @@
+// Drives the oxide-lsp binary over stdio like an editor would, with JSON-RPC messages
+// framed by Content-Length headers. Run with `cargo test --test lsp`.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use serde_json::{json, Value as Json};
+
+const URI: &str = "file:///test.ox";
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_oxide-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("oxide-lsp starts");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut client = Client {
+            child,
+            stdin,
+            stdout,
+            next_id: 1,
+        };
+        let capabilities =
+            client.request("initialize", json!({}))["result"]["capabilities"].clone();
+        assert_eq!(capabilities["textDocumentSync"], 2);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send_raw(&mut self, body: &[u8]) {
+        write!(self.stdin, "Content-Length: {}\r\n\r\n", body.len()).unwrap();
+        self.stdin.write_all(body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn send(&mut self, message: Json) {
+        self.send_raw(message.to_string().as_bytes());
+    }
+
+    fn receive(&mut self) -> Json {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            let read = self.stdout.read_line(&mut header).unwrap();
+            assert!(read > 0, "oxide-lsp closed its output");
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse().ok();
+            }
+        }
+        let mut body = vec![0; length.expect("Content-Length header")];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, method: &str, params: Json) -> Json {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        let response = self.receive();
+        assert_eq!(response["id"], id);
+        response
+    }
+
+    fn notify(&mut self, method: &str, params: Json) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    // Opens the document and returns the messages of its diagnostics.
+    fn open(&mut self, text: &str) -> Vec<String> {
+        self.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": URI, "languageId": "oxide", "version": 1, "text": text } }),
+        );
+        diagnostics(self.receive())
+    }
+
+    fn change(&mut self, start: (u32, u32), end: (u32, u32), text: &str) -> Vec<String> {
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{
+                    "range": {
+                        "start": { "line": start.0, "character": start.1 },
+                        "end": { "line": end.0, "character": end.1 },
+                    },
+                    "text": text,
+                }],
+            }),
+        );
+        diagnostics(self.receive())
+    }
+
+    fn at(&mut self, method: &str, line: u32, character: u32) -> Json {
+        let params = json!({
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+        });
+        self.request(method, params)["result"].clone()
+    }
+
+    // Shuts down cleanly and checks the server exited successfully.
+    fn exit(mut self) {
+        assert_eq!(self.request("shutdown", Json::Null)["result"], Json::Null);
+        self.notify("exit", Json::Null);
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+fn diagnostics(notification: Json) -> Vec<String> {
+    assert_eq!(notification["method"], "textDocument/publishDiagnostics");
+    assert_eq!(notification["params"]["uri"], URI);
+    notification["params"]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|diagnostic| diagnostic["message"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn language_features() {
+    let mut client = Client::start();
+    let text = "init\n    const int MAX = 10;\n    int x;\n    x = MAX + 1;\n    print(x);\nhalt\n";
+    assert_eq!(client.open(text), Vec::<String>::new());
+
+    let hover = client.at("textDocument/hover", 3, 9);
+    assert_eq!(
+        hover["contents"]["value"],
+        "```oxide\nconst int MAX = 10\n```"
+    );
+    let definition = client.at("textDocument/definition", 4, 10);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 2, "character": 8 })
+    );
+
+    let labels: Vec<Json> = client
+        .at("textDocument/completion", 4, 0)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].clone())
+        .collect();
+    for label in ["while", "len", "MAX", "x"] {
+        assert!(
+            labels.contains(&json!(label)),
+            "no completion for {}",
+            label
+        );
+    }
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let names: Vec<&Json> = symbols["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| &symbol["name"])
+        .collect();
+    assert_eq!(names, [&json!("MAX"), &json!("x")]);
+
+    // `x = MAX + 1;` becomes `x = MAX + y;`, which reads an undeclared variable.
+    let messages = client.change((3, 14), (3, 15), "y");
+    assert_eq!(messages.len(), 1, "{:?}", messages);
+    assert!(messages[0].contains("'y'"), "{:?}", messages);
+    assert_eq!(client.change((3, 14), (3, 15), "1"), Vec::<String>::new());
+
+    let formatting = client.request(
+        "textDocument/formatting",
+        json!({ "textDocument": { "uri": URI }, "options": { "tabSize": 4, "insertSpaces": true } }),
+    );
+    assert_eq!(formatting["result"][0]["newText"], text);
+    client.exit();
+}
+
+#[test]
+fn tabs_and_crlf() {
+    let mut client = Client::start();
+    let text = "init\r\n\tint x;\r\n\tx = 1;\r\n\tprint(x);\r\nhalt\r\n";
+    assert_eq!(client.open(text), Vec::<String>::new());
+    let hover = client.at("textDocument/hover", 3, 7);
+    assert_eq!(hover["contents"]["value"], "```oxide\nint x\n```");
+    client.exit();
+}
+
+#[test]
+fn bad_input_keeps_the_server_running() {
+    let mut client = Client::start();
+    let messages = client.open("init\n    int x;\n    x = 99999999999;\nhalt\n");
+    assert_eq!(messages.len(), 1, "{:?}", messages);
+    assert!(messages[0].contains("out of range"), "{:?}", messages);
+
+    client.send_raw(b"{ not json");
+    let error = client.receive();
+    assert_eq!(error["id"], Json::Null);
+    assert_eq!(error["error"]["code"], -32700);
+
+    assert!(client.request("textDocument/rename", json!({}))["error"].is_object());
+    assert_eq!(
+        client.open("init\n    print(1);\nhalt\n"),
+        Vec::<String>::new()
+    );
+    client.exit();
+}
+
+#[test]
+fn constants_too_long_to_evaluate() {
+    let mut client = Client::start();
+    // The constants would have 10^4, 10^8 and 10^16 characters.
+    let text = "init
+    const string a = \"aaaaaaaaaa\";
+    const string b = replace(a, \"a\", a);
+    const string c = replace(b, \"a\", b);
+    const string d = replace(c, \"a\", c);
+    const string e = replace(d, \"a\", d);
+    print(e);
+halt
+";
+    let messages = client.open(text);
+    assert!(
+        messages
+            .iter()
+            .any(|message| message.contains("String longer than")),
+        "{:?}",
+        messages
+    );
+    client.exit();
+}
+
+#[test]
+fn positions_count_utf16_code_units() {
+    let mut client = Client::start();
+    // "😀" is one char but two UTF-16 code units, "é" one of each.
+    let text = "init\n    int é;\n    é = 1; print(\"😀\", é);\nhalt\n";
+    assert_eq!(client.open(text), Vec::<String>::new());
+    let definition = client.at("textDocument/definition", 2, 23);
+    assert_eq!(
+        definition["range"],
+        json!({
+            "start": { "line": 1, "character": 8 },
+            "end": { "line": 1, "character": 9 },
+        })
+    );
+    assert!(client.at("textDocument/hover", 2, 22).is_null());
+    // Only the second `é` is replaced.
+    let messages = client.change((2, 23), (2, 24), "x");
+    assert!(
+        messages
+            .iter()
+            .any(|message| message.starts_with("Use of undeclared identifier 'x'")),
+        "{:?}",
+        messages
+    );
+    client.exit();
+}
+
+#[test]
+fn edits_past_the_end_are_clamped() {
+    let mut client = Client::start();
+    assert_eq!(
+        client.open("init\n    print(1);\n"),
+        ["Unexpected end of input.".to_string()]
+    );
+    assert_eq!(
+        client.change((9, 0), (9, 4), "halt\n"),
+        Vec::<String>::new()
+    );
+    client.exit();
+}
+
+#[test]
+fn ignores_responses() {
+    let mut client = Client::start();
+    client.send(json!({ "jsonrpc": "2.0", "id": 99, "result": null }));
+    // The next message is the diagnostics, not an error for the response.
+    assert_eq!(
+        client.open("init\n    print(1);\nhalt\n"),
+        Vec::<String>::new()
+    );
+    client.exit();
+}
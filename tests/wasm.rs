@@ -0,0 +1,410 @@
+// Compiles the example programs in src/test to WebAssembly, checks the module has the
+// shape hosts expect and runs it under wasmi, comparing what it prints with the
+// interpreter, and does the same for programs made to test floats. Programs the backend
+// doesn't compile, like those reading input, are skipped. Run with
+// `cargo test --test wasm`.
+
+use std::fs;
+
+use oxide::{
+    codegen::wasm::Generator, engine::EngineError, optimizer::Optimizer, parser::Parser,
+    semantic::Analyzer, Engine,
+};
+use wasmi::{core::ValType, Caller, Extern, ExternType, Linker, Module, Store};
+
+#[derive(Default)]
+struct Host {
+    output: String,
+    error: Option<String>,
+}
+
+fn text(caller: &Caller<'_, Host>, address: i32, length: i32) -> String {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .expect("memory is exported");
+    let bytes = &memory.data(caller)[address as usize..(address + length) as usize];
+    String::from_utf8(bytes.to_vec()).expect("output is UTF-8")
+}
+
+// Imports are env.print taking an address and a length and env.error taking a line
+// first, exports are the memory and a main without parameters or results.
+fn check_shape(module: &Module) -> Result<(), String> {
+    for import in module.imports() {
+        let ExternType::Func(func) = import.ty() else {
+            return Err(format!("Import {} isn't a function.", import.name()));
+        };
+        let params: &[ValType] = match (import.module(), import.name()) {
+            ("env", "print") => &[ValType::I32, ValType::I32],
+            ("env", "error") => &[ValType::I32, ValType::I32, ValType::I32],
+            _ => &[],
+        };
+        if params.is_empty() || func.params() != params || !func.results().is_empty() {
+            return Err(format!(
+                "Unexpected import {}.{}: {:?}.",
+                import.module(),
+                import.name(),
+                func
+            ));
+        }
+    }
+    let mut exports: Vec<&str> = module.exports().map(|export| export.name()).collect();
+    exports.sort();
+    if exports != ["main", "memory"] {
+        return Err(format!("Unexpected exports {:?}.", exports));
+    }
+    for export in module.exports() {
+        match (export.name(), export.ty()) {
+            ("memory", ExternType::Memory(_)) => {}
+            ("main", ExternType::Func(func))
+                if func.params().is_empty() && func.results().is_empty() => {}
+            (name, ty) => return Err(format!("Unexpected export {}: {:?}.", name, ty)),
+        }
+    }
+    Ok(())
+}
+
+// What the module printed, and the runtime error it stopped at.
+fn run(wat: &str) -> Result<(String, Option<String>), String> {
+    let wasm = wat::parse_str(wat).map_err(|error| error.to_string())?;
+    let engine = wasmi::Engine::default();
+    let module = Module::new(&engine, &wasm).map_err(|error| error.to_string())?;
+    check_shape(&module)?;
+    let mut store = Store::new(&engine, Host::default());
+    let mut linker = <Linker<Host>>::new(&engine);
+    linker
+        .func_wrap(
+            "env",
+            "print",
+            |mut caller: Caller<'_, Host>, address: i32, length: i32| {
+                let text = text(&caller, address, length);
+                caller.data_mut().output.push_str(&text);
+            },
+        )
+        .and_then(|linker| {
+            linker.func_wrap(
+                "env",
+                "error",
+                |mut caller: Caller<'_, Host>, line: i32, address: i32, length: i32| {
+                    let text = format!("Line {}: {}", line, text(&caller, address, length));
+                    caller.data_mut().error = Some(text);
+                },
+            )
+        })
+        .map_err(|error| error.to_string())?;
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .and_then(|instance| instance.start(&mut store))
+        .map_err(|error| error.to_string())?;
+    let main = instance
+        .get_typed_func::<(), ()>(&store, "main")
+        .map_err(|error| error.to_string())?;
+    let trapped = main.call(&mut store, ()).is_err();
+    let host = store.into_data();
+    match (trapped, host.error) {
+        (false, None) => Ok((host.output, None)),
+        (true, Some(error)) => Ok((host.output, Some(error))),
+        (_, error) => Err(format!("Trapped: {}, error: {:?}.", trapped, error)),
+    }
+}
+
+// Runs a program compiled to WebAssembly and compares it with the interpreter. Returns
+// whether it compiled.
+fn compare(name: &str, source: &str, failures: &mut Vec<String>) -> bool {
+    let Ok(program) = Parser::new(source).parse_program() else {
+        return false;
+    };
+    let Ok(symbols) = Analyzer::new().analyze(&program) else {
+        return false;
+    };
+    let program = Optimizer::new(&symbols).optimize_program(program);
+    let wat = match Generator::new(&symbols).emit_program(&program) {
+        Ok(wat) => wat,
+        Err(error) => {
+            println!("{}: skipped, {}", name, error);
+            return false;
+        }
+    };
+    let expected = match Engine::new().run_source(source) {
+        Ok(outcome) => {
+            let lines: Vec<String> = outcome
+                .output
+                .iter()
+                .map(|line| line.clone() + "\n")
+                .collect();
+            (lines.concat(), None)
+        }
+        Err(EngineError::Runtime(error)) => {
+            // The output up to the error isn't kept by the engine, only the error is
+            // compared.
+            (String::new(), Some(error.to_string()))
+        }
+        Err(error) => panic!("{}: {}", name, error),
+    };
+    match run(&wat) {
+        Ok((_, Some(error))) if expected.1.as_ref() == Some(&error) => {
+            println!("{}: ok, {}", name, error)
+        }
+        Ok(actual) if actual == expected => println!("{}: ok", name),
+        Ok(actual) => failures.push(format!(
+            "{}: expected {:?}, got {:?}",
+            name, expected, actual
+        )),
+        Err(error) => failures.push(format!("{}: {}", name, error)),
+    }
+    true
+}
+
+// Float programs beyond the examples. A program stops at its first runtime error, so
+// each error gets one of its own.
+const FLOAT_PROGRAMS: &[&str] = &[
+    "init
+    float a, b, zero, big, tiny;
+    int i;
+    a = 0.1;
+    b = 0.2;
+    zero = 0.0;
+    big = float(\"1e20\");
+    tiny = float(\"5e-324\");
+    i = 3;
+    print(a + b, \" \", a * 3, \" \", 1 / (a * 3), \" \", b - a, \" \", -a, \" \", -zero);
+    print(a * big, \" \", a / big, \" \", float(\"1.7976931348623157e308\") * a, \" \", tiny * i);
+    print(zero / zero, \" \", 1 / zero, \" \", -1 / zero, \" \", float(\"2.2250738585072014e-308\") / i);
+    print(i + a, \" \", i * b, \" \", i / 2.0, \" \", i == 3.0, \" \", a < b, \" \", a > b);
+    print(zero / zero == zero / zero, \" \", zero == -zero, \" \", 0.3 == a + b);
+    a += 1;
+    b *= i;
+    print(a, \" \", b, \" \", a ** 3, \" \", a ** -3, \" \", b ** 0, \" \", 2.0 ** 1074, \" \", 2.0 ** -1074);
+halt",
+    "init
+    float a, b, tiny;
+    a = 7.5;
+    b = -2.0;
+    tiny = float(\"5e-324\");
+    print(a % b, \" \", -a % b, \" \", a mod b, \" \", -a mod b, \" \", a mod -b, \" \", a div b);
+    print(float(\"1e300\") % 3.0, \" \", tiny % 2.0, \" \", a % (b - b), \" \", (a - a) mod b, \" \", -a div 0.5);
+    print(a % 0.1, \" \", 123456789.0 mod -0.001, \" \", float(\"1e-310\") mod float(\"3e-320\"));
+    print(float(\"1e308\") div float(\"1e-10\"), \" \", a % (a / 0.0), \" \", (a / 0.0) % a);
+halt",
+    "init
+    float x, nan;
+    x = -12.5;
+    nan = x - x;
+    nan = nan / nan;
+    print(abs(x), \" \", min(x, 3), \" \", max(x, 3), \" \", min(nan, x), \" \", max(x, nan));
+    print(sqrt(2), \" \", floor(x), \" \", ceil(x), \" \", round(x), \" \", round(12.5), \" \", round(nan));
+    print(round(0.49999999999999994), \" \", round(-0.3), \" \", round(4503599627370497.0));
+    print(int(x), \" \", int(-x), \" \", int(2147483647.9), \" \", int(-2147483648.5));
+    print(bool(x), \" \", bool(x - x), \" \", bool(nan), \" \", float(true), \" \", float(-7));
+    print(string(x) + \"!\", \" \", string(x / 100000.0), \" \", float(string(x / 3)) == x / 3);
+halt",
+    "init
+    string s;
+    s = \"+.5e-3\";
+    print(float(s), \" \", float(\"5.\"), \" \", float(\"-00012.50\"), \" \", float(\"1E3\"));
+    s = \"InFiNiTy\";
+    print(float(s), \" \", float(\"-inf\"), \" \", float(\"nan\"), \" \", float(\"-NaN\"));
+    s = \"1e400\";
+    print(float(s), \" \", float(\"-1e-400\"), \" \", float(\"0e999999999999\"), \" \", float(\"1e-99999999999\"));
+    s = \"2.4703282292062327e-324\";
+    print(float(s), \" \", float(\"2.4703282292062328e-324\"));
+    s = \"1.7976931348623158e308\";
+    print(float(s), \" \", float(\"1.7976931348623159e308\"));
+    s = \"0.1000000000000000055511151231257827021181583404541015625\";
+    print(float(s), \" \", float(\"9007199254740993\"), \" \", float(\"9007199254740993.00000000001\"));
+    // Shortest digits on the boundary of what reads back, which only counts for an even float.
+    s = \"1e23\";
+    print(float(s), \" \", float(\"100000000000000008388608\"), \" \", float(\"7e22\"), \" \", float(\"69999999999999995805696\"));
+halt",
+    "init
+    float x;
+    x = 10000000000.0;
+    print(int(x));
+halt",
+    "init
+    float x;
+    x = -2.0;
+    print(sqrt(x));
+halt",
+    "init
+    float x;
+    x = float(\"-1e20\");
+    print(sqrt(x / 3));
+halt",
+    "init
+    float x;
+    x = float(\"-1.5e-7\");
+    print(sqrt(x));
+halt",
+    "init
+    string s;
+    s = \"1e\";
+    print(float(s));
+halt",
+    "init
+    string s;
+    s = \" 1\";
+    print(float(s));
+halt",
+    "init
+    string s;
+    s = \"1.5.\";
+    print(float(s));
+halt",
+];
+
+// A xorshift generator, so that a failure can be reproduced.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+// Floats from random bits, read from strings and printed, and arithmetic on them.
+fn random_floats(rng: &mut Rng) -> String {
+    let mut source = String::from("init\n    float a, b;\n    string s;\n");
+    for _ in 0..60 {
+        let (a, b) = (f64::from_bits(rng.next()), f64::from_bits(rng.next()));
+        for x in [a, b] {
+            source.push_str(&format!("    s = \"{:e}\";\n    print(float(s));\n", x));
+        }
+        source.push_str(&format!(
+            "    a = float(\"{:e}\");\n    b = float(\"{:e}\");\n",
+            a, b
+        ));
+        source.push_str(
+            "    print(a + b, \" \", a - b, \" \", a * b, \" \", a / b, \" \", a % b, \" \", a mod b);\n",
+        );
+        source.push_str("    print(a div b, \" \", a < b, \" \", round(a), \" \", a ** 3);\n");
+    }
+    source + "halt\n"
+}
+
+// Strings of up to 1000 random digits, past the 800 the parser keeps.
+fn random_digits(rng: &mut Rng) -> String {
+    let mut source = String::from("init\n    string s;\n");
+    for _ in 0..80 {
+        let digits: String = (0..1 + rng.below(1000))
+            .map(|_| char::from(b'0' + rng.below(10) as u8))
+            .collect();
+        let point = rng.below(digits.len() as u64 + 1) as usize;
+        let exponent = rng.below(800) as i64 - 400;
+        source.push_str(&format!(
+            "    s = \"{}.{}e{}\";\n    print(float(s));\n",
+            &digits[..point],
+            &digits[point..],
+            exponent
+        ));
+    }
+    source + "halt\n"
+}
+
+// The exact decimal of (2m + 1) * 2^(e - 1), halfway between m * 2^e and the float
+// above it.
+fn halfway(m: u64, e: i32) -> String {
+    // Decimal digits, the least significant first.
+    let mut digits: Vec<u32> = (2 * m + 1)
+        .to_string()
+        .bytes()
+        .rev()
+        .map(|b| (b - b'0') as u32)
+        .collect();
+    let multiply = |digits: &mut Vec<u32>, by: u32| {
+        let mut carry = 0;
+        for digit in digits.iter_mut() {
+            let product = *digit * by + carry;
+            *digit = product % 10;
+            carry = product / 10;
+        }
+        while carry > 0 {
+            digits.push(carry % 10);
+            carry /= 10;
+        }
+    };
+    // Multiplying by 5^n is dividing 10^n by 2^n.
+    let fraction = (1 - e).max(0) as usize;
+    for _ in 0..fraction {
+        multiply(&mut digits, 5);
+    }
+    for _ in 0..(e - 1).max(0) {
+        multiply(&mut digits, 2);
+    }
+    while digits.len() <= fraction {
+        digits.push(0);
+    }
+    let text: String = digits
+        .iter()
+        .rev()
+        .map(|d| char::from(b'0' + *d as u8))
+        .collect();
+    format!(
+        "{}.{}",
+        &text[..text.len() - fraction],
+        &text[text.len() - fraction..]
+    )
+}
+
+// Numbers exactly halfway between two floats, which round to the even one, and just
+// above them, a nonzero digit after enough zeros to be past the 800 digits kept.
+fn halfway_floats(rng: &mut Rng) -> String {
+    let mut source = String::from("init\n    string s;\n");
+    for n in 0..40 {
+        let m = rng.below(1 << 53);
+        // Subnormals have the longest decimals, up to 767 significant digits.
+        let e = match n % 2 {
+            0 => rng.below(60) as i32 - 1074,
+            _ => rng.below(2098) as i32 - 1074,
+        };
+        let exact = halfway(m, e);
+        let zeros = "0".repeat(800);
+        for s in [exact.clone(), format!("{}{}1", exact, zeros)] {
+            source.push_str(&format!("    s = \"{}\";\n    print(float(s));\n", s));
+        }
+    }
+    source + "halt\n"
+}
+
+fn main() {
+    let mut paths: Vec<_> = fs::read_dir("src/test")
+        .expect("src/test exists")
+        .map(|entry| entry.expect("src/test is readable").path())
+        .filter(|path| path.extension().is_none())
+        .collect();
+    paths.sort();
+    let mut compiled = 0;
+    let mut failures = vec![];
+    for path in paths {
+        let source = fs::read_to_string(&path).expect("test programs are text");
+        compiled += compare(&path.display().to_string(), &source, &mut failures) as usize;
+    }
+    let mut rng = Rng(0x9E3779B97F4A7C15);
+    let generated = [
+        ("random floats".to_string(), random_floats(&mut rng)),
+        ("random digits".to_string(), random_digits(&mut rng)),
+        ("halfway floats".to_string(), halfway_floats(&mut rng)),
+    ];
+    let programs = FLOAT_PROGRAMS
+        .iter()
+        .enumerate()
+        .map(|(n, source)| (format!("float program {}", n + 1), source.to_string()))
+        .chain(generated);
+    for (name, source) in programs {
+        if !compare(&name, &source, &mut failures) {
+            failures.push(format!("{}: not compiled", name));
+        }
+    }
+    for failure in &failures {
+        println!("{}", failure);
+    }
+    assert!(compiled > 0, "No program in src/test was compiled.");
+    assert!(failures.is_empty(), "{} programs failed.", failures.len());
+}